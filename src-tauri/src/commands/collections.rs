@@ -0,0 +1,549 @@
+use tauri::command;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use rusqlite::{params, OptionalExtension};
+
+pub const COLLECTION_FILE_FORMAT: &str = "rh-mgr-collection";
+pub const COLLECTION_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub hack_count: u32,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Portable representation of a collection. Hacks are referenced by SMW Central
+/// ID and ROM fingerprint so the file can be imported into another library.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionFile {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub hacks: Vec<CollectionFileEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionFileEntry {
+    pub api_id: Option<String>,
+    pub name: String,
+    pub rom_md5: Option<String>,
+    pub rom_checksum: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionImportSummary {
+    pub collection: Collection,
+    pub matched: u32,
+    pub unmatched: Vec<String>,
+}
+
+#[command]
+pub fn get_collections(state: tauri::State<AppState>) -> Result<Vec<Collection>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    get_collections_impl(&conn)
+}
+
+#[command]
+pub fn create_collection(
+    state: tauri::State<AppState>,
+    name: String,
+    description: Option<String>,
+) -> Result<Collection, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    create_collection_impl(&conn, &name, description.as_deref())
+}
+
+#[command]
+pub fn update_collection(
+    state: tauri::State<AppState>,
+    id: u32,
+    name: String,
+    description: Option<String>,
+) -> Result<Collection, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    update_collection_impl(&conn, id, &name, description.as_deref())
+}
+
+#[command]
+pub fn delete_collection(state: tauri::State<AppState>, id: u32) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM collection_hacks WHERE collection_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM collections WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[command]
+pub fn add_hack_to_collection(
+    state: tauri::State<AppState>,
+    collection_id: u32,
+    hack_id: u32,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    add_hack_to_collection_impl(&conn, collection_id, hack_id).map(|_| ())
+}
+
+#[command]
+pub fn remove_hack_from_collection(
+    state: tauri::State<AppState>,
+    collection_id: u32,
+    hack_id: u32,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "DELETE FROM collection_hacks WHERE collection_id = ?1 AND hack_id = ?2",
+        params![collection_id, hack_id],
+    ).map_err(|e| e.to_string())?;
+    touch_collection(&conn, collection_id)?;
+
+    Ok(())
+}
+
+/// Replaces the order of a collection with `hack_ids`. Members missing from the
+/// list keep their relative order after the listed ones.
+#[command]
+pub fn reorder_collection(
+    state: tauri::State<AppState>,
+    collection_id: u32,
+    hack_ids: Vec<u32>,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    reorder_collection_impl(&conn, collection_id, &hack_ids)
+}
+
+#[command]
+pub fn export_collection(
+    state: tauri::State<AppState>,
+    id: u32,
+    path: String,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let file = export_collection_impl(&conn, id)?;
+
+    let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write collection file: {}", e))?;
+
+    Ok(())
+}
+
+#[command]
+pub fn import_collection(
+    state: tauri::State<AppState>,
+    path: String,
+) -> Result<CollectionImportSummary, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read collection file: {}", e))?;
+    let file: CollectionFile = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid collection file: {}", e))?;
+
+    import_collection_impl(&conn, file)
+}
+
+pub fn get_collections_impl(conn: &rusqlite::Connection) -> Result<Vec<Collection>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, c.description,
+                (SELECT COUNT(*) FROM collection_hacks ch WHERE ch.collection_id = c.id),
+                c.created_at, c.updated_at
+         FROM collections c ORDER BY c.name COLLATE NOCASE"
+    ).map_err(|e| e.to_string())?;
+
+    let collections = stmt.query_map([], collection_from_row).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    Ok(collections)
+}
+
+pub fn create_collection_impl(
+    conn: &rusqlite::Connection,
+    name: &str,
+    description: Option<&str>,
+) -> Result<Collection, String> {
+    let name = validate_name(name)?;
+    let now = now()?;
+
+    conn.execute(
+        "INSERT INTO collections (name, description, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+        params![name, description, now, now],
+    ).map_err(|e| name_conflict_error(e, &name))?;
+
+    get_collection(conn, conn.last_insert_rowid() as u32)
+}
+
+pub fn update_collection_impl(
+    conn: &rusqlite::Connection,
+    id: u32,
+    name: &str,
+    description: Option<&str>,
+) -> Result<Collection, String> {
+    let name = validate_name(name)?;
+
+    conn.execute(
+        "UPDATE collections SET name = ?1, description = ?2, updated_at = ?3 WHERE id = ?4",
+        params![name, description, now()?, id],
+    ).map_err(|e| name_conflict_error(e, &name))?;
+
+    get_collection(conn, id)
+}
+
+/// Returns whether the hack was added, i.e. wasn't in the collection already.
+pub fn add_hack_to_collection_impl(
+    conn: &rusqlite::Connection,
+    collection_id: u32,
+    hack_id: u32,
+) -> Result<bool, String> {
+    let exists = |query: &str, id: u32| {
+        conn.query_row(query, params![id], |row| row.get::<_, bool>(0)).map_err(|e| e.to_string())
+    };
+    if !exists("SELECT EXISTS(SELECT 1 FROM collections WHERE id = ?1)", collection_id)? {
+        return Err(format!("Collection {} not found", collection_id));
+    }
+    if !exists("SELECT EXISTS(SELECT 1 FROM hacks WHERE id = ?1)", hack_id)? {
+        return Err(format!("Hack {} not found", hack_id));
+    }
+
+    // New members go to the end; re-adding an existing member keeps its position
+    let added = conn.execute(
+        "INSERT OR IGNORE INTO collection_hacks (collection_id, hack_id, position, added_at)
+         VALUES (?1, ?2, (SELECT COALESCE(MAX(position) + 1, 0) FROM collection_hacks WHERE collection_id = ?1), ?3)",
+        params![collection_id, hack_id, now()?],
+    ).map_err(|e| e.to_string())?;
+    touch_collection(conn, collection_id)?;
+
+    Ok(added > 0)
+}
+
+pub fn reorder_collection_impl(
+    conn: &rusqlite::Connection,
+    collection_id: u32,
+    hack_ids: &[u32],
+) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT hack_id FROM collection_hacks WHERE collection_id = ?1 ORDER BY position"
+    ).map_err(|e| e.to_string())?;
+    let current = stmt.query_map(params![collection_id], |row| row.get::<_, u32>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    // Keep the first occurrence of IDs listed more than once
+    let mut seen = std::collections::HashSet::new();
+    let mut ordered: Vec<u32> = hack_ids.iter().copied()
+        .filter(|id| current.contains(id) && seen.insert(*id))
+        .collect();
+    ordered.extend(current.iter().copied().filter(|id| !hack_ids.contains(id)));
+
+    conn.execute("BEGIN TRANSACTION", []).map_err(|e| e.to_string())?;
+    for (position, hack_id) in ordered.iter().enumerate() {
+        if let Err(e) = conn.execute(
+            "UPDATE collection_hacks SET position = ?1 WHERE collection_id = ?2 AND hack_id = ?3",
+            params![position as i64, collection_id, hack_id],
+        ) {
+            let _ = conn.execute("ROLLBACK", []);
+            return Err(e.to_string());
+        }
+    }
+    conn.execute("COMMIT", []).map_err(|e| e.to_string())?;
+    touch_collection(conn, collection_id)?;
+
+    Ok(())
+}
+
+pub fn export_collection_impl(conn: &rusqlite::Connection, id: u32) -> Result<CollectionFile, String> {
+    let collection = get_collection(conn, id)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT h.api_id, h.name, h.rom_md5, h.rom_checksum
+         FROM collection_hacks ch JOIN hacks h ON h.id = ch.hack_id
+         WHERE ch.collection_id = ?1 ORDER BY ch.position"
+    ).map_err(|e| e.to_string())?;
+
+    let hacks = stmt.query_map(params![id], |row| {
+        Ok(CollectionFileEntry {
+            api_id: row.get(0)?,
+            name: row.get(1)?,
+            rom_md5: row.get::<_, Option<String>>(2)?.filter(|s| !s.is_empty()),
+            rom_checksum: row.get::<_, Option<String>>(3)?.filter(|s| !s.is_empty()),
+        })
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    Ok(CollectionFile {
+        format: COLLECTION_FILE_FORMAT.to_string(),
+        version: COLLECTION_FILE_VERSION,
+        name: collection.name,
+        description: collection.description,
+        hacks,
+    })
+}
+
+pub fn import_collection_impl(
+    conn: &rusqlite::Connection,
+    file: CollectionFile,
+) -> Result<CollectionImportSummary, String> {
+    if file.format != COLLECTION_FILE_FORMAT {
+        return Err(format!("Unsupported collection file format: {}", file.format));
+    }
+    if file.version > COLLECTION_FILE_VERSION {
+        return Err(format!("Collection file version {} is newer than supported", file.version));
+    }
+
+    // Never merge into an existing list; pick a free name instead
+    let base_name = validate_name(&file.name)?;
+    let mut name = base_name.clone();
+    let mut counter = 2;
+    while conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM collections WHERE name = ?1)",
+        params![name],
+        |row| row.get::<_, bool>(0),
+    ).map_err(|e| e.to_string())? {
+        name = format!("{} ({})", base_name, counter);
+        counter += 1;
+    }
+
+    // All or nothing, so a failure never leaves a half-imported list behind
+    conn.execute("BEGIN TRANSACTION", []).map_err(|e| e.to_string())?;
+    match import_members(conn, &name, file) {
+        Ok(summary) => {
+            conn.execute("COMMIT", []).map_err(|e| e.to_string())?;
+            Ok(summary)
+        }
+        Err(e) => {
+            let _ = conn.execute("ROLLBACK", []);
+            Err(e)
+        }
+    }
+}
+
+fn import_members(
+    conn: &rusqlite::Connection,
+    name: &str,
+    file: CollectionFile,
+) -> Result<CollectionImportSummary, String> {
+    let collection = create_collection_impl(conn, name, file.description.as_deref())?;
+
+    let mut matched = 0;
+    let mut unmatched = Vec::new();
+    for entry in file.hacks {
+        match find_hack(conn, &entry)? {
+            // Entries resolving to a hack that's already in the list count once
+            Some(hack_id) => {
+                if add_hack_to_collection_impl(conn, collection.id, hack_id)? {
+                    matched += 1;
+                }
+            }
+            None => unmatched.push(entry.name),
+        }
+    }
+
+    Ok(CollectionImportSummary {
+        collection: get_collection(conn, collection.id)?,
+        matched,
+        unmatched,
+    })
+}
+
+/// Resolves a collection file entry by SMW Central ID, then by ROM fingerprint.
+/// The 16-bit header checksum is shared by unrelated hacks (many keep vanilla's),
+/// so it only counts when exactly one hack has it.
+fn find_hack(conn: &rusqlite::Connection, entry: &CollectionFileEntry) -> Result<Option<u32>, String> {
+    let lookups = [
+        ("SELECT id FROM hacks WHERE api_id = ?1", &entry.api_id),
+        ("SELECT id FROM hacks WHERE rom_md5 = ?1", &entry.rom_md5),
+    ];
+
+    for (query, value) in lookups {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            let id = conn.query_row(query, params![value], |row| row.get(0))
+                .optional().map_err(|e| e.to_string())?;
+            if id.is_some() {
+                return Ok(id);
+            }
+        }
+    }
+
+    if let Some(checksum) = entry.rom_checksum.as_deref().filter(|v| !v.is_empty()) {
+        let (id, count): (Option<u32>, u32) = conn.query_row(
+            "SELECT MIN(id), COUNT(*) FROM hacks WHERE rom_checksum = ?1",
+            params![checksum],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| e.to_string())?;
+        if count == 1 {
+            return Ok(id);
+        }
+    }
+
+    Ok(None)
+}
+
+fn get_collection(conn: &rusqlite::Connection, id: u32) -> Result<Collection, String> {
+    conn.query_row(
+        "SELECT c.id, c.name, c.description,
+                (SELECT COUNT(*) FROM collection_hacks ch WHERE ch.collection_id = c.id),
+                c.created_at, c.updated_at
+         FROM collections c WHERE c.id = ?1",
+        params![id],
+        collection_from_row,
+    ).map_err(|e| e.to_string())
+}
+
+fn collection_from_row(row: &rusqlite::Row) -> rusqlite::Result<Collection> {
+    Ok(Collection {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        hack_count: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn touch_collection(conn: &rusqlite::Connection, id: u32) -> Result<(), String> {
+    conn.execute(
+        "UPDATE collections SET updated_at = ?1 WHERE id = ?2",
+        params![now()?, id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn name_conflict_error(e: rusqlite::Error, name: &str) -> String {
+    match e {
+        rusqlite::Error::SqliteFailure(err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            format!("A collection named '{}' already exists", name)
+        }
+        other => other.to_string(),
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err("Collection name cannot be empty".to_string());
+    }
+    Ok(trimmed.to_string())
+}
+
+fn now() -> Result<i64, String> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use rusqlite::Connection;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("
+            INSERT INTO hacks (name, api_id, rom_md5) VALUES
+            ('Kaizo Mario World', '1', 'aaa'),
+            ('Invictus', '4', 'bbb'),
+            ('Quickie World', '5', NULL)
+        ", []).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_collection_membership_and_order() {
+        let conn = setup();
+        let collection = create_collection_impl(&conn, "Kaizo practice rotation", None).unwrap();
+
+        add_hack_to_collection_impl(&conn, collection.id, 1).unwrap();
+        add_hack_to_collection_impl(&conn, collection.id, 2).unwrap();
+        add_hack_to_collection_impl(&conn, collection.id, 3).unwrap();
+        assert!(!add_hack_to_collection_impl(&conn, collection.id, 1).unwrap()); // no duplicate
+        assert_eq!(add_hack_to_collection_impl(&conn, collection.id, 99).unwrap_err(), "Hack 99 not found");
+        assert_eq!(add_hack_to_collection_impl(&conn, 42, 1).unwrap_err(), "Collection 42 not found");
+
+        reorder_collection_impl(&conn, collection.id, &[3, 1, 3]).unwrap();
+
+        let file = export_collection_impl(&conn, collection.id).unwrap();
+        let names: Vec<&str> = file.hacks.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["Quickie World", "Kaizo Mario World", "Invictus"]);
+        let positions: Vec<i64> = conn.prepare("SELECT position FROM collection_hacks ORDER BY position").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(positions, vec![0, 1, 2]);
+
+        assert!(create_collection_impl(&conn, " Kaizo practice rotation ", None).is_err());
+        let other = create_collection_impl(&conn, "Other", None).unwrap();
+        let err = update_collection_impl(&conn, other.id, "Kaizo practice rotation", None).unwrap_err();
+        assert_eq!(err, "A collection named 'Kaizo practice rotation' already exists");
+    }
+
+    #[test]
+    fn test_import_matches_by_api_id_then_fingerprint() {
+        let conn = setup();
+
+        let file = CollectionFile {
+            format: COLLECTION_FILE_FORMAT.to_string(),
+            version: COLLECTION_FILE_VERSION,
+            name: "Contest 2026 entries".to_string(),
+            description: None,
+            hacks: vec![
+                CollectionFileEntry { api_id: Some("5".to_string()), name: "Quickie World".to_string(), rom_md5: None, rom_checksum: None },
+                CollectionFileEntry { api_id: Some("999".to_string()), name: "Local Invictus".to_string(), rom_md5: Some("bbb".to_string()), rom_checksum: None },
+                CollectionFileEntry { api_id: None, name: "Unknown Hack".to_string(), rom_md5: Some("zzz".to_string()), rom_checksum: None },
+                CollectionFileEntry { api_id: Some("4".to_string()), name: "Invictus again".to_string(), rom_md5: None, rom_checksum: None },
+            ],
+        };
+
+        let summary = import_collection_impl(&conn, file).unwrap();
+        assert_eq!(summary.matched, 2);
+        assert_eq!(summary.unmatched, vec!["Unknown Hack".to_string()]);
+        assert_eq!(summary.collection.hack_count, 2);
+
+        let exported = export_collection_impl(&conn, summary.collection.id).unwrap();
+        assert_eq!(exported.hacks[1].api_id.as_deref(), Some("4"));
+
+        // Importing again creates a separate, renamed collection
+        let again = import_collection_impl(&conn, exported).unwrap();
+        assert_eq!(again.collection.name, "Contest 2026 entries (2)");
+    }
+
+    #[test]
+    fn test_import_ignores_shared_checksums_and_rolls_back_on_error() {
+        let conn = setup();
+        conn.execute("UPDATE hacks SET rom_checksum = 'A0DA' WHERE id IN (1, 2)", []).unwrap();
+        conn.execute("UPDATE hacks SET rom_checksum = '1234' WHERE id = 3", []).unwrap();
+        let entry = |name: &str, checksum: &str| CollectionFileEntry {
+            api_id: None,
+            name: name.to_string(),
+            rom_md5: None,
+            rom_checksum: Some(checksum.to_string()),
+        };
+        let file = || CollectionFile {
+            format: COLLECTION_FILE_FORMAT.to_string(),
+            version: COLLECTION_FILE_VERSION,
+            name: "Checksums".to_string(),
+            description: None,
+            hacks: vec![entry("Vanilla checksum", "A0DA"), entry("Unique checksum", "1234")],
+        };
+
+        let summary = import_collection_impl(&conn, file()).unwrap();
+        assert_eq!(summary.matched, 1);
+        assert_eq!(summary.unmatched, vec!["Vanilla checksum".to_string()]);
+
+        conn.execute(
+            "CREATE TEMP TRIGGER fail_member BEFORE INSERT ON collection_hacks
+             BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+            [],
+        ).unwrap();
+        assert!(import_collection_impl(&conn, file()).is_err());
+        assert_eq!(get_collections_impl(&conn).unwrap().len(), 1, "the failed import leaves nothing behind");
+    }
+}
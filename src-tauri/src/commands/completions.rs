@@ -1,7 +1,7 @@
 use tauri::command;
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
-use rusqlite::params;
+use rusqlite::{params, Connection};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HackCompletion {
@@ -29,6 +29,18 @@ pub struct UpdateCompletion {
     pub play_time_seconds: Option<i32>,
 }
 
+/// A completion detected by the tracker when the credits rolled, awaiting confirmation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingCompletion {
+    pub id: u32,
+    pub hack_id: u32,
+    pub session_id: Option<i64>,
+    pub route: String,
+    pub completed_at: i64,  // UNIX timestamp in seconds
+    pub play_time_seconds: Option<i32>,
+    pub exits_found: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompletionSummary {
     pub total_completions: u32,
@@ -215,3 +227,157 @@ pub fn get_completion_summary(
     })
 }
 
+#[command]
+pub fn get_pending_completions(
+    state: tauri::State<AppState>,
+    hack_id: Option<u32>,
+) -> Result<Vec<PendingCompletion>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    get_pending_completions_impl(&conn, hack_id)
+}
+
+pub fn get_pending_completions_impl(conn: &Connection, hack_id: Option<u32>) -> Result<Vec<PendingCompletion>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, hack_id, session_id, route, completed_at, play_time_seconds, exits_found
+         FROM pending_completions WHERE ?1 IS NULL OR hack_id = ?1 ORDER BY completed_at DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let pending = stmt.query_map(
+        params![hack_id],
+        |row| {
+            Ok(PendingCompletion {
+                id: row.get(0)?,
+                hack_id: row.get(1)?,
+                session_id: row.get(2)?,
+                route: row.get(3)?,
+                completed_at: row.get(4)?,
+                play_time_seconds: row.get(5)?,
+                exits_found: row.get(6)?,
+            })
+        }
+    ).map_err(|e| e.to_string())?;
+
+    pending.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Records a detected completion, optionally under a route the user picked instead of the
+/// inferred one. An existing completion of the same route is updated with the new run.
+#[command]
+pub fn confirm_pending_completion(
+    state: tauri::State<AppState>,
+    id: u32,
+    route: Option<String>,
+) -> Result<HackCompletion, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    confirm_pending_completion_impl(&conn, id, route)
+}
+
+pub fn confirm_pending_completion_impl(conn: &Connection, id: u32, route: Option<String>) -> Result<HackCompletion, String> {
+    let (hack_id, inferred_route, completed_at, play_time_seconds): (u32, String, i64, Option<i32>) = conn.query_row(
+        "SELECT hack_id, route, completed_at, play_time_seconds FROM pending_completions WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).map_err(|_| format!("Pending completion {} not found", id))?;
+
+    let route = route
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .unwrap_or(inferred_route);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs() as i64;
+
+    let completion = conn.query_row(
+        "INSERT INTO hack_completions (hack_id, route, completed_at, play_time_seconds, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+         ON CONFLICT(hack_id, route) DO UPDATE SET
+         completed_at = excluded.completed_at,
+         play_time_seconds = excluded.play_time_seconds,
+         updated_at = excluded.updated_at
+         RETURNING id, hack_id, route, completed_at, play_time_seconds, created_at, updated_at",
+        params![hack_id, route, completed_at, play_time_seconds, now],
+        |row| {
+            Ok(HackCompletion {
+                id: row.get(0)?,
+                hack_id: row.get(1)?,
+                route: row.get(2)?,
+                completed_at: row.get(3)?,
+                play_time_seconds: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        }
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute("DELETE FROM pending_completions WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(completion)
+}
+
+#[command]
+pub fn dismiss_pending_completion(
+    state: tauri::State<AppState>,
+    id: u32,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "DELETE FROM pending_completions WHERE id = ?1",
+        params![id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name, total_exits) VALUES ('Test Hack', 12)", []).unwrap();
+        conn.execute(
+            "INSERT INTO pending_completions (hack_id, route, completed_at, play_time_seconds, exits_found)
+             VALUES (1, 'any%', 1767225600, 5400, 7)",
+            [],
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_confirm_pending_completion() {
+        let conn = setup();
+        assert_eq!(get_pending_completions_impl(&conn, Some(1)).unwrap().len(), 1);
+        assert!(get_pending_completions_impl(&conn, Some(2)).unwrap().is_empty());
+
+        let completion = confirm_pending_completion_impl(&conn, 1, None).unwrap();
+        assert_eq!(completion.route, "any%");
+        assert_eq!(completion.completed_at, Some(1767225600));
+        assert_eq!(completion.play_time_seconds, Some(5400));
+        assert!(get_pending_completions_impl(&conn, None).unwrap().is_empty());
+        assert!(confirm_pending_completion_impl(&conn, 1, None).is_err());
+    }
+
+    #[test]
+    fn test_confirm_updates_existing_route() {
+        let conn = setup();
+        conn.execute(
+            "INSERT INTO hack_completions (hack_id, route, completed_at, play_time_seconds, created_at, updated_at)
+             VALUES (1, 'no cape', 1700000000, 9000, 1700000000, 1700000000)",
+            [],
+        ).unwrap();
+
+        let completion = confirm_pending_completion_impl(&conn, 1, Some(" no cape ".to_string())).unwrap();
+        assert_eq!(completion.id, 1);
+        assert_eq!(completion.completed_at, Some(1767225600));
+        assert_eq!(completion.created_at, 1700000000);
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM hack_completions", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+}
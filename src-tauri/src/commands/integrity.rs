@@ -0,0 +1,395 @@
+use tauri::{command, AppHandle, Manager};
+use crate::commands::patch::{
+    cached_patch_path, is_output_taken, naming_fields_from_row, resolve_clean_rom_path, resolve_output_dir,
+};
+use crate::commands::saves::{backup_root, backup_save_impl, REASON_REPATCH};
+use crate::config::Config;
+use crate::domain::rom::RomValidator;
+use crate::patching::naming::{render_output_path, resolve_collision, DEFAULT_OUTPUT_TEMPLATE};
+use crate::patching::Patcher;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use rusqlite::params;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct HackIntegrity {
+    pub hack_id: i64,
+    pub name: String,
+    pub file_path: String,
+    pub status: String, // "ok", "missing", "modified", "unverified"
+    pub relink_candidate: Option<String>,
+    pub can_repatch: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub checked: u32,
+    pub ok: u32,
+    pub missing: u32,
+    pub modified: u32,
+    pub unverified: u32,
+    pub issues: Vec<HackIntegrity>,
+    pub orphans: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RepairRequest {
+    pub hack_id: i64,
+    pub action: String, // "relink", "repatch", "clear"
+    pub path: Option<String>, // Required for "relink"
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairResult {
+    pub hack_id: i64,
+    pub action: String,
+    pub success: bool,
+    pub file_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairSummary {
+    pub repaired: u32,
+    pub failed: u32,
+    pub results: Vec<RepairResult>,
+}
+
+/// Checks every patched hack against its stored fingerprint and lists ROMs in the
+/// output directory that no hack refers to.
+#[command]
+pub fn verify_library(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<IntegrityReport, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let config = Config::load(&conn).map_err(|e| e.to_string())?;
+    let output_dir = resolve_output_dir(&app, &config)?;
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+
+    verify_library_impl(&conn, &output_dir, Some(&app_data_dir))
+}
+
+#[command]
+pub fn repair_library(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    repairs: Vec<RepairRequest>,
+) -> Result<RepairSummary, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let config = Config::load(&conn).map_err(|e| e.to_string())?;
+
+    let mut results = Vec::new();
+    for repair in repairs {
+        let outcome = match repair.action.as_str() {
+            "relink" => match &repair.path {
+                Some(path) => relink_hack(&conn, repair.hack_id, Path::new(path)),
+                None => Err("A file path is required to relink a hack".to_string()),
+            },
+            "repatch" => repatch_hack(&app, &conn, &config, repair.hack_id),
+            "clear" => clear_hack_path(&conn, repair.hack_id).map(|_| None),
+            other => Err(format!("Unknown repair action: {}", other)),
+        };
+
+        results.push(match outcome {
+            Ok(file_path) => RepairResult {
+                hack_id: repair.hack_id,
+                action: repair.action,
+                success: true,
+                file_path,
+                error: None,
+            },
+            Err(e) => RepairResult {
+                hack_id: repair.hack_id,
+                action: repair.action,
+                success: false,
+                file_path: None,
+                error: Some(e),
+            },
+        });
+    }
+
+    let repaired = results.iter().filter(|r| r.success).count() as u32;
+    Ok(RepairSummary {
+        repaired,
+        failed: results.len() as u32 - repaired,
+        results,
+    })
+}
+
+pub fn verify_library_impl(
+    conn: &rusqlite::Connection,
+    output_dir: &Path,
+    app_data_dir: Option<&Path>,
+) -> Result<IntegrityReport, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, file_path, api_id, rom_md5, rom_checksum FROM hacks WHERE file_path IS NOT NULL ORDER BY name"
+    ).map_err(|e| e.to_string())?;
+
+    let hacks = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, Option<String>>(5)?,
+        ))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    let referenced: Vec<PathBuf> = hacks.iter().map(|h| PathBuf::from(&h.2)).collect();
+    let mut orphans = Vec::new();
+    collect_roms(output_dir, &mut orphans);
+    orphans.retain(|path| !referenced.iter().any(|r| r == path));
+
+    // Fingerprint orphans once so they can be offered as relink targets
+    let orphan_fingerprints: Vec<(PathBuf, String, String)> = orphans
+        .iter()
+        .filter_map(|path| {
+            let content = fs::read(path).ok()?;
+            let (md5, checksum) = RomValidator::fingerprint(&content);
+            Some((path.clone(), md5, checksum))
+        })
+        .collect();
+
+    let mut report = IntegrityReport {
+        checked: 0,
+        ok: 0,
+        missing: 0,
+        modified: 0,
+        unverified: 0,
+        issues: Vec::new(),
+        orphans: orphans.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+    };
+
+    for (hack_id, name, file_path, api_id, rom_md5, rom_checksum) in hacks {
+        report.checked += 1;
+        let rom_md5 = rom_md5.filter(|s| !s.is_empty());
+        let rom_checksum = rom_checksum.filter(|s| !s.is_empty());
+
+        let status = match fs::read(&file_path) {
+            Err(_) => "missing",
+            Ok(content) => {
+                let (md5, checksum) = RomValidator::fingerprint(&content);
+                match (&rom_md5, &rom_checksum) {
+                    (Some(expected), _) if *expected != md5 => "modified",
+                    (Some(_), _) => "ok",
+                    (None, Some(expected)) if *expected != checksum => "modified",
+                    (None, Some(_)) => "ok",
+                    (None, None) => "unverified",
+                }
+            }
+        };
+
+        match status {
+            "ok" => { report.ok += 1; continue; }
+            "missing" => report.missing += 1,
+            "modified" => report.modified += 1,
+            _ => report.unverified += 1,
+        }
+
+        // Prefer an exact content match; fall back to the header checksum only if it is unambiguous
+        let relink_candidate = if status == "missing" {
+            let by_md5 = rom_md5.as_ref().and_then(|expected| {
+                orphan_fingerprints.iter().find(|(_, md5, _)| md5 == expected)
+            });
+            let by_checksum = || {
+                let expected = rom_checksum.as_ref()?;
+                let mut matches = orphan_fingerprints.iter().filter(|(_, _, sum)| sum == expected);
+                let first = matches.next()?;
+                if matches.next().is_none() { Some(first) } else { None }
+            };
+            by_md5.or_else(by_checksum).map(|(path, _, _)| path.to_string_lossy().to_string())
+        } else {
+            None
+        };
+
+        let can_repatch = match (app_data_dir, &api_id) {
+            (Some(dir), Some(api_id)) => cached_patch_path(dir, api_id).is_some(),
+            _ => false,
+        };
+
+        report.issues.push(HackIntegrity {
+            hack_id,
+            name,
+            file_path,
+            status: status.to_string(),
+            relink_candidate,
+            can_repatch,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Points a hack at a different ROM file, refusing files that contradict its stored fingerprint.
+pub fn relink_hack(conn: &rusqlite::Connection, hack_id: i64, path: &Path) -> Result<Option<String>, String> {
+    let content = fs::read(path).map_err(|e| format!("Failed to read ROM: {}", e))?;
+    let (md5, checksum) = RomValidator::fingerprint(&content);
+
+    let (expected_md5, expected_checksum): (Option<String>, Option<String>) = conn.query_row(
+        "SELECT rom_md5, rom_checksum FROM hacks WHERE id = ?1",
+        params![hack_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| e.to_string())?;
+
+    let mismatch = match (expected_md5.filter(|s| !s.is_empty()), expected_checksum.filter(|s| !s.is_empty())) {
+        (Some(expected), _) => expected != md5,
+        (None, Some(expected)) => expected != checksum,
+        (None, None) => false,
+    };
+    if mismatch {
+        return Err(format!("{} does not match the fingerprint of this hack", path.display()));
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+    conn.execute(
+        "UPDATE hacks SET file_path = ?1, rom_md5 = ?2, rom_checksum = ?3 WHERE id = ?4",
+        params![path_str, md5, checksum, hack_id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(Some(path_str))
+}
+
+fn repatch_hack(
+    app: &AppHandle,
+    conn: &rusqlite::Connection,
+    config: &Config,
+    hack_id: i64,
+) -> Result<Option<String>, String> {
+    let (fields, current_path) = conn.query_row(
+        "SELECT id, name, api_id, difficulty, type, authors, file_path FROM hacks WHERE id = ?1",
+        params![hack_id],
+        |row| Ok((naming_fields_from_row(row)?, row.get::<_, Option<String>>(6)?)),
+    ).map_err(|e| e.to_string())?;
+
+    let api_id = fields.api_id.clone().ok_or_else(|| "Hack has no SMW Central ID".to_string())?;
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let patch = cached_patch_path(&app_data_dir, &api_id)
+        .ok_or_else(|| "No cached patch available; patch the hack again from SMW Central".to_string())?;
+    let clean_rom_path = resolve_clean_rom_path(app, config)?;
+
+    // Rebuild in place when the old location is known, otherwise follow the naming template
+    let output_path = match current_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let output_dir = resolve_output_dir(app, config)?;
+            let template = config.output_name_template.as_deref().unwrap_or(DEFAULT_OUTPUT_TEMPLATE);
+            let target = output_dir.join(render_output_path(template, &fields)?);
+            resolve_collision(&target, |candidate| is_output_taken(conn, candidate, None))
+        }
+    };
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+
+    // The rebuilt ROM replaces the one the save belongs to, so keep a copy first
+    backup_save_impl(conn, &backup_root(app)?, hack_id, REASON_REPATCH)?;
+    Patcher::patch_bps(&clean_rom_path, &patch, &output_path)?;
+
+    let content = fs::read(&output_path).map_err(|e| format!("Failed to read patched ROM: {}", e))?;
+    let (md5, checksum) = RomValidator::fingerprint(&content);
+    let path_str = output_path.to_string_lossy().to_string();
+    conn.execute(
+        "UPDATE hacks SET file_path = ?1, rom_md5 = ?2, rom_checksum = ?3 WHERE id = ?4",
+        params![path_str, md5, checksum, hack_id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(Some(path_str))
+}
+
+fn clear_hack_path(conn: &rusqlite::Connection, hack_id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE hacks SET file_path = NULL WHERE id = ?1",
+        params![hack_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn collect_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_roms(&path, roms);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("sfc") || e.eq_ignore_ascii_case("smc"))
+            .unwrap_or(false)
+        {
+            roms.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+
+    fn insert_patched(conn: &Connection, name: &str, path: &Path, content: &[u8]) -> i64 {
+        let (md5, checksum) = RomValidator::fingerprint(content);
+        conn.execute(
+            "INSERT INTO hacks (name, file_path, rom_md5, rom_checksum) VALUES (?1, ?2, ?3, ?4)",
+            params![name, path.to_string_lossy(), md5, checksum],
+        ).unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn test_verify_library_reports_missing_modified_and_orphans() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path();
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+
+        let ok_path = output_dir.join("Ok.sfc");
+        fs::write(&ok_path, b"ok rom").unwrap();
+        insert_patched(&conn, "Ok", &ok_path, b"ok rom");
+
+        let modified_path = output_dir.join("Modified.sfc");
+        fs::write(&modified_path, b"changed").unwrap();
+        insert_patched(&conn, "Modified", &modified_path, b"original");
+
+        // The missing hack's ROM was moved into a subfolder
+        let missing_path = output_dir.join("Missing.sfc");
+        let moved_path = output_dir.join("sub").join("Moved.sfc");
+        fs::create_dir_all(moved_path.parent().unwrap()).unwrap();
+        fs::write(&moved_path, b"missing rom").unwrap();
+        let missing_id = insert_patched(&conn, "Missing", &missing_path, b"missing rom");
+
+        let report = verify_library_impl(&conn, output_dir, None).unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.ok, 1);
+        assert_eq!(report.modified, 1);
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.orphans, vec![moved_path.to_string_lossy().to_string()]);
+
+        let missing = report.issues.iter().find(|i| i.hack_id == missing_id).unwrap();
+        assert_eq!(missing.relink_candidate.as_deref(), Some(moved_path.to_string_lossy().as_ref()));
+
+        relink_hack(&conn, missing_id, &moved_path).unwrap();
+        let report = verify_library_impl(&conn, output_dir, None).unwrap();
+        assert_eq!(report.ok, 2);
+        assert!(report.orphans.is_empty());
+    }
+
+    #[test]
+    fn test_relink_rejects_mismatched_rom() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+
+        let other = temp_dir.path().join("Other.sfc");
+        fs::write(&other, b"other rom").unwrap();
+        let id = insert_patched(&conn, "Hack", &temp_dir.path().join("Hack.sfc"), b"hack rom");
+
+        assert!(relink_hack(&conn, id, &other).is_err());
+    }
+}
@@ -4,6 +4,10 @@ use crate::config::Config;
 use std::process::Command;
 use std::path::Path;
 use log::{info, debug, error};
+use serde::Serialize;
+use crate::tracking::hardware::{remote_rom_path, DEFAULT_HARDWARE_ROM_FOLDER};
+use crate::tracking::livesplit::parse_split_rules;
+use crate::tracking::service::{SessionEnd, MEMORY_SOURCE_RETROARCH, MEMORY_SOURCE_USB2SNES};
 #[cfg(target_os = "macos")]
 use std::path::PathBuf;
 
@@ -90,15 +94,25 @@ pub fn launch_hack(
     ).optional().map_err(|e| e.to_string())?;
 
     if let Some(id) = hack_id {
-        let tracking = state.tracking.clone();
-        tauri::async_runtime::spawn(async move {
-            tracking.set_active_hack(id).await;
-        });
+        let _ = conn.execute(
+            "UPDATE hacks SET last_played = ?1 WHERE id = ?2",
+            rusqlite::params![chrono::Utc::now().to_rfc3339(), id],
+        );
     }
 
     match cmd.spawn() {
-        Ok(_) => {
-            info!("Emulator launched successfully");
+        Ok(child) => {
+            info!("Emulator launched successfully (pid {})", child.id());
+            // Attribute tracking to this hack until the emulator exits
+            let tracking = state.tracking.clone();
+            tauri::async_runtime::spawn(async move {
+                match hack_id {
+                    Some(id) => tracking.set_active_hack(id).await,
+                    // Unknown ROM: leave it to auto-detection rather than the previous hack
+                    None => tracking.end_session(SessionEnd::HackChanged).await,
+                }
+                tracking.supervise_emulator(child).await;
+            });
             Ok(())
         },
         Err(e) => {
@@ -108,7 +122,75 @@ pub fn launch_hack(
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct HardwareLaunch {
+    pub remote_path: String,
+    pub uploaded: bool,
+}
+
+/// Uploads a patched hack to the connected cart's SD card and boots it.
+#[command]
+pub async fn launch_hack_on_hardware(
+    state: tauri::State<'_, AppState>,
+    hack_id: i64,
+) -> Result<HardwareLaunch, String> {
+    let (file_path, remote_path, recorded_md5) = {
+        let conn = state.db.get().map_err(|e| e.to_string())?;
+        let config = Config::load(&conn).map_err(|e| e.to_string())?;
+        let file_path: Option<String> = conn.query_row(
+            "SELECT file_path FROM hacks WHERE id = ?1",
+            [hack_id],
+            |row| row.get(0),
+        ).map_err(|e| format!("Hack not found: {}", e))?;
+        let file_path = file_path.ok_or("Hack has not been patched yet")?;
+        let folder = config.hardware_rom_folder
+            .unwrap_or_else(|| DEFAULT_HARDWARE_ROM_FOLDER.to_string());
+        let file_name = Path::new(&file_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or("Invalid ROM file path")?;
+        let remote_path = remote_rom_path(&folder, &file_name);
+
+        use rusqlite::OptionalExtension;
+        let recorded_md5: Option<String> = conn.query_row(
+            "SELECT rom_md5 FROM hardware_uploads WHERE remote_path = ?1",
+            [&remote_path],
+            |row| row.get(0),
+        ).optional().map_err(|e| e.to_string())?;
+        (file_path, remote_path, recorded_md5)
+    };
+
+    let data = std::fs::read(&file_path)
+        .map_err(|e| format!("Failed to read ROM {}: {}", file_path, e))?;
+    let md5 = format!("{:x}", md5::compute(&data));
+    let already_uploaded = recorded_md5.as_deref() == Some(md5.as_str());
+
+    info!("Launching hack {} on hardware at {}", hack_id, remote_path);
+    let uploaded = state.tracking.boot_on_hardware(&data, &remote_path, already_uploaded).await?;
+
+    {
+        let conn = state.db.get().map_err(|e| e.to_string())?;
+        let now = chrono::Utc::now().to_rfc3339();
+        if uploaded {
+            conn.execute(
+                "INSERT OR REPLACE INTO hardware_uploads (remote_path, hack_id, rom_md5, size, uploaded_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![remote_path, hack_id, md5, data.len() as i64, now],
+            ).map_err(|e| e.to_string())?;
+        }
+        let _ = conn.execute(
+            "UPDATE hacks SET last_played = ?1 WHERE id = ?2",
+            rusqlite::params![now, hack_id],
+        );
+    }
+
+    state.tracking.set_active_hack(hack_id).await;
+
+    Ok(HardwareLaunch { remote_path, uploaded })
+}
+
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub fn save_config(
     state: tauri::State<AppState>,
     emulator_path: String,
@@ -117,8 +199,27 @@ pub fn save_config(
     enable_debug_logging: bool,
     enable_auto_tracking: bool,
     additional_args: String,
+    output_name_template: Option<String>,
+    idle_timeout_seconds: Option<u32>,
+    hardware_rom_folder: Option<String>,
+    usb2snes_endpoints: Option<String>,
+    memory_source: Option<String>,
+    retroarch_address: Option<String>,
+    livesplit_enabled: Option<bool>,
+    livesplit_address: Option<String>,
+    livesplit_splits: Option<String>,
 ) -> Result<(), String> {
     let conn = state.db.get().map_err(|e| e.to_string())?;
+    let existing = Config::load(&conn).map_err(|e| e.to_string())?;
+
+    if let Some(source) = &memory_source {
+        if source != MEMORY_SOURCE_USB2SNES && source != MEMORY_SOURCE_RETROARCH {
+            return Err(format!("Unknown memory source: {}", source));
+        }
+    }
+    if let Some(splits) = &livesplit_splits {
+        parse_split_rules(splits)?;
+    }
     
     // Convert empty strings to None, but preserve non-empty strings
     let config = Config {
@@ -156,6 +257,78 @@ pub fn save_config(
                 Some(trimmed.to_string())
             }
         },
+        // Optional settings are left unchanged when the caller doesn't send them
+        output_name_template: match output_name_template {
+            Some(template) => {
+                let trimmed = template.trim();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed.to_string())
+                }
+            }
+            None => existing.output_name_template,
+        },
+        idle_timeout_seconds: idle_timeout_seconds.or(existing.idle_timeout_seconds),
+        hardware_rom_folder: match hardware_rom_folder {
+            Some(folder) => {
+                let trimmed = folder.trim();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed.to_string())
+                }
+            }
+            None => existing.hardware_rom_folder,
+        },
+        usb2snes_endpoints: match usb2snes_endpoints {
+            Some(endpoints) => {
+                let trimmed = endpoints.trim();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed.to_string())
+                }
+            }
+            None => existing.usb2snes_endpoints,
+        },
+        // Chosen from the device picker via `set_usb2snes_device`
+        usb2snes_device: existing.usb2snes_device,
+        memory_source: memory_source.or(existing.memory_source),
+        retroarch_address: match retroarch_address {
+            Some(address) => {
+                let trimmed = address.trim();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed.to_string())
+                }
+            }
+            None => existing.retroarch_address,
+        },
+        livesplit_enabled: livesplit_enabled.or(existing.livesplit_enabled),
+        livesplit_address: match livesplit_address {
+            Some(address) => {
+                let trimmed = address.trim();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed.to_string())
+                }
+            }
+            None => existing.livesplit_address,
+        },
+        livesplit_splits: match livesplit_splits {
+            Some(splits) => {
+                let trimmed = splits.trim();
+                if trimmed.is_empty() {
+                    None
+                } else {
+                    Some(trimmed.to_string())
+                }
+            }
+            None => existing.livesplit_splits,
+        },
     };
     
     config.save(&conn).map_err(|e| e.to_string())?;
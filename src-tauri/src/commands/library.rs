@@ -1,5 +1,7 @@
-use tauri::command;
+use tauri::{command, AppHandle};
+use crate::commands::saves::{backup_root, backup_save_impl, REASON_DELETE};
 use crate::state::AppState;
+use crate::tracking::sram::srm_path;
 use serde::{Deserialize, Serialize};
 use rusqlite::params;
 
@@ -20,9 +22,47 @@ pub struct Hack {
     pub hack_type: Option<String>, // Using hack_type to avoid Rust keyword conflict
     pub download_url: Option<String>,
     pub readme: Option<String>,
+    pub last_played: Option<String>,
+    // User metadata from `user_hack_data`
+    pub play_status: Option<String>,
+    pub personal_rating: Option<f64>,
+    pub notes: Option<String>,
+    pub favorite: bool,
+    pub user_tags: Option<String>, // JSON array
 }
 
-#[derive(Debug, Deserialize)]
+const HACK_COLUMNS: &str = "id, name, file_path, api_id, authors, release_date, description, images, tags, rating, downloads, difficulty, type, download_url, readme, last_played,
+         play_status, personal_rating, notes, favorite, user_tags";
+
+const HACK_FROM: &str = "hacks LEFT JOIN user_hack_data ON user_hack_data.hack_id = hacks.id";
+
+fn hack_from_row(row: &rusqlite::Row) -> rusqlite::Result<Hack> {
+    Ok(Hack {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        file_path: row.get(2)?,
+        api_id: row.get(3)?,
+        authors: row.get(4)?,
+        release_date: row.get(5)?,
+        description: row.get(6)?,
+        images: row.get(7)?,
+        tags: row.get(8)?,
+        rating: row.get(9)?,
+        downloads: row.get(10)?,
+        difficulty: row.get(11)?,
+        hack_type: row.get(12)?,
+        download_url: row.get(13)?,
+        readme: row.get(14)?,
+        last_played: row.get(15)?,
+        play_status: row.get(16)?,
+        personal_rating: row.get(17)?,
+        notes: row.get(18)?,
+        favorite: row.get::<_, Option<bool>>(19)?.unwrap_or(false),
+        user_tags: row.get(20)?,
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct HackFilters {
     pub patched_only: Option<bool>,
     pub unpatched_only: Option<bool>,
@@ -34,6 +74,44 @@ pub struct HackFilters {
     pub hack_types: Option<Vec<String>>,
     pub author: Option<String>,
     pub min_rating: Option<f64>,
+    pub play_statuses: Option<Vec<String>>, // OR filtering
+    pub favorites_only: Option<bool>,
+    pub user_tags: Option<Vec<String>>, // AND filtering
+    pub min_personal_rating: Option<f64>,
+    pub collection_id: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HackFacets {
+    pub difficulties: Vec<FacetCount>,
+    pub hack_types: Vec<FacetCount>,
+    pub play_statuses: Vec<FacetCount>,
+    pub patched: u32,
+    pub unpatched: u32,
+    pub favorites: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HackPage {
+    pub items: Vec<Hack>,
+    pub total: Option<u32>, // Hacks matching the filters, across all pages; first page only
+    pub facets: Option<HackFacets>, // First page only, like `total`
+    pub next_cursor: Option<String>, // Pass back as `cursor` to fetch the following page
+}
+
+/// Position of the last row of a page, encoded as an opaque string for the frontend.
+#[derive(Debug, Serialize, Deserialize)]
+struct HackCursor {
+    sort_by: String,
+    descending: bool,
+    value: serde_json::Value,
+    id: u32,
 }
 
 #[command]
@@ -41,23 +119,148 @@ pub fn get_hacks(
     state: tauri::State<AppState>,
     limit: Option<u32>,
     offset: Option<u32>,
+    cursor: Option<String>,
     filters: Option<HackFilters>,
-) -> Result<Vec<Hack>, String> {
+) -> Result<HackPage, String> {
     let conn = state.db.get().map_err(|e| e.to_string())?;
-    get_hacks_impl(&conn, limit, offset, filters)
+    get_hacks_impl(&conn, limit, offset, cursor.as_deref(), filters)
 }
 
+/// Returns one page of hacks. When `cursor` is given the page starts right after
+/// the cursor row (keyset pagination) and `offset` is ignored.
 pub fn get_hacks_impl(
     conn: &rusqlite::Connection,
     limit: Option<u32>,
     offset: Option<u32>,
+    cursor: Option<&str>,
     filters: Option<HackFilters>,
-) -> Result<Vec<Hack>, String> {
+) -> Result<HackPage, String> {
     let limit = limit.unwrap_or(50);
     let offset = offset.unwrap_or(0);
     
     let filters = filters.unwrap_or_default();
+    let (mut where_clauses, mut params_vec) = build_filter_clause(&filters);
+    
+    // Build ORDER BY clause
+    // Collections default to their own order
+    let default_sort = if filters.collection_id.is_some() { "position" } else { "name" };
+    let sort_by = filters.sort_by.as_deref().unwrap_or(default_sort);
+    let descending = filters.sort_direction.as_deref()
+        .map(|d| d.eq_ignore_ascii_case("desc"))
+        .unwrap_or(false);
+    let (sort_by, sort_expr) = match sort_by {
+        "date" => ("date", "release_date"),
+        "rating" => ("rating", "rating"),
+        "downloads" => ("downloads", "downloads"),
+        "personal_rating" => ("personal_rating", "personal_rating"),
+        "last_played" => ("last_played", "last_played"),
+        "status" => ("status", "play_status"),
+        "position" if filters.collection_id.is_some() => (
+            "position",
+            "(SELECT position FROM collection_hacks WHERE collection_id = ? AND hack_id = hacks.id)",
+        ),
+        _ => ("name", "name"),
+    };
+    // The position expression carries the collection ID as a parameter each time it is used
+    let sort_params = || -> Vec<Box<dyn rusqlite::ToSql>> {
+        if sort_by == "position" { vec![Box::new(filters.collection_id)] } else { Vec::new() }
+    };
     
+    // Counting scans every matching row, so cursor pages reuse the first page's numbers
+    let (total, facets) = if cursor.is_none() {
+        let where_clause = to_where_clause(&where_clauses);
+        let total: u32 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} {}", HACK_FROM, where_clause),
+            to_params(&params_vec).as_slice(),
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+        (Some(total), Some(get_facets(conn, &filters)?))
+    } else {
+        (None, None)
+    };
+    
+    // Keyset condition: rows after the cursor in (sort value, id) order, NULL sort values last
+    let mut keyset_params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    if let Some(cursor) = cursor {
+        let cursor: HackCursor = serde_json::from_str(cursor).map_err(|_| "Invalid cursor".to_string())?;
+        if cursor.sort_by != sort_by || cursor.descending != descending {
+            return Err("Cursor does not match the current sort order".to_string());
+        }
+        let cmp = if descending { "<" } else { ">" };
+        let value = json_to_sql(&cursor.value);
+        if value == rusqlite::types::Value::Null {
+            where_clauses.push(format!("({} IS NULL AND id {} ?)", sort_expr, cmp));
+            keyset_params.extend(sort_params());
+            keyset_params.push(Box::new(cursor.id));
+        } else {
+            where_clauses.push(format!(
+                "({expr} {cmp} ? OR ({expr} = ? AND id {cmp} ?) OR {expr} IS NULL)",
+                expr = sort_expr,
+                cmp = cmp
+            ));
+            keyset_params.extend(sort_params());
+            keyset_params.push(Box::new(value.clone()));
+            keyset_params.extend(sort_params());
+            keyset_params.push(Box::new(value));
+            keyset_params.push(Box::new(cursor.id));
+            keyset_params.extend(sort_params());
+        }
+    }
+    
+    let direction = if descending { "DESC" } else { "ASC" };
+    let query = format!(
+        "SELECT {cols}, {expr} AS sort_key FROM {from} {where_clause} ORDER BY {expr} {dir} NULLS LAST, id {dir} LIMIT ? OFFSET ?",
+        cols = HACK_COLUMNS,
+        expr = sort_expr,
+        from = HACK_FROM,
+        where_clause = to_where_clause(&where_clauses),
+        dir = direction
+    );
+    
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    
+    // Parameters in statement order: sort key column, filters, keyset, ORDER BY, limit and offset
+    let mut query_params = sort_params();
+    query_params.append(&mut params_vec);
+    query_params.append(&mut keyset_params);
+    query_params.extend(sort_params());
+    query_params.push(Box::new(limit));
+    query_params.push(Box::new(if cursor.is_some() { 0 } else { offset }));
+    
+    let rows = stmt.query_map(
+        to_params(&query_params).as_slice(),
+        |row| Ok((hack_from_row(row)?, row.get::<_, rusqlite::types::Value>(21)?)),
+    ).map_err(|e| e.to_string())?;
+    
+    let mut items = Vec::new();
+    let mut last_key = None;
+    for row in rows {
+        let (hack, key) = row.map_err(|e| e.to_string())?;
+        last_key = Some((key, hack.id));
+        items.push(hack);
+    }
+    
+    let next_cursor = match last_key {
+        Some((key, id)) if items.len() as u32 == limit => Some(
+            serde_json::to_string(&HackCursor {
+                sort_by: sort_by.to_string(),
+                descending,
+                value: sql_to_json(key),
+                id,
+            }).map_err(|e| e.to_string())?,
+        ),
+        _ => None,
+    };
+    
+    Ok(HackPage {
+        items,
+        total,
+        facets,
+        next_cursor,
+    })
+}
+
+fn build_filter_clause(filters: &HackFilters) -> (Vec<String>, Vec<Box<dyn rusqlite::ToSql>>) {
     // Build WHERE clause
     let mut where_clauses: Vec<String> = Vec::new();
     let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -128,64 +331,129 @@ pub fn get_hacks_impl(
         where_clauses.push("rating >= ?".to_string());
         params_vec.push(Box::new(min_rating));
     }
+    if let Some(statuses) = &filters.play_statuses {
+        if !statuses.is_empty() {
+            let placeholders = vec!["?"; statuses.len()].join(", ");
+            where_clauses.push(format!("play_status IN ({})", placeholders));
+            for status in statuses {
+                params_vec.push(Box::new(status.clone()));
+            }
+        }
+    }
+    if filters.favorites_only.unwrap_or(false) {
+        where_clauses.push("favorite = 1".to_string());
+    }
+    if let Some(user_tags) = &filters.user_tags {
+        for tag in user_tags {
+            where_clauses.push("EXISTS (SELECT 1 FROM json_each(user_tags) WHERE value = ?)".to_string());
+            params_vec.push(Box::new(tag.clone()));
+        }
+    }
+    if let Some(min_personal_rating) = filters.min_personal_rating {
+        where_clauses.push("personal_rating >= ?".to_string());
+        params_vec.push(Box::new(min_personal_rating));
+    }
+    if let Some(collection_id) = filters.collection_id {
+        where_clauses.push("id IN (SELECT hack_id FROM collection_hacks WHERE collection_id = ?)".to_string());
+        params_vec.push(Box::new(collection_id));
+    }
     
-    let where_clause = if where_clauses.is_empty() {
+    (where_clauses, params_vec)
+}
+
+fn to_where_clause(where_clauses: &[String]) -> String {
+    if where_clauses.is_empty() {
         String::new()
     } else {
         format!("WHERE {}", where_clauses.join(" AND "))
+    }
+}
+
+fn to_params(params_vec: &[Box<dyn rusqlite::ToSql>]) -> Vec<&dyn rusqlite::ToSql> {
+    params_vec.iter().map(|p| p.as_ref()).collect()
+}
+
+/// Counts per facet value over all hacks matching `filters`.
+fn get_facets(conn: &rusqlite::Connection, filters: &HackFilters) -> Result<HackFacets, String> {
+    let grouped = |column: &str| -> Result<Vec<FacetCount>, String> {
+        let (mut where_clauses, params_vec) = build_filter_clause(filters);
+        where_clauses.push(format!("{} IS NOT NULL AND {} != ''", column, column));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {col}, COUNT(*) FROM {} {} GROUP BY {col} ORDER BY {col}",
+            HACK_FROM,
+            to_where_clause(&where_clauses),
+            col = column
+        )).map_err(|e| e.to_string())?;
+        let counts = stmt.query_map(to_params(&params_vec).as_slice(), |row| {
+            Ok(FacetCount { value: row.get(0)?, count: row.get(1)? })
+        }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+        Ok(counts)
     };
     
-    // Build ORDER BY clause
-    let sort_by = filters.sort_by.as_deref().unwrap_or("name");
-    let sort_direction = filters.sort_direction.as_deref().unwrap_or("asc");
-    let order_by = match sort_by {
-        "date" => format!("ORDER BY release_date {}", sort_direction),
-        "rating" => format!("ORDER BY rating {} NULLS LAST", sort_direction),
-        "downloads" => format!("ORDER BY downloads {} NULLS LAST", sort_direction),
-        _ => format!("ORDER BY name {}", sort_direction),
-    };
-    
-    let query = format!(
-        "SELECT id, name, file_path, api_id, authors, release_date, description, images, tags, rating, downloads, difficulty, type, download_url, readme 
-         FROM hacks {} {} LIMIT ? OFFSET ?",
-        where_clause, order_by
-    );
-    
-    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
-    
-    // Add limit and offset to params
-    params_vec.push(Box::new(limit));
-    params_vec.push(Box::new(offset));
-    
-    let hacks = stmt.query_map(
-        params_vec.iter().map(|p| p.as_ref()).collect::<Vec<_>>().as_slice(),
-        |row| {
-            Ok(Hack {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                file_path: row.get(2)?,
-                api_id: row.get(3)?,
-                authors: row.get(4)?,
-                release_date: row.get(5)?,
-                description: row.get(6)?,
-                images: row.get(7)?,
-                tags: row.get(8)?,
-                rating: row.get(9)?,
-                downloads: row.get(10)?,
-                difficulty: row.get(11)?,
-                hack_type: row.get(12)?,
-                download_url: row.get(13)?,
-                readme: row.get(14)?,
-            })
+    let difficulties = grouped("difficulty")?;
+    let play_statuses = grouped("play_status")?;
+    
+    // Types are comma-separated, so they are counted individually
+    let (where_clauses, params_vec) = build_filter_clause(filters);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT type FROM {} {}", HACK_FROM, to_where_clause(&where_clauses)
+    )).map_err(|e| e.to_string())?;
+    let mut type_counts: std::collections::BTreeMap<String, u32> = std::collections::BTreeMap::new();
+    let rows = stmt.query_map(to_params(&params_vec).as_slice(), |row| row.get::<_, Option<String>>(0))
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        for individual_type in row.map_err(|e| e.to_string())?.unwrap_or_default().split(',') {
+            let trimmed = individual_type.trim();
+            if !trimmed.is_empty() {
+                *type_counts.entry(trimmed.to_string()).or_default() += 1;
+            }
         }
+    }
+    let hack_types = type_counts.into_iter().map(|(value, count)| FacetCount { value, count }).collect();
+    
+    let (where_clauses, params_vec) = build_filter_clause(filters);
+    let (patched, unpatched, favorites): (u32, u32, u32) = conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(file_path IS NOT NULL), 0), COALESCE(SUM(file_path IS NULL), 0), COALESCE(SUM(favorite = 1), 0)
+             FROM {} {}",
+            HACK_FROM,
+            to_where_clause(&where_clauses)
+        ),
+        to_params(&params_vec).as_slice(),
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
     ).map_err(|e| e.to_string())?;
     
-    let mut result = Vec::new();
-    for hack in hacks {
-        result.push(hack.map_err(|e| e.to_string())?);
+    Ok(HackFacets {
+        difficulties,
+        hack_types,
+        play_statuses,
+        patched,
+        unpatched,
+        favorites,
+    })
+}
+
+fn sql_to_json(value: rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value;
+    match value {
+        Value::Integer(i) => serde_json::Value::from(i),
+        Value::Real(f) => serde_json::Value::from(f),
+        Value::Text(s) => serde_json::Value::from(s),
+        Value::Null | Value::Blob(_) => serde_json::Value::Null,
+    }
+}
+
+fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        _ => Value::Null,
     }
-    
-    Ok(result)
 }
 
 #[command]
@@ -196,29 +464,10 @@ pub fn get_hack_details(
     let conn = state.db.get().map_err(|e| e.to_string())?;
     
     let mut stmt = conn.prepare(
-        "SELECT id, name, file_path, api_id, authors, release_date, description, images, tags, rating, downloads, difficulty, type, download_url, readme 
-         FROM hacks WHERE id = ?1"
+        &format!("SELECT {} FROM {} WHERE id = ?1", HACK_COLUMNS, HACK_FROM)
     ).map_err(|e| e.to_string())?;
     
-    let mut rows = stmt.query_map(params![hack_id], |row| {
-        Ok(Hack {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            file_path: row.get(2)?,
-            api_id: row.get(3)?,
-            authors: row.get(4)?,
-            release_date: row.get(5)?,
-            description: row.get(6)?,
-            images: row.get(7)?,
-            tags: row.get(8)?,
-            rating: row.get(9)?,
-            downloads: row.get(10)?,
-            difficulty: row.get(11)?,
-            hack_type: row.get(12)?,
-            download_url: row.get(13)?,
-            readme: row.get(14)?,
-        })
-    }).map_err(|e| e.to_string())?;
+    let mut rows = stmt.query_map(params![hack_id], hack_from_row).map_err(|e| e.to_string())?;
     
     Ok(rows.next().transpose().map_err(|e| e.to_string())?)
 }
@@ -227,6 +476,7 @@ pub fn get_hack_details(
 pub struct FilterOptions {
     pub difficulties: Vec<String>,
     pub hack_types: Vec<String>,
+    pub user_tags: Vec<String>,
 }
 
 #[command]
@@ -274,31 +524,29 @@ pub fn get_filter_options(
     let mut hack_types: Vec<String> = hack_types_set.into_iter().collect();
     hack_types.sort();
     
+    // Get user tags from the JSON arrays in user_hack_data
+    let mut user_tags_set = std::collections::BTreeSet::new();
+    let mut stmt = conn.prepare(
+        "SELECT user_tags FROM user_hack_data WHERE user_tags IS NOT NULL"
+    ).map_err(|e| e.to_string())?;
+    
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+    
+    for row in rows {
+        let tags: Vec<String> = serde_json::from_str(&row.map_err(|e| e.to_string())?).unwrap_or_default();
+        user_tags_set.extend(tags);
+    }
+    
     Ok(FilterOptions {
         difficulties,
         hack_types,
+        user_tags: user_tags_set.into_iter().collect(),
     })
 }
 
-impl Default for HackFilters {
-    fn default() -> Self {
-        Self {
-            patched_only: None,
-            unpatched_only: None,
-            sort_by: None,
-            sort_direction: None,
-            difficulty: None,
-            difficulties: None,
-            hack_type: None,
-            hack_types: None,
-            author: None,
-            min_rating: None,
-        }
-    }
-}
-
 #[command]
 pub fn delete_hack(
+    app: AppHandle,
     state: tauri::State<AppState>,
     hack_id: u32,
     delete_completions: bool,
@@ -312,15 +560,22 @@ pub fn delete_hack(
         |row| row.get(0),
     ).map_err(|e| e.to_string())?;
     
-    // 2. Delete file if exists
+    // 2. Back up the save file; nothing is deleted if that fails
+    backup_save_impl(&conn, &backup_root(&app)?, hack_id as i64, REASON_DELETE)?;
+    
+    // 3. Delete file and its save if they exist
     if let Some(path) = file_path {
         let path = std::path::Path::new(&path);
         if path.exists() {
             std::fs::remove_file(path).map_err(|e| e.to_string())?;
         }
+        let save = srm_path(path);
+        if save.exists() {
+            std::fs::remove_file(save).map_err(|e| e.to_string())?;
+        }
     }
     
-    // 3. Delete completions if requested
+    // 4. Delete completions if requested
     if delete_completions {
         conn.execute(
             "DELETE FROM hack_completions WHERE hack_id = ?1",
@@ -328,7 +583,7 @@ pub fn delete_hack(
         ).map_err(|e| e.to_string())?;
     }
     
-    // 4. Update hack record (remove file_path instead of deleting record)
+    // 5. Update hack record (remove file_path instead of deleting record)
     conn.execute(
         "UPDATE hacks SET file_path = NULL WHERE id = ?1",
         params![hack_id],
@@ -365,7 +620,7 @@ mod tests {
         let state = setup_test_db();
         let conn = state.db.get().unwrap();
         
-        let hacks = get_hacks_impl(&conn, None, None, None).unwrap();
+        let hacks = get_hacks_impl(&conn, None, None, None, None).unwrap().items;
         assert_eq!(hacks.len(), 4);
     }
     
@@ -379,7 +634,7 @@ mod tests {
             ..Default::default()
         };
         
-        let hacks = get_hacks_impl(&conn, None, None, Some(filters)).unwrap();
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
         assert_eq!(hacks.len(), 1);
         assert_eq!(hacks[0].name, "Super Mario World: Return to Dinosaur Land");
     }
@@ -395,7 +650,7 @@ mod tests {
             ..Default::default()
         };
         
-        let hacks = get_hacks_impl(&conn, None, None, Some(filters)).unwrap();
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
         assert_eq!(hacks[0].name, "Kaizo Mario World"); // 5.0
         assert_eq!(hacks[1].name, "Invictus"); // 4.8
         assert_eq!(hacks[2].name, "Super Mario World: Return to Dinosaur Land"); // 4.5
@@ -412,8 +667,154 @@ mod tests {
             ..Default::default()
         };
         
-        let hacks = get_hacks_impl(&conn, None, None, Some(filters)).unwrap();
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
         assert_eq!(hacks.len(), 1);
         assert_eq!(hacks[0].name, "Invictus");
     }
+    
+    #[test]
+    fn test_filter_and_sort_by_user_data() {
+        let state = setup_test_db();
+        let conn = state.db.get().unwrap();
+        
+        conn.execute("
+            INSERT INTO user_hack_data (hack_id, play_status, personal_rating, favorite, user_tags)
+            SELECT id, 'playing', 3.0, 1, '[\"practice\"]' FROM hacks WHERE name = 'Invictus'
+        ", []).unwrap();
+        conn.execute("
+            INSERT INTO user_hack_data (hack_id, play_status, personal_rating, favorite, user_tags)
+            SELECT id, 'beaten', 4.0, 0, '[\"practice\", \"stream\"]' FROM hacks WHERE name = 'Quickie World'
+        ", []).unwrap();
+        
+        let filters = HackFilters {
+            favorites_only: Some(true),
+            ..Default::default()
+        };
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
+        assert_eq!(hacks.len(), 1);
+        assert_eq!(hacks[0].name, "Invictus");
+        assert!(hacks[0].favorite);
+        
+        let filters = HackFilters {
+            user_tags: Some(vec!["practice".to_string()]),
+            sort_by: Some("personal_rating".to_string()),
+            sort_direction: Some("desc".to_string()),
+            ..Default::default()
+        };
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
+        assert_eq!(hacks.len(), 2);
+        assert_eq!(hacks[0].name, "Quickie World");
+        
+        // Tags match exactly, whatever characters they contain
+        let tags = serde_json::to_string(&["practice", "C:\\saves", "ökö"]).unwrap();
+        conn.execute(
+            "UPDATE user_hack_data SET user_tags = ?1 WHERE hack_id = (SELECT id FROM hacks WHERE name = 'Invictus')",
+            [tags],
+        ).unwrap();
+        for (tag, expected) in [("C:\\saves", 1), ("ökö", 1), ("pract%", 0), ("practic_", 0), ("practice\", \"stream", 0)] {
+            let filters = HackFilters {
+                user_tags: Some(vec![tag.to_string()]),
+                ..Default::default()
+            };
+            let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
+            assert_eq!(hacks.len(), expected, "tag {:?}", tag);
+        }
+        
+        let filters = HackFilters {
+            play_statuses: Some(vec!["beaten".to_string(), "dropped".to_string()]),
+            ..Default::default()
+        };
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
+        assert_eq!(hacks.len(), 1);
+        assert_eq!(hacks[0].play_status.as_deref(), Some("beaten"));
+    }
+    
+    #[test]
+    fn test_filter_by_collection_uses_collection_order() {
+        let state = setup_test_db();
+        let conn = state.db.get().unwrap();
+        
+        let collection = crate::commands::collections::create_collection_impl(&conn, "Stream next week", None).unwrap();
+        for name in ["Quickie World", "Invictus"] {
+            let id: u32 = conn.query_row("SELECT id FROM hacks WHERE name = ?1", [name], |row| row.get(0)).unwrap();
+            crate::commands::collections::add_hack_to_collection_impl(&conn, collection.id, id).unwrap();
+        }
+        
+        let filters = HackFilters {
+            collection_id: Some(collection.id),
+            ..Default::default()
+        };
+        let hacks = get_hacks_impl(&conn, None, None, None, Some(filters)).unwrap().items;
+        let names: Vec<&str> = hacks.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["Quickie World", "Invictus"]);
+    }
+    
+    #[test]
+    fn test_page_reports_total_and_facets() {
+        let state = setup_test_db();
+        let conn = state.db.get().unwrap();
+        
+        let page = get_hacks_impl(&conn, Some(2), None, None, None).unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.total, Some(4));
+        let facets = page.facets.unwrap();
+        assert_eq!(facets.unpatched, 4);
+        
+        let kaizo = facets.difficulties.iter().find(|f| f.value == "Kaizo").unwrap();
+        assert_eq!(kaizo.count, 3);
+        
+        let filters = HackFilters {
+            difficulty: Some("Kaizo".to_string()),
+            ..Default::default()
+        };
+        let page = get_hacks_impl(&conn, Some(1), None, None, Some(filters)).unwrap();
+        assert_eq!(page.total, Some(3));
+        let facets = page.facets.unwrap();
+        assert_eq!(facets.hack_types.len(), 1);
+        assert_eq!(facets.hack_types[0].count, 3);
+        
+        // Cursor pages skip the counting
+        let next = get_hacks_impl(&conn, Some(1), None, page.next_cursor.as_deref(), None).unwrap();
+        assert_eq!(next.total, None);
+        assert!(next.facets.is_none());
+    }
+    
+    #[test]
+    fn test_cursor_pagination_walks_all_rows() {
+        let state = setup_test_db();
+        let conn = state.db.get().unwrap();
+        conn.execute("INSERT INTO hacks (name, rating) VALUES ('Unrated Hack', NULL), ('Also Kaizo', 5.0)", []).unwrap();
+        
+        for direction in ["asc", "desc"] {
+            let filters = || HackFilters {
+                sort_by: Some("rating".to_string()),
+                sort_direction: Some(direction.to_string()),
+                ..Default::default()
+            };
+            
+            let expected: Vec<u32> = get_hacks_impl(&conn, Some(100), None, None, Some(filters()))
+                .unwrap().items.iter().map(|h| h.id).collect();
+            assert_eq!(expected.len(), 6);
+            
+            let mut walked = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let page = get_hacks_impl(&conn, Some(2), None, cursor.as_deref(), Some(filters())).unwrap();
+                walked.extend(page.items.iter().map(|h| h.id));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            assert_eq!(walked, expected, "keyset pages should match the full {} listing", direction);
+        }
+        
+        // A cursor is only valid for the sort order that produced it
+        let page = get_hacks_impl(&conn, Some(2), None, None, None).unwrap();
+        let filters = HackFilters {
+            sort_by: Some("downloads".to_string()),
+            ..Default::default()
+        };
+        assert!(get_hacks_impl(&conn, Some(2), None, page.next_cursor.as_deref(), Some(filters)).is_err());
+    }
 }
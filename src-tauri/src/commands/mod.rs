@@ -5,4 +5,7 @@ pub mod launcher;
 pub mod sync;
 pub mod completions;
 pub mod logs;
-
+pub mod integrity;
+pub mod user_data;
+pub mod collections;
+pub mod saves;
@@ -1,11 +1,16 @@
 use tauri::{command, AppHandle, Manager, Emitter};
 use crate::patching::Patcher;
+use crate::patching::naming::{render_output_path, resolve_collision, NamingFields, DEFAULT_OUTPUT_TEMPLATE};
+use crate::commands::saves::{backup_root, backup_save_impl, check_save_move, move_save, REASON_REPATCH};
 use crate::state::AppState;
 use crate::config::Config;
-use std::path::PathBuf;
+use crate::domain::rom::RomValidator;
+use crate::tracking::memory_map::move_sidecar;
+use std::path::{Path, PathBuf};
 use std::fs;
 use reqwest;
 use rusqlite;
+use serde::Serialize;
 
 #[command]
 pub async fn patch_rom(
@@ -17,29 +22,9 @@ pub async fn patch_rom(
     let conn = state.db.get().map_err(|e| e.to_string())?;
     let config = Config::load(&conn).map_err(|e| e.to_string())?;
     
-    let clean_rom_path = if let Some(rom_path) = config.clean_rom_path {
-        let path = PathBuf::from(&rom_path);
-        if !path.exists() {
-            return Err(format!("Clean ROM not found at configured path: {}", rom_path));
-        }
-        path
-    } else {
-        // Fallback to app data directory
-        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-        let clean_rom_dir = app_data_dir.join("clean_rom");
-        let path = clean_rom_dir.join("smw.sfc");
-        if !path.exists() {
-            return Err("Clean ROM not found. Please configure your clean ROM path in settings.".to_string());
-        }
-        path
-    };
+    let clean_rom_path = resolve_clean_rom_path(&app, &config)?;
     
-    let output_dir = if let Some(output) = config.output_directory {
-        PathBuf::from(&output)
-    } else {
-        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-        app_data_dir.join("patched")
-    };
+    let output_dir = resolve_output_dir(&app, &config)?;
     
     fs::create_dir_all(&output_dir).map_err(|e| format!("Failed to create output directory: {}", e))?;
     
@@ -105,75 +90,73 @@ pub async fn patch_rom(
     };
     
     // Fallback to API ID if name lookup fails.
-    let hack_name = conn.query_row::<String, _, _>(
-        "SELECT name FROM hacks WHERE api_id = ?1",
+    let (naming_fields, current_path) = conn.query_row(
+        "SELECT id, name, api_id, difficulty, type, authors, file_path FROM hacks WHERE api_id = ?1",
         rusqlite::params![api_id],
-        |row| row.get(0),
-    ).unwrap_or_else(|_| format!("hack_{}", api_id));
+        |row| Ok((naming_fields_from_row(row)?, row.get::<_, Option<String>>(6)?)),
+    ).unwrap_or_else(|_| (
+        NamingFields {
+            name: format!("hack_{}", api_id),
+            api_id: Some(api_id.clone()),
+            ..Default::default()
+        },
+        None,
+    ));
     
-    let sanitized_name = hack_name
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
-        .collect::<String>();
+    let template = config.output_name_template.as_deref().unwrap_or(DEFAULT_OUTPUT_TEMPLATE);
+    let relative_path = render_output_path(template, &naming_fields)?;
+    let output_path = resolve_collision(&output_dir.join(relative_path), |candidate| {
+        is_output_taken(&conn, candidate, current_path.as_deref())
+    });
     
-    let output_path = output_dir.join(format!("{}.sfc", sanitized_name));
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
     
     let _ = app.emit("patch-progress", serde_json::json!({
         "stage": "patching",
         "message": "Applying patch...",
     }));
     
+    // The save has to be able to follow the ROM before anything is written
+    if let Some(current) = current_path.as_deref().map(Path::new) {
+        check_save_move(current, &output_path)?;
+    }
+    
+    // Repatching replaces the ROM the save belongs to, so keep a copy first
+    if current_path.is_some() {
+        backup_save_impl(&conn, &backup_root(&app)?, naming_fields.id, REASON_REPATCH)?;
+    }
+    
     Patcher::patch_bps(&clean_rom_path, &extracted_patch, &output_path)
         .map_err(|e| format!("Failed to apply patch: {}", e))?;
     
-    // Calculate Checksum for Passive Tracking
-    let rom_content = fs::read(&output_path).map_err(|e| format!("Failed to read patched ROM: {}", e))?;
-    let mut checksum_hex = String::new();
-    
-    // Try LoROM (Header at 0x7FC0)
-    // Checksum at +0x1C (0x1E actually? Header is 21 bytes? No, standard header is 64 bytes usually reserved area)
-    // SNES Header:
-    // Title: 21 bytes
-    // Map Mode: 1 byte
-    // Type: 1 byte
-    // Size: 1 byte
-    // SRAM: 1 byte
-    // Country: 1 byte
-    // License: 1 byte
-    // Version: 1 byte
-    // Checksum Comp: 2 bytes
-    // Checksum: 2 bytes
-    // Total offset to checksum: 21+1+1+1+1+1+1+1 = 28 bytes? No.
-    // 0x7FC0 + 0x1C = 0x7FDC (Checksum Complement)
-    // 0x7FC0 + 0x1E = 0x7FDE (Checksum)
-    
-    // Helper to get checksum from offset
-    let get_checksum = |offset: usize| -> Option<u16> {
-        if rom_content.len() < offset + 0x20 { return None; }
-        let comp = ((rom_content[offset + 0x1D] as u16) << 8) | (rom_content[offset + 0x1C] as u16);
-        let sum = ((rom_content[offset + 0x1F] as u16) << 8) | (rom_content[offset + 0x1E] as u16);
-        if (comp ^ sum) == 0xFFFF {
-            Some(sum)
-        } else {
-            None
+    // The save and memory map follow the hack when the new ROM lands somewhere else
+    if let Some(current) = current_path.as_deref().map(Path::new) {
+        move_rom_companions(current, &output_path)?;
+        if current != output_path {
+            if let Err(e) = remove_replaced_rom(current, &output_dir) {
+                eprintln!("Failed to remove old ROM {}: {}", current.display(), e);
+            }
         }
-    };
+    }
     
-    if let Some(sum) = get_checksum(0x7FC0) {
-        checksum_hex = format!("{:04X}", sum);
-    } else if let Some(sum) = get_checksum(0xFFC0) {
-        checksum_hex = format!("{:04X}", sum);
-    } 
-    // If we can't find a valid checksum, we might leave it empty or try checking for a specific SMW header location?
-    // Most SMW hacks are LoROM.
+    // Fingerprint for passive tracking and integrity checks
+    let rom_content = fs::read(&output_path).map_err(|e| format!("Failed to read patched ROM: {}", e))?;
+    let (rom_md5, checksum_hex) = RomValidator::fingerprint(&rom_content);
     
     let output_path_str = output_path.to_string_lossy().to_string();
     conn.execute(
-        "UPDATE hacks SET file_path = ?1, readme = ?2, rom_checksum = ?3 WHERE api_id = ?4",
-        rusqlite::params![output_path_str, readme_content, checksum_hex, api_id],
+        "UPDATE hacks SET file_path = ?1, readme = ?2, rom_checksum = ?3, rom_md5 = ?4 WHERE api_id = ?5",
+        rusqlite::params![output_path_str, readme_content, checksum_hex, rom_md5, api_id],
 
     ).map_err(|e| format!("Failed to update hack in database: {}", e))?;
     
+    // Keep the patch so the ROM can be rebuilt later without downloading it again
+    if let Err(e) = cache_patch(&app_data_dir, &api_id, &extracted_patch) {
+        eprintln!("Failed to cache patch for {}: {}", api_id, e);
+    }
+    
     if is_zip {
         let _ = fs::remove_file(&extracted_patch);
         let _ = fs::remove_file(&downloaded_file);
@@ -189,3 +172,321 @@ pub async fn patch_rom(
     Ok(output_path.to_string_lossy().to_string())
 }
 
+
+#[derive(Debug, Serialize)]
+pub struct OutputRename {
+    pub hack_id: i64,
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub status: String, // "planned", "moved", "missing", "error"
+    pub error: Option<String>,
+}
+
+/// Moves existing patched ROMs to the locations given by the configured naming
+/// template. With `dry_run` the planned renames are returned without touching any files.
+#[command]
+pub fn apply_output_template(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    dry_run: bool,
+) -> Result<Vec<OutputRename>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let config = Config::load(&conn).map_err(|e| e.to_string())?;
+    let output_dir = resolve_output_dir(&app, &config)?;
+    let template = config.output_name_template.as_deref().unwrap_or(DEFAULT_OUTPUT_TEMPLATE);
+    
+    apply_output_template_impl(&conn, &output_dir, template, dry_run)
+}
+
+pub fn apply_output_template_impl(
+    conn: &rusqlite::Connection,
+    output_dir: &Path,
+    template: &str,
+    dry_run: bool,
+) -> Result<Vec<OutputRename>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, api_id, difficulty, type, authors, file_path FROM hacks WHERE file_path IS NOT NULL ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+    
+    let hacks = stmt.query_map([], |row| {
+        Ok((naming_fields_from_row(row)?, row.get::<_, String>(6)?))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    
+    let mut planned: Vec<PathBuf> = Vec::new();
+    let mut renames = Vec::new();
+    
+    for (fields, current) in hacks {
+        let target = output_dir.join(render_output_path(template, &fields)?);
+        if Path::new(&current) == target {
+            continue;
+        }
+        
+        let target = resolve_collision(&target, |candidate| {
+            planned.iter().any(|p| p == candidate) || is_output_taken(conn, candidate, Some(&current))
+        });
+        if Path::new(&current) == target {
+            continue;
+        }
+        planned.push(target.clone());
+        
+        let mut rename = OutputRename {
+            hack_id: fields.id,
+            name: fields.name,
+            from: current.clone(),
+            to: target.to_string_lossy().to_string(),
+            status: "planned".to_string(),
+            error: None,
+        };
+        
+        if !Path::new(&current).exists() {
+            rename.status = "missing".to_string();
+        } else if !dry_run {
+            match move_output(Path::new(&current), &target, output_dir) {
+                Ok(()) => {
+                    conn.execute(
+                        "UPDATE hacks SET file_path = ?1 WHERE id = ?2",
+                        rusqlite::params![rename.to, rename.hack_id],
+                    ).map_err(|e| format!("Failed to update hack in database: {}", e))?;
+                    rename.status = "moved".to_string();
+                }
+                Err(e) => {
+                    rename.status = "error".to_string();
+                    rename.error = Some(e);
+                }
+            }
+        }
+        
+        renames.push(rename);
+    }
+    
+    Ok(renames)
+}
+
+pub(crate) fn resolve_clean_rom_path(app: &AppHandle, config: &Config) -> Result<PathBuf, String> {
+    if let Some(rom_path) = &config.clean_rom_path {
+        let path = PathBuf::from(rom_path);
+        if !path.exists() {
+            return Err(format!("Clean ROM not found at configured path: {}", rom_path));
+        }
+        Ok(path)
+    } else {
+        // Fallback to app data directory
+        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        let clean_rom_dir = app_data_dir.join("clean_rom");
+        let path = clean_rom_dir.join("smw.sfc");
+        if !path.exists() {
+            return Err("Clean ROM not found. Please configure your clean ROM path in settings.".to_string());
+        }
+        Ok(path)
+    }
+}
+
+pub(crate) fn resolve_output_dir(app: &AppHandle, config: &Config) -> Result<PathBuf, String> {
+    if let Some(output) = &config.output_directory {
+        Ok(PathBuf::from(output))
+    } else {
+        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        Ok(app_data_dir.join("patched"))
+    }
+}
+
+/// Location of the cached patch for a hack, if one was kept from a previous patch.
+pub(crate) fn cached_patch_path(app_data_dir: &Path, api_id: &str) -> Option<PathBuf> {
+    ["bps", "ips"]
+        .iter()
+        .map(|ext| app_data_dir.join("patch_cache").join(format!("hack_{}.{}", api_id, ext)))
+        .find(|path| path.exists())
+}
+
+fn cache_patch(app_data_dir: &Path, api_id: &str, patch: &Path) -> Result<(), String> {
+    let cache_dir = app_data_dir.join("patch_cache");
+    fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+    
+    let ext = patch.extension().and_then(|e| e.to_str()).unwrap_or("bps").to_lowercase();
+    for old in ["bps", "ips"] {
+        let _ = fs::remove_file(cache_dir.join(format!("hack_{}.{}", api_id, old)));
+    }
+    fs::copy(patch, cache_dir.join(format!("hack_{}.{}", api_id, ext))).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub(crate) fn naming_fields_from_row(row: &rusqlite::Row) -> rusqlite::Result<NamingFields> {
+    Ok(NamingFields {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        api_id: row.get(2)?,
+        difficulty: row.get(3)?,
+        hack_type: row.get(4)?,
+        authors: row.get(5)?,
+    })
+}
+
+/// A path is taken if it exists on disk or is recorded for another hack,
+/// unless it is the current output of the hack being placed.
+pub(crate) fn is_output_taken(conn: &rusqlite::Connection, path: &Path, current_path: Option<&str>) -> bool {
+    let path_str = path.to_string_lossy();
+    if current_path == Some(path_str.as_ref()) {
+        return false;
+    }
+    if path.exists() {
+        return true;
+    }
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM hacks WHERE file_path = ?1)",
+        rusqlite::params![path_str],
+        |row| row.get(0),
+    ).unwrap_or(false)
+}
+
+fn move_output(from: &Path, to: &Path, output_dir: &Path) -> Result<(), String> {
+    check_save_move(from, to)?;
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    
+    // Rename fails across filesystems, so fall back to copy + delete
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to).map_err(|e| format!("Failed to copy ROM: {}", e))?;
+        fs::remove_file(from).map_err(|e| format!("Failed to remove old ROM: {}", e))?;
+    }
+    move_rom_companions(from, to)?;
+    remove_empty_parents(from, output_dir);
+    
+    Ok(())
+}
+
+/// Moves the files kept next to a ROM, its save and memory map, to sit next to `to`.
+fn move_rom_companions(from: &Path, to: &Path) -> Result<(), String> {
+    move_save(from, to)?;
+    move_sidecar(from, to)
+}
+
+/// Deletes the ROM a repatch replaced with one at a different path.
+fn remove_replaced_rom(old: &Path, output_dir: &Path) -> Result<(), String> {
+    match fs::remove_file(old) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.to_string()),
+    }
+    remove_empty_parents(old, output_dir);
+    Ok(())
+}
+
+/// Cleans up folders left empty by moving `from` away, but never the output directory itself.
+fn remove_empty_parents(from: &Path, output_dir: &Path) {
+    let mut dir = from.parent();
+    while let Some(d) = dir {
+        if d == output_dir || !d.starts_with(output_dir) || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_apply_output_template_moves_files_and_updates_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path();
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        
+        let first = output_dir.join("Hack One.sfc");
+        let second = output_dir.join("Hack Two.sfc");
+        fs::write(&first, b"one").unwrap();
+        fs::write(&second, b"two").unwrap();
+        fs::write(output_dir.join("Hack One.srm"), b"save").unwrap();
+        fs::write(output_dir.join("Hack One.memmap.json"), b"{}").unwrap();
+        
+        conn.execute(
+            "INSERT INTO hacks (name, api_id, difficulty, file_path) VALUES
+             ('Hack One', '1', 'Kaizo', ?1),
+             ('Hack Two', '2', 'Kaizo', ?2),
+             ('Hack Three', '3', 'Normal', NULL)",
+            rusqlite::params![first.to_string_lossy(), second.to_string_lossy()],
+        ).unwrap();
+        
+        // Both hacks collapse onto the same name; the second must not overwrite the first
+        let template = "{difficulty}/Same.sfc";
+        let plan = apply_output_template_impl(&conn, output_dir, template, true).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert!(plan.iter().all(|r| r.status == "planned"));
+        assert!(first.exists(), "dry run must not move files");
+        
+        let result = apply_output_template_impl(&conn, output_dir, template, false).unwrap();
+        assert!(result.iter().all(|r| r.status == "moved"));
+        
+        let moved_first = output_dir.join("Kaizo").join("Same.sfc");
+        let moved_second = output_dir.join("Kaizo").join("Same (2).sfc");
+        assert_eq!(fs::read(&moved_first).unwrap(), b"one");
+        assert_eq!(fs::read(&moved_second).unwrap(), b"two");
+        assert!(!first.exists());
+        // Saves move along with their ROM
+        assert_eq!(fs::read(output_dir.join("Kaizo").join("Same.srm")).unwrap(), b"save");
+        assert!(!output_dir.join("Hack One.srm").exists());
+        // So does a custom memory map, or tracking would fall back to vanilla's
+        assert_eq!(fs::read(output_dir.join("Kaizo").join("Same.memmap.json")).unwrap(), b"{}");
+        assert!(!output_dir.join("Hack One.memmap.json").exists());
+        
+        let stored: String = conn.query_row(
+            "SELECT file_path FROM hacks WHERE api_id = '2'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(stored, moved_second.to_string_lossy());
+        
+        // Re-applying the same template is a no-op
+        let again = apply_output_template_impl(&conn, output_dir, template, false).unwrap();
+        assert!(again.is_empty());
+    }
+
+    #[test]
+    fn test_apply_output_template_keeps_rom_when_save_cannot_follow() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path();
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        
+        let rom = output_dir.join("Hack One.sfc");
+        fs::write(&rom, b"one").unwrap();
+        fs::write(output_dir.join("Hack One.srm"), b"save").unwrap();
+        // A leftover save where the ROM would go
+        fs::write(output_dir.join("Renamed.srm"), b"someone else's save").unwrap();
+        conn.execute(
+            "INSERT INTO hacks (name, api_id, file_path) VALUES ('Hack One', '1', ?1)",
+            rusqlite::params![rom.to_string_lossy()],
+        ).unwrap();
+        
+        let result = apply_output_template_impl(&conn, output_dir, "Renamed.sfc", false).unwrap();
+        assert_eq!(result[0].status, "error");
+        assert!(rom.exists());
+        assert!(!output_dir.join("Renamed.sfc").exists());
+        assert_eq!(fs::read(output_dir.join("Hack One.srm")).unwrap(), b"save");
+        assert_eq!(fs::read(output_dir.join("Renamed.srm")).unwrap(), b"someone else's save");
+    }
+
+    #[test]
+    fn test_remove_replaced_rom_cleans_up_empty_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path();
+        let old = output_dir.join("Kaizo").join("Hack.sfc");
+        fs::create_dir_all(old.parent().unwrap()).unwrap();
+        fs::write(&old, b"old").unwrap();
+        
+        remove_replaced_rom(&old, output_dir).unwrap();
+        assert!(!old.exists());
+        assert!(!output_dir.join("Kaizo").exists());
+        assert!(output_dir.exists());
+        
+        // Already gone is fine
+        remove_replaced_rom(&old, output_dir).unwrap();
+    }
+}
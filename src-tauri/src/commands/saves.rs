@@ -0,0 +1,351 @@
+use tauri::{command, AppHandle, Manager};
+use crate::state::AppState;
+use crate::tracking::sram::srm_path;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder in the app data directory holding every hack's save history.
+pub const SAVE_BACKUP_DIR: &str = "save_backups";
+/// Versions kept per hack; older ones are removed as new backups are made.
+const MAX_BACKUPS_PER_HACK: i64 = 30;
+
+/// Why a backup was taken.
+pub const REASON_SESSION_END: &str = "session_end";
+pub const REASON_MANUAL: &str = "manual";
+pub const REASON_DELETE: &str = "delete";
+pub const REASON_REPATCH: &str = "repatch";
+pub const REASON_RESTORE: &str = "restore";
+
+#[derive(Debug, Serialize)]
+pub struct SaveBackup {
+    pub id: i64,
+    pub hack_id: i64,
+    pub created_at: String,
+    pub reason: String,
+    pub size: i64,
+    pub md5: String,
+}
+
+pub(crate) fn backup_root(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join(SAVE_BACKUP_DIR))
+}
+
+#[command]
+pub fn get_save_backups(
+    state: tauri::State<AppState>,
+    hack_id: i64,
+) -> Result<Vec<SaveBackup>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    get_save_backups_impl(&conn, hack_id)
+}
+
+pub fn get_save_backups_impl(conn: &Connection, hack_id: i64) -> Result<Vec<SaveBackup>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, hack_id, created_at, reason, size, md5
+         FROM save_backups WHERE hack_id = ?1 ORDER BY id DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let backups = stmt.query_map(params![hack_id], |row| {
+        Ok(SaveBackup {
+            id: row.get(0)?,
+            hack_id: row.get(1)?,
+            created_at: row.get(2)?,
+            reason: row.get(3)?,
+            size: row.get(4)?,
+            md5: row.get(5)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    backups.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Backs up a hack's save file now. Returns `None` when there's no save file or it
+/// hasn't changed since the last backup.
+#[command]
+pub fn create_save_backup(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    hack_id: i64,
+) -> Result<Option<SaveBackup>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    backup_save_impl(&conn, &backup_root(&app)?, hack_id, REASON_MANUAL)
+}
+
+/// Copies the `.srm` next to the hack's ROM into its save history.
+pub fn backup_save_impl(
+    conn: &Connection,
+    backup_root: &Path,
+    hack_id: i64,
+    reason: &str,
+) -> Result<Option<SaveBackup>, String> {
+    let file_path: Option<String> = conn.query_row(
+        "SELECT file_path FROM hacks WHERE id = ?1",
+        params![hack_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    let Some(srm) = file_path.as_deref().map(srm_path).filter(|p| p.exists()) else {
+        return Ok(None);
+    };
+    backup_file(conn, backup_root, hack_id, &srm, reason)
+}
+
+fn backup_file(
+    conn: &Connection,
+    backup_root: &Path,
+    hack_id: i64,
+    srm: &Path,
+    reason: &str,
+) -> Result<Option<SaveBackup>, String> {
+    let data = fs::read(srm).map_err(|e| format!("Failed to read {}: {}", srm.display(), e))?;
+    let md5 = format!("{:x}", md5::compute(&data));
+
+    let latest: Option<String> = conn.query_row(
+        "SELECT md5 FROM save_backups WHERE hack_id = ?1 ORDER BY id DESC LIMIT 1",
+        params![hack_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    if latest.as_deref() == Some(md5.as_str()) {
+        return Ok(None);
+    }
+
+    let now = Utc::now();
+    let file_name = format!("hack_{}/{}_{}.srm", hack_id, now.format("%Y%m%dT%H%M%S%.3fZ"), &md5[..8]);
+    let backup_path = backup_root.join(&file_name);
+    if let Some(parent) = backup_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    }
+    fs::write(&backup_path, &data).map_err(|e| format!("Failed to write save backup: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO save_backups (hack_id, file_name, md5, size, reason, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![hack_id, file_name, md5, data.len() as i64, reason, now.to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    let backup = SaveBackup {
+        id: conn.last_insert_rowid(),
+        hack_id,
+        created_at: now.to_rfc3339(),
+        reason: reason.to_string(),
+        size: data.len() as i64,
+        md5,
+    };
+
+    prune_backups(conn, backup_root, hack_id)?;
+    Ok(Some(backup))
+}
+
+fn prune_backups(conn: &Connection, backup_root: &Path, hack_id: i64) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_name FROM save_backups WHERE hack_id = ?1 ORDER BY id DESC LIMIT -1 OFFSET ?2"
+    ).map_err(|e| e.to_string())?;
+    let old = stmt.query_map(params![hack_id, MAX_BACKUPS_PER_HACK], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    for (id, file_name) in old {
+        let _ = fs::remove_file(backup_root.join(file_name));
+        conn.execute("DELETE FROM save_backups WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Backs up the save of the hack played in a session that just ended; the emulator
+/// has usually written its `.srm` by then.
+pub fn backup_after_session(conn: &Connection, backup_root: &Path, session_id: i64) -> Result<Option<SaveBackup>, String> {
+    let hack_id: i64 = conn.query_row(
+        "SELECT hack_id FROM play_sessions WHERE id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    backup_save_impl(conn, backup_root, hack_id, REASON_SESSION_END)
+}
+
+/// Puts a backed up save back next to the hack's ROM. The current save is backed up
+/// first. The emulator has to be closed, or it overwrites the restored file on exit.
+#[command]
+pub fn restore_save_backup(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    backup_id: i64,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    restore_save_backup_impl(&conn, &backup_root(&app)?, backup_id)
+}
+
+pub fn restore_save_backup_impl(conn: &Connection, backup_root: &Path, backup_id: i64) -> Result<(), String> {
+    let (hack_id, file_name, file_path): (i64, String, Option<String>) = conn.query_row(
+        "SELECT b.hack_id, b.file_name, h.file_path FROM save_backups b
+         JOIN hacks h ON h.id = b.hack_id WHERE b.id = ?1",
+        params![backup_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).map_err(|_| format!("Save backup {} not found", backup_id))?;
+    let rom = file_path.ok_or("The hack has no ROM to restore the save next to")?;
+
+    backup_save_impl(conn, backup_root, hack_id, REASON_RESTORE)?;
+    fs::copy(backup_root.join(&file_name), srm_path(&rom))
+        .map_err(|e| format!("Failed to restore save: {}", e))?;
+    Ok(())
+}
+
+#[command]
+pub fn export_save_backup(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    backup_id: i64,
+    destination: String,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    export_save_backup_impl(&conn, &backup_root(&app)?, backup_id, Path::new(&destination))
+}
+
+pub fn export_save_backup_impl(conn: &Connection, backup_root: &Path, backup_id: i64, destination: &Path) -> Result<(), String> {
+    let file_name: String = conn.query_row(
+        "SELECT file_name FROM save_backups WHERE id = ?1",
+        params![backup_id],
+        |row| row.get(0),
+    ).map_err(|_| format!("Save backup {} not found", backup_id))?;
+
+    fs::copy(backup_root.join(file_name), destination)
+        .map_err(|e| format!("Failed to export save to {}: {}", destination.display(), e))?;
+    Ok(())
+}
+
+/// Fails if moving `from_rom`'s save next to `to_rom` would have to overwrite a different
+/// save already there, or leave the old one behind. Call before moving the ROM.
+pub(crate) fn check_save_move(from_rom: &Path, to_rom: &Path) -> Result<(), String> {
+    let from = srm_path(from_rom);
+    let to = srm_path(to_rom);
+    if from == to || !from.exists() || !to.exists() {
+        return Ok(());
+    }
+    match (fs::read(&from), fs::read(&to)) {
+        (Ok(old), Ok(existing)) if old == existing => Ok(()),
+        _ => Err(format!("A different save already exists at {}", to.display())),
+    }
+}
+
+/// Moves a ROM's save file along with it. A different save at the destination is never
+/// overwritten; see `check_save_move`.
+pub(crate) fn move_save(from_rom: &Path, to_rom: &Path) -> Result<(), String> {
+    let from = srm_path(from_rom);
+    let to = srm_path(to_rom);
+    if from == to || !from.exists() {
+        return Ok(());
+    }
+    check_save_move(from_rom, to_rom)?;
+    if to.exists() {
+        // The same save is already there
+        return fs::remove_file(&from).map_err(|e| format!("Failed to remove old save file: {}", e));
+    }
+    // Rename fails across filesystems, so fall back to copy + delete
+    if fs::rename(&from, &to).is_err() {
+        fs::copy(&from, &to).map_err(|e| format!("Failed to copy save file: {}", e))?;
+        fs::remove_file(&from).map_err(|e| format!("Failed to remove old save file: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use tempfile::TempDir;
+
+    fn setup(dir: &Path) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO hacks (name, file_path) VALUES ('Test Hack', ?1)",
+            params![dir.join("Hack.sfc").to_string_lossy()],
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_backups_skip_unchanged_saves() {
+        let temp_dir = TempDir::new().unwrap();
+        let backups = temp_dir.path().join(SAVE_BACKUP_DIR);
+        let conn = setup(temp_dir.path());
+
+        // No save yet
+        assert!(backup_save_impl(&conn, &backups, 1, REASON_MANUAL).unwrap().is_none());
+
+        fs::write(temp_dir.path().join("Hack.srm"), b"first").unwrap();
+        assert!(backup_save_impl(&conn, &backups, 1, REASON_SESSION_END).unwrap().is_some());
+        assert!(backup_save_impl(&conn, &backups, 1, REASON_SESSION_END).unwrap().is_none());
+
+        fs::write(temp_dir.path().join("Hack.srm"), b"second").unwrap();
+        let backup = backup_save_impl(&conn, &backups, 1, REASON_MANUAL).unwrap().unwrap();
+        assert_eq!(backup.size, 6);
+
+        let history = get_save_backups_impl(&conn, 1).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].reason, REASON_MANUAL);
+    }
+
+    #[test]
+    fn test_restore_backs_up_current_save_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let backups = temp_dir.path().join(SAVE_BACKUP_DIR);
+        let srm = temp_dir.path().join("Hack.srm");
+        let conn = setup(temp_dir.path());
+
+        fs::write(&srm, b"old progress").unwrap();
+        let old = backup_save_impl(&conn, &backups, 1, REASON_SESSION_END).unwrap().unwrap();
+        fs::write(&srm, b"new progress").unwrap();
+
+        restore_save_backup_impl(&conn, &backups, old.id).unwrap();
+        assert_eq!(fs::read(&srm).unwrap(), b"old progress");
+
+        let history = get_save_backups_impl(&conn, 1).unwrap();
+        assert_eq!(history[0].reason, REASON_RESTORE);
+        let export = temp_dir.path().join("export.srm");
+        export_save_backup_impl(&conn, &backups, history[0].id, &export).unwrap();
+        assert_eq!(fs::read(&export).unwrap(), b"new progress");
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let temp_dir = TempDir::new().unwrap();
+        let backups = temp_dir.path().join(SAVE_BACKUP_DIR);
+        let conn = setup(temp_dir.path());
+
+        for i in 0..MAX_BACKUPS_PER_HACK + 2 {
+            fs::write(temp_dir.path().join("Hack.srm"), i.to_string()).unwrap();
+            backup_save_impl(&conn, &backups, 1, REASON_SESSION_END).unwrap();
+        }
+        let history = get_save_backups_impl(&conn, 1).unwrap();
+        assert_eq!(history.len() as i64, MAX_BACKUPS_PER_HACK);
+        assert_eq!(fs::read_dir(backups.join("hack_1")).unwrap().count() as i64, MAX_BACKUPS_PER_HACK);
+    }
+
+    #[test]
+    fn test_move_save_never_leaves_a_save_behind() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (old_rom, new_rom) = (dir.join("Old.sfc"), dir.join("New.sfc"));
+
+        fs::write(dir.join("Old.srm"), b"progress").unwrap();
+        move_save(&old_rom, &new_rom).unwrap();
+        assert_eq!(fs::read(dir.join("New.srm")).unwrap(), b"progress");
+        assert!(!dir.join("Old.srm").exists());
+
+        // A different save at the destination is neither overwritten nor silently skipped
+        fs::write(dir.join("Old.srm"), b"other progress").unwrap();
+        assert!(check_save_move(&old_rom, &new_rom).is_err());
+        assert!(move_save(&old_rom, &new_rom).is_err());
+        assert_eq!(fs::read(dir.join("New.srm")).unwrap(), b"progress");
+        assert_eq!(fs::read(dir.join("Old.srm")).unwrap(), b"other progress");
+
+        // An identical copy there just replaces the old one
+        fs::write(dir.join("Old.srm"), b"progress").unwrap();
+        move_save(&old_rom, &new_rom).unwrap();
+        assert!(!dir.join("Old.srm").exists());
+    }
+}
@@ -206,12 +206,17 @@ pub async fn sync_database(
             .get("type")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
+        // SMWC reports the exit count as text such as "96 exit(s)"
+        let total_exits = api_hack.fields
+            .get("length")
+            .and_then(|v| v.as_str())
+            .and_then(parse_exit_count);
         
         if exists {
             // Update existing hack (update metadata, preserve user data like file_path)
             conn.execute(
                 "UPDATE hacks SET name = ?1, authors = ?2, release_date = ?3, description = ?4, 
-                 images = ?5, tags = ?6, rating = ?7, downloads = ?8, difficulty = ?9, type = ?10, download_url = ?11 WHERE api_id = ?12",
+                 images = ?5, tags = ?6, rating = ?7, downloads = ?8, difficulty = ?9, type = ?10, download_url = ?11, total_exits = ?12 WHERE api_id = ?13",
                 params![
                     hack_name.clone(),
                     authors_json,
@@ -224,14 +229,15 @@ pub async fn sync_database(
                     difficulty,
                     hack_type,
                     api_hack.download_url,
+                    total_exits,
                     api_id_str
                 ],
             ).map_err(|e| format!("Failed to update hack '{}': {}", hack_name, e))?;
         } else {
             // Insert new hack (explicitly set file_path to NULL for synced hacks)
             conn.execute(
-                "INSERT INTO hacks (name, api_id, file_path, authors, release_date, description, images, tags, rating, downloads, difficulty, type, download_url) 
-                 VALUES (?1, ?2, NULL, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                "INSERT INTO hacks (name, api_id, file_path, authors, release_date, description, images, tags, rating, downloads, difficulty, type, download_url, total_exits) 
+                 VALUES (?1, ?2, NULL, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
                 params![
                     hack_name.clone(),
                     api_id_str,
@@ -244,7 +250,8 @@ pub async fn sync_database(
                     api_hack.downloads as i64,
                     difficulty,
                     hack_type,
-                    api_hack.download_url
+                    api_hack.download_url,
+                    total_exits
                 ],
             ).map_err(|e| format!("Failed to insert hack '{}': {}", hack_name, e))?;
             synced_count += 1;
@@ -261,6 +268,11 @@ pub async fn sync_database(
     Ok(synced_count)
 }
 
+fn parse_exit_count(length: &str) -> Option<i64> {
+    let digits: String = length.trim().chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
 #[command]
 pub fn add_sample_hacks(
     state: tauri::State<AppState>,
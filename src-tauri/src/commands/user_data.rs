@@ -0,0 +1,296 @@
+use tauri::command;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use rusqlite::{params, OptionalExtension};
+
+pub const PLAY_STATUSES: [&str; 4] = ["backlog", "playing", "beaten", "dropped"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserHackData {
+    pub hack_id: u32,
+    pub play_status: Option<String>, // "backlog", "playing", "beaten", "dropped"
+    pub personal_rating: Option<f64>, // 0.0 - 5.0
+    pub notes: Option<String>,
+    pub favorite: bool,
+    pub user_tags: Vec<String>,
+    pub updated_at: Option<i64>, // UNIX timestamp in seconds
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserHackData {
+    pub hack_id: u32,
+    pub play_status: Option<String>,
+    pub personal_rating: Option<f64>,
+    pub notes: Option<String>,
+    pub favorite: bool,
+    pub user_tags: Vec<String>,
+}
+
+#[command]
+pub fn get_user_hack_data(
+    state: tauri::State<AppState>,
+    hack_id: u32,
+) -> Result<UserHackData, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    get_user_hack_data_impl(&conn, hack_id)
+}
+
+#[command]
+pub fn update_user_hack_data(
+    state: tauri::State<AppState>,
+    data: UpdateUserHackData,
+) -> Result<UserHackData, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    update_user_hack_data_impl(&conn, data)
+}
+
+#[command]
+pub fn set_hack_favorite(
+    state: tauri::State<AppState>,
+    hack_id: u32,
+    favorite: bool,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    set_hack_favorite_impl(&conn, hack_id, favorite)
+}
+
+#[command]
+pub fn set_hack_play_status(
+    state: tauri::State<AppState>,
+    hack_id: u32,
+    play_status: Option<String>,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    set_hack_play_status_impl(&conn, hack_id, play_status)
+}
+
+pub fn get_user_hack_data_impl(conn: &rusqlite::Connection, hack_id: u32) -> Result<UserHackData, String> {
+    let data = conn.query_row(
+        "SELECT hack_id, play_status, personal_rating, notes, favorite, user_tags, updated_at
+         FROM user_hack_data WHERE hack_id = ?1",
+        params![hack_id],
+        |row| {
+            let user_tags: Option<String> = row.get(5)?;
+            Ok(UserHackData {
+                hack_id: row.get(0)?,
+                play_status: row.get(1)?,
+                personal_rating: row.get(2)?,
+                notes: row.get(3)?,
+                favorite: row.get(4)?,
+                user_tags: user_tags
+                    .and_then(|t| serde_json::from_str(&t).ok())
+                    .unwrap_or_default(),
+                updated_at: row.get(6)?,
+            })
+        },
+    ).optional().map_err(|e| e.to_string())?;
+
+    // Hacks without a row yet simply have no user data
+    Ok(data.unwrap_or(UserHackData {
+        hack_id,
+        play_status: None,
+        personal_rating: None,
+        notes: None,
+        favorite: false,
+        user_tags: Vec::new(),
+        updated_at: None,
+    }))
+}
+
+pub fn update_user_hack_data_impl(
+    conn: &rusqlite::Connection,
+    data: UpdateUserHackData,
+) -> Result<UserHackData, String> {
+    validate_play_status(data.play_status.as_deref())?;
+    if let Some(rating) = data.personal_rating {
+        if !(0.0..=5.0).contains(&rating) {
+            return Err(format!("Personal rating must be between 0 and 5, got {}", rating));
+        }
+    }
+
+    let mut user_tags: Vec<String> = data.user_tags
+        .iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    user_tags.sort();
+    user_tags.dedup();
+    let tags_json = serde_json::to_string(&user_tags).map_err(|e| e.to_string())?;
+
+    let notes = data.notes.filter(|n| !n.trim().is_empty());
+
+    conn.execute(
+        "INSERT INTO user_hack_data (hack_id, play_status, personal_rating, notes, favorite, user_tags, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(hack_id) DO UPDATE SET
+         play_status = excluded.play_status,
+         personal_rating = excluded.personal_rating,
+         notes = excluded.notes,
+         favorite = excluded.favorite,
+         user_tags = excluded.user_tags,
+         updated_at = excluded.updated_at",
+        params![
+            data.hack_id,
+            data.play_status,
+            data.personal_rating,
+            notes,
+            data.favorite,
+            tags_json,
+            now()?
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    get_user_hack_data_impl(conn, data.hack_id)
+}
+
+/// Sets only the favorite flag, leaving the rest of the hack's user data as it is.
+pub fn set_hack_favorite_impl(conn: &rusqlite::Connection, hack_id: u32, favorite: bool) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO user_hack_data (hack_id, favorite, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(hack_id) DO UPDATE SET favorite = excluded.favorite, updated_at = excluded.updated_at",
+        params![hack_id, favorite, now()?],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Sets only the play status, leaving the rest of the hack's user data as it is.
+pub fn set_hack_play_status_impl(
+    conn: &rusqlite::Connection,
+    hack_id: u32,
+    play_status: Option<String>,
+) -> Result<(), String> {
+    validate_play_status(play_status.as_deref())?;
+
+    conn.execute(
+        "INSERT INTO user_hack_data (hack_id, play_status, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(hack_id) DO UPDATE SET play_status = excluded.play_status, updated_at = excluded.updated_at",
+        params![hack_id, play_status, now()?],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn validate_play_status(status: Option<&str>) -> Result<(), String> {
+    match status {
+        Some(s) if !PLAY_STATUSES.contains(&s) => Err(format!(
+            "Invalid play status '{}'. Expected one of: {}",
+            s,
+            PLAY_STATUSES.join(", ")
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn now() -> Result<i64, String> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{connection_manager, init_db};
+    use r2d2::ManageConnection;
+    use rusqlite::Connection;
+
+    fn setup() -> Connection {
+        let conn = connection_manager(":memory:").connect().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name, api_id) VALUES ('Invictus', '4')", []).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_update_and_get_user_data() {
+        let conn = setup();
+
+        let empty = get_user_hack_data_impl(&conn, 1).unwrap();
+        assert!(!empty.favorite);
+        assert!(empty.play_status.is_none());
+
+        let saved = update_user_hack_data_impl(&conn, UpdateUserHackData {
+            hack_id: 1,
+            play_status: Some("playing".to_string()),
+            personal_rating: Some(4.5),
+            notes: Some("Castle 2 cape trick".to_string()),
+            favorite: true,
+            user_tags: vec!["practice".to_string(), " stream ".to_string(), "practice".to_string()],
+        }).unwrap();
+
+        assert_eq!(saved.play_status.as_deref(), Some("playing"));
+        assert_eq!(saved.personal_rating, Some(4.5));
+        assert!(saved.favorite);
+        assert_eq!(saved.user_tags, vec!["practice".to_string(), "stream".to_string()]);
+    }
+
+    #[test]
+    fn test_update_rejects_invalid_values() {
+        let conn = setup();
+
+        let invalid_status = update_user_hack_data_impl(&conn, UpdateUserHackData {
+            hack_id: 1,
+            play_status: Some("finished".to_string()),
+            personal_rating: None,
+            notes: None,
+            favorite: false,
+            user_tags: Vec::new(),
+        });
+        assert!(invalid_status.is_err());
+
+        let invalid_rating = update_user_hack_data_impl(&conn, UpdateUserHackData {
+            hack_id: 1,
+            play_status: None,
+            personal_rating: Some(7.0),
+            notes: None,
+            favorite: false,
+            user_tags: Vec::new(),
+        });
+        assert!(invalid_rating.is_err());
+    }
+
+    #[test]
+    fn test_favorite_and_play_status_keep_other_fields() {
+        let conn = setup();
+
+        // Both create the row when there is none yet
+        set_hack_favorite_impl(&conn, 1, true).unwrap();
+        let data = get_user_hack_data_impl(&conn, 1).unwrap();
+        assert!(data.favorite);
+        assert!(data.play_status.is_none());
+
+        update_user_hack_data_impl(&conn, UpdateUserHackData {
+            hack_id: 1,
+            play_status: Some("playing".to_string()),
+            personal_rating: Some(4.0),
+            notes: Some("Castle 2 cape trick".to_string()),
+            favorite: true,
+            user_tags: vec!["practice".to_string()],
+        }).unwrap();
+
+        set_hack_play_status_impl(&conn, 1, Some("beaten".to_string())).unwrap();
+        set_hack_favorite_impl(&conn, 1, false).unwrap();
+        let data = get_user_hack_data_impl(&conn, 1).unwrap();
+        assert_eq!(data.play_status.as_deref(), Some("beaten"));
+        assert!(!data.favorite);
+        assert_eq!(data.personal_rating, Some(4.0));
+        assert_eq!(data.notes.as_deref(), Some("Castle 2 cape trick"));
+        assert_eq!(data.user_tags, vec!["practice".to_string()]);
+
+        set_hack_play_status_impl(&conn, 1, None).unwrap();
+        assert!(get_user_hack_data_impl(&conn, 1).unwrap().play_status.is_none());
+        assert!(set_hack_play_status_impl(&conn, 1, Some("finished".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_user_data_requires_an_existing_hack() {
+        let conn = setup();
+
+        assert!(set_hack_favorite_impl(&conn, 99, true).is_err());
+        assert!(set_hack_play_status_impl(&conn, 99, Some("backlog".to_string())).is_err());
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM user_hack_data", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -9,6 +9,17 @@ pub struct Config {
     pub enable_debug_logging: Option<bool>,
     pub enable_auto_tracking: Option<bool>,
     pub additional_args: Option<String>,
+    pub output_name_template: Option<String>,
+    pub idle_timeout_seconds: Option<u32>,
+    pub hardware_rom_folder: Option<String>,
+    pub usb2snes_endpoints: Option<String>,
+    pub usb2snes_device: Option<String>,
+    pub memory_source: Option<String>,
+    pub retroarch_address: Option<String>,
+    pub livesplit_enabled: Option<bool>,
+    pub livesplit_address: Option<String>,
+    /// JSON list of `SplitRule`s, one per split.
+    pub livesplit_splits: Option<String>,
 }
 
 impl Config {
@@ -20,6 +31,16 @@ impl Config {
             enable_debug_logging: None,
             enable_auto_tracking: None,
             additional_args: None,
+            output_name_template: None,
+            idle_timeout_seconds: None,
+            hardware_rom_folder: None,
+            usb2snes_endpoints: None,
+            usb2snes_device: None,
+            memory_source: None,
+            retroarch_address: None,
+            livesplit_enabled: None,
+            livesplit_address: None,
+            livesplit_splits: None,
         };
         
         let mut stmt = conn.prepare("SELECT key, value FROM config")?;
@@ -36,6 +57,16 @@ impl Config {
                 "enable_debug_logging" => config.enable_debug_logging = Some(value == "true"),
                 "enable_auto_tracking" => config.enable_auto_tracking = Some(value == "true"),
                 "additional_args" => config.additional_args = Some(value),
+                "output_name_template" => config.output_name_template = Some(value),
+                "idle_timeout_seconds" => config.idle_timeout_seconds = value.parse().ok(),
+                "hardware_rom_folder" => config.hardware_rom_folder = Some(value),
+                "usb2snes_endpoints" => config.usb2snes_endpoints = Some(value),
+                "usb2snes_device" => config.usb2snes_device = Some(value),
+                "memory_source" => config.memory_source = Some(value),
+                "retroarch_address" => config.retroarch_address = Some(value),
+                "livesplit_enabled" => config.livesplit_enabled = Some(value == "true"),
+                "livesplit_address" => config.livesplit_address = Some(value),
+                "livesplit_splits" => config.livesplit_splits = Some(value),
                 _ => {}
             }
         }
@@ -121,6 +152,136 @@ impl Config {
                 conn.execute("DELETE FROM config WHERE key = ?1", params!["additional_args"])?;
             }
         }
+
+        // Save or delete output_name_template
+        match &self.output_name_template {
+            Some(template) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["output_name_template", template],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["output_name_template"])?;
+            }
+        }
+
+        // Save or delete idle_timeout_seconds
+        match &self.idle_timeout_seconds {
+            Some(seconds) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["idle_timeout_seconds", seconds.to_string()],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["idle_timeout_seconds"])?;
+            }
+        }
+
+        // Save or delete hardware_rom_folder
+        match &self.hardware_rom_folder {
+            Some(folder) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["hardware_rom_folder", folder],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["hardware_rom_folder"])?;
+            }
+        }
+
+        // Save or delete usb2snes_endpoints
+        match &self.usb2snes_endpoints {
+            Some(endpoints) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["usb2snes_endpoints", endpoints],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["usb2snes_endpoints"])?;
+            }
+        }
+
+        // Save or delete usb2snes_device
+        match &self.usb2snes_device {
+            Some(device) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["usb2snes_device", device],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["usb2snes_device"])?;
+            }
+        }
+
+        // Save or delete memory_source
+        match &self.memory_source {
+            Some(source) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["memory_source", source],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["memory_source"])?;
+            }
+        }
+
+        // Save or delete retroarch_address
+        match &self.retroarch_address {
+            Some(address) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["retroarch_address", address],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["retroarch_address"])?;
+            }
+        }
+
+        // Save or delete livesplit_enabled
+        match &self.livesplit_enabled {
+            Some(enable) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["livesplit_enabled", enable.to_string()],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["livesplit_enabled"])?;
+            }
+        }
+
+        // Save or delete livesplit_address
+        match &self.livesplit_address {
+            Some(address) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["livesplit_address", address],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["livesplit_address"])?;
+            }
+        }
+
+        // Save or delete livesplit_splits
+        match &self.livesplit_splits {
+            Some(splits) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO config (key, value) VALUES (?1, ?2)",
+                    params!["livesplit_splits", splits],
+                )?;
+            }
+            None => {
+                conn.execute("DELETE FROM config WHERE key = ?1", params!["livesplit_splits"])?;
+            }
+        }
         
         Ok(())
     }
@@ -144,6 +305,16 @@ mod tests {
             enable_debug_logging: Some(true),
             enable_auto_tracking: Some(true),
             additional_args: Some("--arg1 --arg2".to_string()),
+            output_name_template: Some("{difficulty}/{name}.sfc".to_string()),
+            idle_timeout_seconds: Some(120),
+            hardware_rom_folder: Some("/romhacks".to_string()),
+            usb2snes_endpoints: Some("ws://127.0.0.1:23074, ws://127.0.0.1:8080".to_string()),
+            usb2snes_device: Some("SD2SNES".to_string()),
+            memory_source: Some("retroarch".to_string()),
+            retroarch_address: Some("127.0.0.1:55355".to_string()),
+            livesplit_enabled: Some(true),
+            livesplit_address: Some("127.0.0.1:16834".to_string()),
+            livesplit_splits: Some(r#"[{"rule":"credits"}]"#.to_string()),
         };
         
         config.save(&conn).unwrap();
@@ -152,6 +323,16 @@ mod tests {
         assert_eq!(loaded.emulator_path, config.emulator_path);
         assert_eq!(loaded.output_directory, config.output_directory);
         assert_eq!(loaded.enable_debug_logging, config.enable_debug_logging);
+        assert_eq!(loaded.output_name_template, config.output_name_template);
+        assert_eq!(loaded.idle_timeout_seconds, config.idle_timeout_seconds);
+        assert_eq!(loaded.hardware_rom_folder, config.hardware_rom_folder);
+        assert_eq!(loaded.usb2snes_endpoints, config.usb2snes_endpoints);
+        assert_eq!(loaded.usb2snes_device, config.usb2snes_device);
+        assert_eq!(loaded.memory_source, config.memory_source);
+        assert_eq!(loaded.retroarch_address, config.retroarch_address);
+        assert_eq!(loaded.livesplit_enabled, config.livesplit_enabled);
+        assert_eq!(loaded.livesplit_address, config.livesplit_address);
+        assert_eq!(loaded.livesplit_splits, config.livesplit_splits);
     }
 }
 
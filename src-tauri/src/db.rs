@@ -1,5 +1,12 @@
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Connection, Result};
 
+/// Connections for the app's database. SQLite leaves foreign keys off unless each
+/// connection enables them, so the schema's cascades only work on these.
+pub fn connection_manager(path: impl AsRef<std::path::Path>) -> SqliteConnectionManager {
+    SqliteConnectionManager::file(path).with_init(|conn| conn.pragma_update(None, "foreign_keys", true))
+}
+
 pub fn init_db(conn: &Connection) -> Result<()> {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS hacks (
@@ -20,7 +27,10 @@ pub fn init_db(conn: &Connection) -> Result<()> {
                 type TEXT,
                 download_url TEXT,
                 readme TEXT,
-                rom_checksum TEXT
+                rom_checksum TEXT,
+                rom_md5 TEXT,
+                total_exits INTEGER,
+                memory_map TEXT
             )",
             [],
         )?;
@@ -77,6 +87,10 @@ fn migrate_db(conn: &Connection) -> Result<()> {
     };
     
     if needs_migration {
+        // Dropping the old table would cascade into every table referencing hacks
+        let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        conn.pragma_update(None, "foreign_keys", false)?;
+
         // Recreate table with all columns
         conn.execute("BEGIN TRANSACTION", [])?;
         let should_recreate = if let Ok(sql) = conn.query_row::<String, _, _>(
@@ -109,7 +123,10 @@ fn migrate_db(conn: &Connection) -> Result<()> {
                     type TEXT,
                     download_url TEXT,
                     readme TEXT,
-                    rom_checksum TEXT
+                    rom_checksum TEXT,
+                    rom_md5 TEXT,
+                    total_exits INTEGER,
+                    memory_map TEXT
                 )",
                 [],
             )?;
@@ -145,8 +162,12 @@ fn migrate_db(conn: &Connection) -> Result<()> {
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN download_url TEXT", []);
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN readme TEXT", []);
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN rom_checksum TEXT", []);
+        let _ = conn.execute("ALTER TABLE hacks ADD COLUMN rom_md5 TEXT", []);
+        let _ = conn.execute("ALTER TABLE hacks ADD COLUMN total_exits INTEGER", []);
+        let _ = conn.execute("ALTER TABLE hacks ADD COLUMN memory_map TEXT", []);
         
         conn.execute("COMMIT", [])?;
+        conn.pragma_update(None, "foreign_keys", foreign_keys)?;
     } else {
         // Add missing columns if they don't exist.
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN authors TEXT", []);
@@ -160,6 +181,9 @@ fn migrate_db(conn: &Connection) -> Result<()> {
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN type TEXT", []);
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN download_url TEXT", []);
         let _ = conn.execute("ALTER TABLE hacks ADD COLUMN readme TEXT", []);
+        let _ = conn.execute("ALTER TABLE hacks ADD COLUMN rom_md5 TEXT", []);
+        let _ = conn.execute("ALTER TABLE hacks ADD COLUMN total_exits INTEGER", []);
+        let _ = conn.execute("ALTER TABLE hacks ADD COLUMN memory_map TEXT", []);
     }
     
     let _ = conn.execute(
@@ -191,10 +215,19 @@ fn migrate_db(conn: &Connection) -> Result<()> {
             duration_seconds INTEGER DEFAULT 0,
             save_slot INTEGER,
             exit_count INTEGER DEFAULT 0,
+            duration_ms INTEGER DEFAULT 0,
+            end_reason TEXT,
             FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
         )",
         [],
     )?;
+    let _ = conn.execute("ALTER TABLE play_sessions ADD COLUMN duration_ms INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE play_sessions ADD COLUMN end_reason TEXT", []);
+    // Sessions recorded before millisecond timing only have whole seconds
+    let _ = conn.execute(
+        "UPDATE play_sessions SET duration_ms = duration_seconds * 1000 WHERE duration_ms = 0 AND duration_seconds > 0",
+        [],
+    );
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS level_timings (
@@ -202,12 +235,219 @@ fn migrate_db(conn: &Connection) -> Result<()> {
             hack_id INTEGER NOT NULL,
             level_id INTEGER NOT NULL,
             duration_seconds INTEGER DEFAULT 0,
+            duration_ms INTEGER DEFAULT 0,
             visit_count INTEGER DEFAULT 0,
+            death_count INTEGER DEFAULT 0,
+            retry_count INTEGER DEFAULT 0,
+            clear_count INTEGER DEFAULT 0,
             FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE,
             UNIQUE(hack_id, level_id)
         )",
         [],
     )?;
+    let _ = conn.execute("ALTER TABLE level_timings ADD COLUMN duration_ms INTEGER DEFAULT 0", []);
+    let _ = conn.execute(
+        "UPDATE level_timings SET duration_ms = duration_seconds * 1000 WHERE duration_ms = 0 AND duration_seconds > 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE level_timings ADD COLUMN death_count INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE level_timings ADD COLUMN retry_count INTEGER DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE level_timings ADD COLUMN clear_count INTEGER DEFAULT 0", []);
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_level_stats (
+            session_id INTEGER NOT NULL,
+            hack_id INTEGER NOT NULL,
+            level_id INTEGER NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            deaths INTEGER NOT NULL DEFAULT 0,
+            retries INTEGER NOT NULL DEFAULT 0,
+            clears INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (session_id, level_id),
+            FOREIGN KEY (session_id) REFERENCES play_sessions(id) ON DELETE CASCADE,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS level_clears (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hack_id INTEGER NOT NULL,
+            session_id INTEGER,
+            level_id INTEGER NOT NULL,
+            event_id INTEGER NOT NULL,
+            exit_type TEXT NOT NULL,
+            cleared_at DATETIME NOT NULL,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE,
+            FOREIGN KEY (session_id) REFERENCES play_sessions(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_level_clears_hack_id ON level_clears(hack_id)",
+        [],
+    )?;
+
+    // Every individual level attempt, for personal bests and split history
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS level_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hack_id INTEGER NOT NULL,
+            session_id INTEGER,
+            level_id INTEGER NOT NULL,
+            start_time DATETIME NOT NULL,
+            end_time DATETIME,
+            outcome TEXT,
+            duration_ms INTEGER DEFAULT 0,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE,
+            FOREIGN KEY (session_id) REFERENCES play_sessions(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_level_attempts_hack_level ON level_attempts(hack_id, level_id)",
+        [],
+    )?;
+
+    // Level names, entered by the user or imported from the hack's level list
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS level_labels (
+            hack_id INTEGER NOT NULL,
+            level_id INTEGER NOT NULL,
+            label TEXT NOT NULL,
+            source TEXT NOT NULL,
+            PRIMARY KEY (hack_id, level_id),
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Completions detected at the credits, waiting for the user to confirm them
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_completions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hack_id INTEGER NOT NULL,
+            session_id INTEGER,
+            route TEXT NOT NULL,
+            completed_at INTEGER NOT NULL,
+            play_time_seconds INTEGER,
+            exits_found INTEGER NOT NULL,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE,
+            FOREIGN KEY (session_id) REFERENCES play_sessions(id) ON DELETE SET NULL,
+            UNIQUE(hack_id, route)
+        )",
+        [],
+    )?;
+
+    // Versioned copies of each hack's save file, stored under the app data directory
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS save_backups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hack_id INTEGER NOT NULL,
+            file_name TEXT NOT NULL,
+            md5 TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_save_backups_hack_id ON save_backups(hack_id)",
+        [],
+    )?;
+
+    // Engine memory maps imported by the user, assigned via hacks.memory_map
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS memory_maps (
+            name TEXT PRIMARY KEY,
+            definition TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // ROMs written to a cart's SD card, so unchanged files aren't uploaded again
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS hardware_uploads (
+            remote_path TEXT PRIMARY KEY,
+            hack_id INTEGER,
+            rom_md5 TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            uploaded_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+
+    // User-owned metadata lives outside `hacks` so SMWC syncs never touch it
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS user_hack_data (
+            hack_id INTEGER PRIMARY KEY,
+            play_status TEXT,
+            personal_rating REAL,
+            notes TEXT,
+            favorite INTEGER NOT NULL DEFAULT 0,
+            user_tags TEXT,
+            updated_at INTEGER,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS collections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS collection_hacks (
+            collection_id INTEGER NOT NULL,
+            hack_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            added_at INTEGER NOT NULL,
+            PRIMARY KEY (collection_id, hack_id),
+            FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+            FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_collection_hacks_hack_id ON collection_hacks(hack_id)",
+        [],
+    )?;
+
+    // Sort indexes for keyset pagination in the library
+    for (index, column) in [
+        ("idx_hacks_name", "name"),
+        ("idx_hacks_release_date", "release_date"),
+        ("idx_hacks_rating", "rating"),
+        ("idx_hacks_downloads", "downloads"),
+        ("idx_hacks_last_played", "last_played"),
+    ] {
+        conn.execute(
+            &format!("CREATE INDEX IF NOT EXISTS {} ON hacks({}, id)", index, column),
+            [],
+        )?;
+    }
+    for (index, column) in [
+        ("idx_user_hack_data_personal_rating", "personal_rating"),
+        ("idx_user_hack_data_play_status", "play_status"),
+    ] {
+        conn.execute(
+            &format!("CREATE INDEX IF NOT EXISTS {} ON user_hack_data({}, hack_id)", index, column),
+            [],
+        )?;
+    }
     
     Ok(())
 }
@@ -320,4 +560,60 @@ mod tests {
         );
         assert!(result.is_ok(), "Should be able to insert NULL file_path now");
     }
+
+    #[test]
+    fn test_pooled_connections_enforce_foreign_keys() {
+        use r2d2::ManageConnection;
+
+        let conn = connection_manager(":memory:").connect().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute("INSERT INTO user_hack_data (hack_id, favorite) VALUES (1, 1)", []).unwrap();
+        conn.execute("INSERT INTO collections (name, created_at, updated_at) VALUES ('Kaizo', 0, 0)", []).unwrap();
+        conn.execute("INSERT INTO collection_hacks (collection_id, hack_id, position, added_at) VALUES (1, 1, 0, 0)", []).unwrap();
+
+        assert!(conn.execute("INSERT INTO user_hack_data (hack_id) VALUES (99)", []).is_err());
+
+        let count = |table: &str| conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0)).unwrap();
+        conn.execute("DELETE FROM collections WHERE id = 1", []).unwrap();
+        assert_eq!(count("collection_hacks"), 0);
+        conn.execute("DELETE FROM hacks WHERE id = 1", []).unwrap();
+        assert_eq!(count("user_hack_data"), 0);
+    }
+
+    #[test]
+    fn test_recreating_hacks_keeps_rows_referencing_them() {
+        use r2d2::ManageConnection;
+
+        let conn = connection_manager(":memory:").connect().unwrap();
+        conn.execute(
+            "CREATE TABLE hacks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                clean_rom_path TEXT,
+                api_id TEXT UNIQUE,
+                last_played DATETIME
+            )",
+            [],
+        ).unwrap();
+        conn.execute("INSERT INTO hacks (name, file_path) VALUES ('Test Hack', '/roms/test.sfc')", []).unwrap();
+        conn.execute(
+            "CREATE TABLE play_sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                hack_id INTEGER NOT NULL,
+                start_time DATETIME NOT NULL,
+                FOREIGN KEY (hack_id) REFERENCES hacks(id) ON DELETE CASCADE
+            )",
+            [],
+        ).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-01T00:00:00Z')", []).unwrap();
+
+        init_db(&conn).unwrap();
+
+        let sessions: i64 = conn.query_row("SELECT COUNT(*) FROM play_sessions", [], |row| row.get(0)).unwrap();
+        assert_eq!(sessions, 1);
+        let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert!(foreign_keys);
+    }
 }
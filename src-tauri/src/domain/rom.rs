@@ -20,6 +20,29 @@ impl RomValidator {
         // For now just Headerless.
         Ok(hash == "cdd3c8c37322978ca8669b34bc89c804") 
     }
+
+    /// Reads the internal SNES header checksum, trying LoROM (0x7FC0) before HiROM (0xFFC0).
+    /// The checksum is only accepted if it matches its complement.
+    pub fn header_checksum(rom: &[u8]) -> Option<u16> {
+        let get_checksum = |offset: usize| -> Option<u16> {
+            if rom.len() < offset + 0x20 { return None; }
+            let comp = ((rom[offset + 0x1D] as u16) << 8) | (rom[offset + 0x1C] as u16);
+            let sum = ((rom[offset + 0x1F] as u16) << 8) | (rom[offset + 0x1E] as u16);
+            if (comp ^ sum) == 0xFFFF { Some(sum) } else { None }
+        };
+
+        get_checksum(0x7FC0).or_else(|| get_checksum(0xFFC0))
+    }
+
+    /// Returns the `(md5, header checksum)` fingerprint stored for patched ROMs.
+    /// The checksum is an empty string when the header is not valid.
+    pub fn fingerprint(rom: &[u8]) -> (String, String) {
+        let md5 = format!("{:x}", md5::compute(rom));
+        let checksum = Self::header_checksum(rom)
+            .map(|sum| format!("{:04X}", sum))
+            .unwrap_or_default();
+        (md5, checksum)
+    }
 }
 
 #[cfg(test)]
@@ -36,5 +59,22 @@ mod tests {
         let hash = RomValidator::calculate_md5(file.path()).unwrap();
         assert_eq!(hash, "098f6bcd4621d373cade4e832627b4f6");
     }
+
+    #[test]
+    fn test_header_checksum_lorom() {
+        let mut rom = vec![0u8; 0x8000];
+        // Checksum 0x1234, complement 0xEDCB
+        rom[0x7FDC] = 0xCB;
+        rom[0x7FDD] = 0xED;
+        rom[0x7FDE] = 0x34;
+        rom[0x7FDF] = 0x12;
+        assert_eq!(RomValidator::header_checksum(&rom), Some(0x1234));
+
+        let (_, checksum) = RomValidator::fingerprint(&rom);
+        assert_eq!(checksum, "1234");
+
+        rom[0x7FDC] = 0x00;
+        assert_eq!(RomValidator::header_checksum(&rom), None);
+    }
 }
 
@@ -8,7 +8,8 @@ pub mod config;
 pub mod tracking;
 
 use state::AppState;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use tracking::events::TRACKING_EVENT;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -25,11 +26,28 @@ pub fn run() {
             commands::onboarding::validate_clean_rom,
             commands::onboarding::has_clean_rom,
             commands::patch::patch_rom, 
+            commands::patch::apply_output_template,
             commands::library::get_hacks,
             commands::library::get_hack_details,
             commands::library::get_filter_options,
             commands::library::delete_hack,
+            commands::integrity::verify_library,
+            commands::integrity::repair_library,
+            commands::user_data::get_user_hack_data,
+            commands::user_data::update_user_hack_data,
+            commands::user_data::set_hack_favorite,
+            commands::user_data::set_hack_play_status,
+            commands::collections::get_collections,
+            commands::collections::create_collection,
+            commands::collections::update_collection,
+            commands::collections::delete_collection,
+            commands::collections::add_hack_to_collection,
+            commands::collections::remove_hack_from_collection,
+            commands::collections::reorder_collection,
+            commands::collections::export_collection,
+            commands::collections::import_collection,
             commands::launcher::launch_hack,
+            commands::launcher::launch_hack_on_hardware,
             commands::launcher::save_config,
             commands::launcher::get_config,
             commands::sync::sync_database,
@@ -39,8 +57,30 @@ pub fn run() {
             commands::completions::update_completion,
             commands::completions::delete_completion,
             commands::completions::get_completion_summary,
+            commands::completions::get_pending_completions,
+            commands::completions::confirm_pending_completion,
+            commands::completions::dismiss_pending_completion,
+            commands::saves::get_save_backups,
+            commands::saves::create_save_backup,
+            commands::saves::restore_save_backup,
+            commands::saves::export_save_backup,
             crate::tracking::commands::get_tracking_status,
+            crate::tracking::commands::practice_action,
+            crate::tracking::commands::start_tracking_recording,
+            crate::tracking::commands::stop_tracking_recording,
+            crate::tracking::commands::replay_tracking_recording,
+            crate::tracking::commands::get_usb2snes_devices,
+            crate::tracking::commands::set_usb2snes_device,
             crate::tracking::commands::get_hack_stats,
+            crate::tracking::commands::get_session_stats,
+            crate::tracking::commands::get_level_records,
+            crate::tracking::commands::get_level_attempts,
+            crate::tracking::commands::set_level_label,
+            crate::tracking::commands::import_level_labels,
+            crate::tracking::commands::import_memory_map,
+            crate::tracking::commands::get_memory_maps,
+            crate::tracking::commands::set_hack_memory_map,
+            crate::tracking::commands::get_save_progress,
             crate::tracking::commands::clear_hack_stats,
             crate::tracking::commands::clear_all_tracking_data,
             commands::logs::get_log_content,
@@ -79,7 +119,30 @@ pub fn run() {
                 }
             }
             
-            app.manage(AppState::new(db_path_str));
+            let state = AppState::new(db_path_str);
+
+            // Forward live tracking events to the frontend
+            let mut tracking_events = state.tracking.subscribe();
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match tracking_events.recv().await {
+                        Ok(event) => {
+                            let _ = app_handle.emit(TRACKING_EVENT, event);
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            log::warn!("Dropped {} tracking events", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Back up the save file whenever a play session ends
+            state.tracking.back_up_saves_to(app_data_dir.join(commands::saves::SAVE_BACKUP_DIR));
+
+            app.manage(state);
             Ok(())
         })
 
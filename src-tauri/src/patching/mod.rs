@@ -1,3 +1,5 @@
+pub mod naming;
+
 use std::path::{Path, PathBuf};
 use std::fs;
 use flips;
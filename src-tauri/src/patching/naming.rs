@@ -0,0 +1,179 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Template used when none is configured; matches the historical flat layout.
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "{name}.sfc";
+
+/// Hack metadata available to output naming templates.
+#[derive(Debug, Clone, Default)]
+pub struct NamingFields {
+    pub id: i64,
+    pub name: String,
+    pub api_id: Option<String>,
+    pub difficulty: Option<String>,
+    pub hack_type: Option<String>,
+    pub authors: Option<String>, // JSON array as stored in `hacks.authors`
+}
+
+impl NamingFields {
+    fn first_author(&self) -> Option<String> {
+        let authors: serde_json::Value = serde_json::from_str(self.authors.as_deref()?).ok()?;
+        let first = authors.as_array()?.first()?;
+        first
+            .get("name")
+            .and_then(|n| n.as_str())
+            .or_else(|| first.as_str())
+            .map(|s| s.to_string())
+    }
+
+    fn value(&self, key: &str) -> Result<String, String> {
+        let value = match key {
+            "name" => Some(self.name.clone()),
+            "id" => Some(self.id.to_string()),
+            "api_id" => self.api_id.clone(),
+            "difficulty" => self.difficulty.clone(),
+            "type" => self.hack_type.clone(),
+            "author" => self.first_author(),
+            _ => return Err(format!("Unknown placeholder in output template: {{{}}}", key)),
+        };
+
+        Ok(value
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "Unknown".to_string()))
+    }
+}
+
+/// Replaces characters that are unsafe in file names. Path separators are
+/// replaced too, so a substituted value can never introduce a new folder.
+pub fn sanitize_component(value: &str) -> String {
+    let sanitized = value
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>();
+    sanitized.trim().to_string()
+}
+
+/// Renders a naming template such as `{difficulty}/{name} ({api_id}).sfc` into a
+/// path relative to the output directory.
+pub fn render_output_path(template: &str, fields: &NamingFields) -> Result<PathBuf, String> {
+    let template = if template.trim().is_empty() { DEFAULT_OUTPUT_TEMPLATE } else { template.trim() };
+
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in output template: {}", template))?;
+        let key = &rest[start + 1..start + end];
+        rendered.push_str(&sanitize_component(&fields.value(key)?));
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+
+    // Only plain folder/file components are allowed so outputs stay inside the output directory
+    let mut path = PathBuf::new();
+    for component in Path::new(&rendered.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => {
+                let part = part.to_string_lossy();
+                let part = part.trim();
+                if !part.is_empty() {
+                    path.push(part);
+                }
+            }
+            Component::CurDir => {}
+            _ => return Err(format!("Output template must be a relative path: {}", template)),
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        return Err(format!("Output template produced an empty file name: {}", template));
+    }
+
+    let has_rom_extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("sfc") || e.eq_ignore_ascii_case("smc"))
+        .unwrap_or(false);
+    if !has_rom_extension {
+        let file_name = format!("{}.sfc", path.file_name().unwrap_or_default().to_string_lossy());
+        path.set_file_name(file_name);
+    }
+
+    Ok(path)
+}
+
+/// Returns `path`, or the first `name (n).ext` variant for which `is_taken` is false.
+pub fn resolve_collision(path: &Path, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    if !is_taken(path) {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+
+    let mut counter = 2;
+    loop {
+        let file_name = match &extension {
+            Some(ext) => format!("{} ({}).{}", stem, counter, ext),
+            None => format!("{} ({})", stem, counter),
+        };
+        let candidate = path.with_file_name(file_name);
+        if !is_taken(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> NamingFields {
+        NamingFields {
+            id: 7,
+            name: "Kaizo: Mario World!".to_string(),
+            api_id: Some("123".to_string()),
+            difficulty: Some("Kaizo: Hard".to_string()),
+            hack_type: None,
+            authors: Some(r#"[{"id":1,"name":"T. Takemoto"}]"#.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_default_template_matches_legacy_naming() {
+        let path = render_output_path("", &fields()).unwrap();
+        assert_eq!(path, PathBuf::from("Kaizo_ Mario World_.sfc"));
+    }
+
+    #[test]
+    fn test_render_template_with_folders() {
+        let path = render_output_path("{difficulty}/{author}/{name} ({api_id}).sfc", &fields()).unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("Kaizo_ Hard").join("T_ Takemoto").join("Kaizo_ Mario World_ (123).sfc")
+        );
+
+        let path = render_output_path("{type}/{id}", &fields()).unwrap();
+        assert_eq!(path, PathBuf::from("Unknown").join("7.sfc"));
+    }
+
+    #[test]
+    fn test_render_template_rejects_invalid_templates() {
+        assert!(render_output_path("{nope}.sfc", &fields()).is_err());
+        assert!(render_output_path("{name", &fields()).is_err());
+        assert!(render_output_path("../{name}.sfc", &fields()).is_err());
+        assert!(render_output_path("/abs/{name}.sfc", &fields()).is_err());
+    }
+
+    #[test]
+    fn test_resolve_collision_appends_counter() {
+        let taken = [PathBuf::from("out/Hack.sfc"), PathBuf::from("out/Hack (2).sfc")];
+        let path = resolve_collision(Path::new("out/Hack.sfc"), |p| taken.iter().any(|t| t == p));
+        assert_eq!(path, PathBuf::from("out/Hack (3).sfc"));
+
+        let path = resolve_collision(Path::new("out/Other.sfc"), |p| taken.iter().any(|t| t == p));
+        assert_eq!(path, PathBuf::from("out/Other.sfc"));
+    }
+}
@@ -10,7 +10,7 @@ pub struct AppState {
 
 impl AppState {
     pub fn new(db_path: &str) -> Self {
-        let manager = SqliteConnectionManager::file(db_path);
+        let manager = crate::db::connection_manager(db_path);
         let pool = Pool::new(manager).expect("Failed to create pool");
         
         // Initialize DB
@@ -1,6 +1,13 @@
 use tauri::{State, command};
 use crate::state::AppState;
-use serde::Serialize;
+use crate::tracking::memory_map::MemoryMap;
+use crate::tracking::practice::PracticeAction;
+use crate::tracking::service::Usb2SnesDevice;
+use crate::tracking::sram::{self, SaveSlot};
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize)]
 pub struct TrackingStatus {
@@ -14,13 +21,83 @@ pub struct TrackingStatus {
 pub struct LevelTiming {
     pub level_id: i64,
     pub seconds: i64,
+    pub milliseconds: i64,
+    pub attempts: i64,
+    pub deaths: i64,
+    pub retries: i64,
+    pub clears: i64,
 }
 
+#[derive(Serialize)]
+pub struct SessionLevelStats {
+    pub level_id: i64,
+    pub attempts: i64,
+    pub deaths: i64,
+    pub retries: i64,
+    pub clears: i64,
+}
+
+#[derive(Serialize)]
+pub struct SessionStats {
+    pub session_id: i64,
+    pub start_time: String,
+    pub end_time: Option<String>,
+    /// Why the session was closed; `None` while it's still open.
+    pub end_reason: Option<String>,
+    /// Save file (0-2) played in the session, when the tracker saw it.
+    pub save_slot: Option<i64>,
+    pub duration_seconds: i64,
+    pub duration_ms: i64,
+    pub exit_count: i64,
+    pub levels: Vec<SessionLevelStats>,
+}
+
+/// Personal best and trend for one level, computed from its finished attempts.
+#[derive(Debug, Serialize)]
+pub struct LevelRecord {
+    pub level_id: i64,
+    pub label: Option<String>,
+    pub attempts: i64,
+    pub clears: i64,
+    pub deaths: i64,
+    pub exits: i64,
+    /// Fastest clear.
+    pub best_ms: Option<i64>,
+    pub average_ms: Option<i64>,
+    /// Average of the last `RECENT_CLEARS` clears.
+    pub recent_average_ms: Option<i64>,
+    /// Recent average minus overall average; negative means getting faster.
+    pub trend_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelAttemptRecord {
+    pub id: i64,
+    pub session_id: Option<i64>,
+    pub start_time: String,
+    pub end_time: Option<String>,
+    /// "clear", "death" or "exit"; `None` while the attempt is in progress or was cut short.
+    pub outcome: Option<String>,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LevelLabel {
+    pub level_id: i64,
+    pub label: String,
+}
+
+/// How many of the latest clears make up the recent average.
+const RECENT_CLEARS: usize = 5;
+
 #[derive(Serialize)]
 pub struct HackStats {
     pub total_play_time_seconds: i64,
+    pub total_play_time_ms: i64,
     pub session_count: i64,
     pub level_timings: Vec<LevelTiming>,
+    pub exits_found: i64,
+    pub total_exits: Option<i64>,
 }
 
 #[command]
@@ -34,14 +111,57 @@ pub async fn get_tracking_status(state: State<'_, AppState>) -> Result<TrackingS
     })
 }
 
+#[command]
+pub async fn get_usb2snes_devices(state: State<'_, AppState>) -> Result<Vec<Usb2SnesDevice>, String> {
+    state.tracking.list_devices().await
+}
+
+/// Persists the preferred device (a case-insensitive name pattern) and reconnects to it.
+#[command]
+pub async fn set_usb2snes_device(state: State<'_, AppState>, device: Option<String>) -> Result<(), String> {
+    {
+        let conn = state.db.get().map_err(|e| e.to_string())?;
+        let mut config = Config::load(&conn).map_err(|e| e.to_string())?;
+        config.usb2snes_device = device
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty());
+        config.save(&conn).map_err(|e| e.to_string())?;
+    }
+    state.tracking.reconnect().await;
+    Ok(())
+}
+
+#[command]
+pub async fn practice_action(state: State<'_, AppState>, action: PracticeAction) -> Result<(), String> {
+    state.tracking.apply_practice(action).await
+}
+
+/// Records every memory poll to `file_path` so the session can be replayed later.
+#[command]
+pub async fn start_tracking_recording(state: State<'_, AppState>, file_path: String) -> Result<(), String> {
+    state.tracking.start_recording(std::path::Path::new(&file_path)).await
+}
+
+#[command]
+pub async fn stop_tracking_recording(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.tracking.stop_recording().await)
+}
+
+/// Feeds a recording to the tracker in place of the device. `speed` scales the
+/// recorded timing; without it one recorded poll is played per tracker poll.
+#[command]
+pub async fn replay_tracking_recording(state: State<'_, AppState>, file_path: String, speed: Option<f64>) -> Result<(), String> {
+    state.tracking.replay(std::path::Path::new(&file_path), speed).await
+}
+
 #[command]
 pub async fn get_hack_stats(state: State<'_, AppState>, hack_id: i64) -> Result<HackStats, String> {
     let conn = state.db.get().map_err(|e| e.to_string())?;
     use rusqlite::OptionalExtension;
     
     // Total Play Time
-    let total_seconds: Option<i64> = conn.query_row(
-        "SELECT SUM(duration_seconds) FROM play_sessions WHERE hack_id = ?1",
+    let total_ms: Option<i64> = conn.query_row(
+        "SELECT SUM(duration_ms) FROM play_sessions WHERE hack_id = ?1",
         [hack_id],
         |row| row.get(0)
     ).optional().map_err(|e| e.to_string())?.flatten();
@@ -53,42 +173,526 @@ pub async fn get_hack_stats(state: State<'_, AppState>, hack_id: i64) -> Result<
     ).map_err(|e| e.to_string())?;
 
     // Level Timings
-    let mut stmt = conn.prepare("SELECT level_id, duration_seconds FROM level_timings WHERE hack_id = ?1").map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT level_id, duration_ms, visit_count, death_count, retry_count, clear_count
+         FROM level_timings WHERE hack_id = ?1"
+    ).map_err(|e| e.to_string())?;
     let level_timings = stmt.query_map([hack_id], |row| {
         Ok(LevelTiming {
             level_id: row.get(0)?,
-            seconds: row.get(1)?,
+            seconds: row.get::<_, Option<i64>>(1)?.unwrap_or(0) / 1000,
+            milliseconds: row.get::<_, Option<i64>>(1)?.unwrap_or(0),
+            attempts: row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+            deaths: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+            retries: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+            clears: row.get::<_, Option<i64>>(5)?.unwrap_or(0),
         })
     }).map_err(|e| e.to_string())?
     .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
 
+    // Exit Progress
+    let exits_found: i64 = conn.query_row(
+        "SELECT COUNT(DISTINCT event_id) FROM level_clears WHERE hack_id = ?1",
+        [hack_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+
+    let total_exits: Option<i64> = conn.query_row(
+        "SELECT total_exits FROM hacks WHERE id = ?1",
+        [hack_id],
+        |row| row.get(0)
+    ).optional().map_err(|e| e.to_string())?.flatten();
+
     Ok(HackStats {
-        total_play_time_seconds: total_seconds.unwrap_or(0),
+        total_play_time_seconds: total_ms.unwrap_or(0) / 1000,
+        total_play_time_ms: total_ms.unwrap_or(0),
         session_count,
         level_timings,
+        exits_found,
+        total_exits,
     })
 }
 
+#[command]
+pub async fn get_session_stats(state: State<'_, AppState>, hack_id: i64) -> Result<Vec<SessionStats>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, start_time, end_time, duration_ms, exit_count, end_reason, save_slot
+         FROM play_sessions WHERE hack_id = ?1 ORDER BY start_time DESC"
+    ).map_err(|e| e.to_string())?;
+    let mut sessions = stmt.query_map([hack_id], |row| {
+        Ok(SessionStats {
+            session_id: row.get(0)?,
+            start_time: row.get(1)?,
+            end_time: row.get(2)?,
+            end_reason: row.get(5)?,
+            save_slot: row.get(6)?,
+            duration_seconds: row.get::<_, Option<i64>>(3)?.unwrap_or(0) / 1000,
+            duration_ms: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+            exit_count: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+            levels: Vec::new(),
+        })
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT level_id, attempts, deaths, retries, clears
+         FROM session_level_stats WHERE session_id = ?1 ORDER BY level_id"
+    ).map_err(|e| e.to_string())?;
+    for session in sessions.iter_mut() {
+        session.levels = stmt.query_map([session.session_id], |row| {
+            Ok(SessionLevelStats {
+                level_id: row.get(0)?,
+                attempts: row.get(1)?,
+                deaths: row.get(2)?,
+                retries: row.get(3)?,
+                clears: row.get(4)?,
+            })
+        }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    }
+
+    Ok(sessions)
+}
+
 #[command]
 pub async fn clear_hack_stats(state: State<'_, AppState>, hack_id: i64) -> Result<(), String> {
     let conn = state.db.get().map_err(|e| e.to_string())?;
-    
-    conn.execute("DELETE FROM play_sessions WHERE hack_id = ?1", [hack_id])
+    clear_hack_stats_impl(&conn, hack_id)
+}
+
+#[command]
+pub async fn clear_all_tracking_data(state: State<'_, AppState>) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    clear_all_tracking_data_impl(&conn)
+}
+
+/// Tables holding tracked play, cleared together so none refers to deleted sessions.
+/// Pending completions carry play time summed from the sessions.
+const TRACKING_TABLES: [&str; 6] = [
+    "play_sessions",
+    "level_timings",
+    "level_clears",
+    "session_level_stats",
+    "level_attempts",
+    "pending_completions",
+];
+
+pub fn clear_hack_stats_impl(conn: &Connection, hack_id: i64) -> Result<(), String> {
+    for table in TRACKING_TABLES {
+        conn.execute(&format!("DELETE FROM {} WHERE hack_id = ?1", table), [hack_id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn clear_all_tracking_data_impl(conn: &Connection) -> Result<(), String> {
+    for table in TRACKING_TABLES {
+        conn.execute(&format!("DELETE FROM {}", table), [])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn get_level_records(state: State<'_, AppState>, hack_id: i64) -> Result<Vec<LevelRecord>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    get_level_records_impl(&conn, hack_id)
+}
+
+pub fn get_level_records_impl(conn: &Connection, hack_id: i64) -> Result<Vec<LevelRecord>, String> {
+    let labels = level_labels(conn, hack_id)?;
+
+    // Finished attempts, oldest first within each level
+    let mut stmt = conn.prepare(
+        "SELECT level_id, outcome, duration_ms FROM level_attempts
+         WHERE hack_id = ?1 AND outcome IS NOT NULL
+         ORDER BY level_id, start_time, id"
+    ).map_err(|e| e.to_string())?;
+    let attempts = stmt.query_map([hack_id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?.unwrap_or(0)))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    let mut by_level: BTreeMap<i64, Vec<(String, i64)>> = BTreeMap::new();
+    for (level_id, outcome, duration_ms) in attempts {
+        by_level.entry(level_id).or_default().push((outcome, duration_ms));
+    }
+
+    Ok(by_level
+        .into_iter()
+        .map(|(level_id, attempts)| {
+            let count = |outcome: &str| attempts.iter().filter(|(o, _)| o == outcome).count() as i64;
+            let clear_times: Vec<i64> = attempts
+                .iter()
+                .filter(|(o, _)| o == "clear")
+                .map(|(_, ms)| *ms)
+                .collect();
+            let recent = &clear_times[clear_times.len().saturating_sub(RECENT_CLEARS)..];
+            let average_ms = average(&clear_times);
+            let recent_average_ms = average(recent);
+
+            LevelRecord {
+                level_id,
+                label: labels.get(&level_id).cloned(),
+                attempts: attempts.len() as i64,
+                clears: clear_times.len() as i64,
+                deaths: count("death"),
+                exits: count("exit"),
+                best_ms: clear_times.iter().copied().min(),
+                average_ms,
+                recent_average_ms,
+                trend_ms: average_ms.zip(recent_average_ms).map(|(all, recent)| recent - all),
+            }
+        })
+        .collect())
+}
+
+fn average(values: &[i64]) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<i64>() / values.len() as i64)
+}
+
+fn level_labels(conn: &Connection, hack_id: i64) -> Result<HashMap<i64, String>, String> {
+    let mut stmt = conn.prepare("SELECT level_id, label FROM level_labels WHERE hack_id = ?1")
         .map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM level_timings WHERE hack_id = ?1", [hack_id])
+    let labels = stmt.query_map([hack_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
         .map_err(|e| e.to_string())?;
-    
+    Ok(labels)
+}
+
+/// Split history of one level, newest first.
+#[command]
+pub async fn get_level_attempts(
+    state: State<'_, AppState>,
+    hack_id: i64,
+    level_id: i64,
+    limit: Option<i64>,
+) -> Result<Vec<LevelAttemptRecord>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, session_id, start_time, end_time, outcome, duration_ms FROM level_attempts
+         WHERE hack_id = ?1 AND level_id = ?2
+         ORDER BY start_time DESC, id DESC
+         LIMIT ?3"
+    ).map_err(|e| e.to_string())?;
+    let attempts = stmt.query_map(rusqlite::params![hack_id, level_id, limit.unwrap_or(-1)], |row| {
+        Ok(LevelAttemptRecord {
+            id: row.get(0)?,
+            session_id: row.get(1)?,
+            start_time: row.get(2)?,
+            end_time: row.get(3)?,
+            outcome: row.get(4)?,
+            duration_ms: row.get::<_, Option<i64>>(5)?.unwrap_or(0),
+        })
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    Ok(attempts)
+}
+
+/// Names a level; an empty or missing label removes it.
+#[command]
+pub async fn set_level_label(
+    state: State<'_, AppState>,
+    hack_id: i64,
+    level_id: i64,
+    label: Option<String>,
+) -> Result<(), String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    set_level_label_impl(&conn, hack_id, level_id, label)
+}
+
+pub fn set_level_label_impl(conn: &Connection, hack_id: i64, level_id: i64, label: Option<String>) -> Result<(), String> {
+    match label.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+        Some(label) => conn.execute(
+            "INSERT OR REPLACE INTO level_labels (hack_id, level_id, label, source) VALUES (?1, ?2, ?3, 'user')",
+            rusqlite::params![hack_id, level_id, label],
+        ),
+        None => conn.execute(
+            "DELETE FROM level_labels WHERE hack_id = ?1 AND level_id = ?2",
+            rusqlite::params![hack_id, level_id],
+        ),
+    }.map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Imports level names from hack metadata (e.g. a level list shipped with the hack).
+/// Labels the user entered themselves are kept.
 #[command]
-pub async fn clear_all_tracking_data(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn import_level_labels(state: State<'_, AppState>, hack_id: i64, labels: Vec<LevelLabel>) -> Result<(), String> {
     let conn = state.db.get().map_err(|e| e.to_string())?;
-    
-    conn.execute("DELETE FROM play_sessions", [])
+    import_level_labels_impl(&conn, hack_id, &labels)
+}
+
+pub fn import_level_labels_impl(conn: &Connection, hack_id: i64, labels: &[LevelLabel]) -> Result<(), String> {
+    for entry in labels {
+        let label = entry.label.trim();
+        if label.is_empty() {
+            continue;
+        }
+        conn.execute(
+            "INSERT INTO level_labels (hack_id, level_id, label, source) VALUES (?1, ?2, ?3, 'metadata')
+             ON CONFLICT(hack_id, level_id) DO UPDATE SET label = excluded.label
+             WHERE source = 'metadata'",
+            rusqlite::params![hack_id, entry.level_id, label],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Save file progress of a hack, one entry per save file in use.
+#[derive(Debug, Serialize)]
+pub struct SaveProgress {
+    /// "device" when read from the running game, "file" for the `.srm` next to the ROM.
+    pub source: String,
+    pub path: Option<String>,
+    pub total_exits: Option<i64>,
+    pub slots: Vec<SaveSlot>,
+}
+
+/// Reads a hack's save files: live from the device while the hack is running,
+/// otherwise from the emulator's `.srm` next to the patched ROM.
+#[command]
+pub async fn get_save_progress(state: State<'_, AppState>, hack_id: i64) -> Result<SaveProgress, String> {
+    let live = match state.tracking.read_save_data(hack_id).await {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Tracking: Failed to read SRAM, using the save file instead: {}", e);
+            None
+        }
+    };
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    get_save_progress_impl(&conn, hack_id, live)
+}
+
+pub fn get_save_progress_impl(conn: &Connection, hack_id: i64, live: Option<Vec<u8>>) -> Result<SaveProgress, String> {
+    let (file_path, total_exits): (Option<String>, Option<i64>) = conn.query_row(
+        "SELECT file_path, total_exits FROM hacks WHERE id = ?1",
+        [hack_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| e.to_string())?;
+
+    let (source, path, data) = match live {
+        Some(data) => ("device", None, data),
+        None => {
+            let srm = file_path
+                .as_deref()
+                .map(sram::srm_path)
+                .filter(|p| p.exists())
+                .ok_or("No save file found for this hack")?;
+            let data = std::fs::read(&srm).map_err(|e| format!("Failed to read {}: {}", srm.display(), e))?;
+            ("file", Some(srm.to_string_lossy().into_owned()), data)
+        }
+    };
+
+    Ok(SaveProgress {
+        source: source.to_string(),
+        path,
+        total_exits,
+        slots: sram::parse_sram(&data)?.into_iter().flatten().collect(),
+    })
+}
+
+/// Installs an engine memory map from a JSON file, replacing one with the same name.
+/// Returns the map's name.
+#[command]
+pub async fn import_memory_map(state: State<'_, AppState>, file_path: String) -> Result<String, String> {
+    let json = std::fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let name = import_memory_map_impl(&conn, &json)?;
+    state.tracking.reload_memory_map().await;
+    Ok(name)
+}
+
+pub fn import_memory_map_impl(conn: &Connection, json: &str) -> Result<String, String> {
+    let map = MemoryMap::from_json(json)?;
+    let name = map.name.trim();
+    if name.is_empty() {
+        return Err("Memory map needs a name".to_string());
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO memory_maps (name, definition) VALUES (?1, ?2)",
+        rusqlite::params![name, json],
+    ).map_err(|e| e.to_string())?;
+    Ok(name.to_string())
+}
+
+#[command]
+pub async fn get_memory_maps(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT name FROM memory_maps ORDER BY name")
         .map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM level_timings", [])
+    let names = stmt.query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(names)
+}
+
+/// Assigns an installed engine memory map to a hack; `None` goes back to vanilla SMW.
+#[command]
+pub async fn set_hack_memory_map(state: State<'_, AppState>, hack_id: i64, name: Option<String>) -> Result<(), String> {
+    {
+        let conn = state.db.get().map_err(|e| e.to_string())?;
+        set_hack_memory_map_impl(&conn, hack_id, name)?;
+    }
+    state.tracking.reload_memory_map().await;
+    Ok(())
+}
+
+pub fn set_hack_memory_map_impl(conn: &Connection, hack_id: i64, name: Option<String>) -> Result<(), String> {
+    use rusqlite::OptionalExtension;
+    if let Some(name) = &name {
+        let exists = conn.query_row("SELECT 1 FROM memory_maps WHERE name = ?1", [name], |_| Ok(()))
+            .optional()
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if !exists {
+            return Err(format!("Memory map '{}' is not installed", name));
+        }
+    }
+    conn.execute("UPDATE hacks SET memory_map = ?1 WHERE id = ?2", rusqlite::params![name, hack_id])
         .map_err(|e| e.to_string())?;
-    
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn
+    }
+
+    fn add_attempt(conn: &Connection, level_id: i64, minute: u32, outcome: Option<&str>, duration_ms: i64) {
+        conn.execute(
+            "INSERT INTO level_attempts (hack_id, level_id, start_time, outcome, duration_ms) VALUES (1, ?1, ?2, ?3, ?4)",
+            rusqlite::params![level_id, format!("2026-01-01T00:{:02}:00Z", minute), outcome, duration_ms],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_clearing_stats_removes_pending_completions() {
+        let conn = setup();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Other Hack')", []).unwrap();
+        for hack_id in [1, 2] {
+            conn.execute("INSERT INTO play_sessions (hack_id, start_time, duration_ms) VALUES (?1, '2026-01-01T00:00:00Z', 60000)", [hack_id]).unwrap();
+            conn.execute(
+                "INSERT INTO pending_completions (hack_id, session_id, route, completed_at, play_time_seconds, exits_found)
+                 VALUES (?1, ?1, 'any%', 1767225600, 60, 1)",
+                [hack_id],
+            ).unwrap();
+        }
+        let count = |table: &str| conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0)).unwrap();
+
+        clear_hack_stats_impl(&conn, 1).unwrap();
+        assert_eq!((count("play_sessions"), count("pending_completions")), (1, 1));
+        let remaining: i64 = conn.query_row("SELECT hack_id FROM pending_completions", [], |row| row.get(0)).unwrap();
+        assert_eq!(remaining, 2);
+
+        clear_all_tracking_data_impl(&conn).unwrap();
+        assert_eq!((count("play_sessions"), count("pending_completions")), (0, 0));
+    }
+
+    #[test]
+    fn test_level_records_best_average_and_trend() {
+        let conn = setup();
+        add_attempt(&conn, 5, 0, Some("clear"), 60_000);
+        add_attempt(&conn, 5, 1, Some("death"), 10_000);
+        add_attempt(&conn, 5, 2, Some("exit"), 5_000);
+        for (minute, ms) in [(3, 50_000), (4, 44_000), (5, 42_000), (6, 40_000), (7, 39_000)] {
+            add_attempt(&conn, 5, minute, Some("clear"), ms);
+        }
+        // In progress, not counted yet
+        add_attempt(&conn, 5, 8, None, 1_000);
+        add_attempt(&conn, 6, 9, Some("death"), 3_000);
+
+        let records = get_level_records_impl(&conn, 1).unwrap();
+        assert_eq!(records.len(), 2);
+
+        let level = &records[0];
+        assert_eq!((level.attempts, level.clears, level.deaths, level.exits), (8, 6, 1, 1));
+        assert_eq!(level.best_ms, Some(39_000));
+        assert_eq!(level.average_ms, Some(45_833));
+        assert_eq!(level.recent_average_ms, Some(43_000));
+        assert_eq!(level.trend_ms, Some(43_000 - 45_833));
+
+        assert_eq!(records[1].best_ms, None);
+        assert_eq!(records[1].trend_ms, None);
+    }
+
+    #[test]
+    fn test_user_labels_win_over_metadata() {
+        let conn = setup();
+        add_attempt(&conn, 5, 0, Some("clear"), 1_000);
+
+        set_level_label_impl(&conn, 1, 5, Some(" Cheese Bridge ".to_string())).unwrap();
+        import_level_labels_impl(&conn, 1, &[
+            LevelLabel { level_id: 5, label: "Level 5".to_string() },
+            LevelLabel { level_id: 6, label: "Level 6".to_string() },
+        ]).unwrap();
+
+        let labels = level_labels(&conn, 1).unwrap();
+        assert_eq!(labels[&5], "Cheese Bridge");
+        assert_eq!(labels[&6], "Level 6");
+        assert_eq!(get_level_records_impl(&conn, 1).unwrap()[0].label.as_deref(), Some("Cheese Bridge"));
+
+        set_level_label_impl(&conn, 1, 5, None).unwrap();
+        assert!(!level_labels(&conn, 1).unwrap().contains_key(&5));
+    }
+
+    #[test]
+    fn test_memory_maps_must_be_installed_before_use() {
+        let conn = setup();
+        assert!(set_hack_memory_map_impl(&conn, 1, Some("Custom Engine".to_string())).is_err());
+
+        let name = import_memory_map_impl(&conn, r#"{"name":"Custom Engine","death_animation":10}"#).unwrap();
+        assert_eq!(name, "Custom Engine");
+        assert!(import_memory_map_impl(&conn, r#"{"name":"Broken","level_id":{"address":0,"size":0}}"#).is_err());
+
+        set_hack_memory_map_impl(&conn, 1, Some(name)).unwrap();
+        assert_eq!(crate::tracking::memory_map::load_for_hack(&conn, 1).unwrap().death_animation, 10);
+    }
+
+    #[test]
+    fn test_save_progress_from_srm_next_to_rom() {
+        let conn = setup();
+        assert!(get_save_progress_impl(&conn, 1, None).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let rom = dir.path().join("Hack.sfc");
+        conn.execute(
+            "UPDATE hacks SET file_path = ?1, total_exits = 20 WHERE id = 1",
+            [rom.to_string_lossy()],
+        ).unwrap();
+
+        // File B with 5 exits; the checksum makes all bytes add up to $5A5A
+        let mut srm = vec![0u8; 0x800];
+        srm[0x8F + 0x60] = 0x1F;
+        srm[0x8F + 0x8C] = 5;
+        let checksum = 0x5A5Au16 - 0x1F - 5;
+        srm[0x8F + 0x8D..0x8F + 0x8F].copy_from_slice(&checksum.to_le_bytes());
+        std::fs::write(dir.path().join("Hack.srm"), &srm).unwrap();
+
+        let progress = get_save_progress_impl(&conn, 1, None).unwrap();
+        assert_eq!(progress.source, "file");
+        assert_eq!(progress.total_exits, Some(20));
+        assert_eq!(progress.slots.len(), 1);
+        assert_eq!((progress.slots[0].slot, progress.slots[0].exit_count), (1, 5));
+
+        // Live SRAM wins over the file
+        let progress = get_save_progress_impl(&conn, 1, Some(vec![0u8; 0x800])).unwrap();
+        assert_eq!(progress.source, "device");
+        assert!(progress.slots.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::tracking::service::SessionEnd;
+use crate::tracking::smw::{AttemptOutcome, ExitType};
+
+/// Name of the Tauri event every `TrackingEvent` is emitted under.
+pub const TRACKING_EVENT: &str = "tracking-event";
+/// Events kept for slow subscribers before the oldest are dropped.
+const EVENT_BUFFER: usize = 64;
+
+/// Live updates from the tracking loop. Serialized with a `type` tag, e.g.
+/// `{"type":"level_entered","hack_id":3,"level_id":5,"retry":false}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TrackingEvent {
+    Connected { source: String },
+    Disconnected,
+    HackDetected { hack_id: i64 },
+    SessionStarted { session_id: i64, hack_id: i64 },
+    SessionEnded { session_id: i64, reason: SessionEnd },
+    LevelEntered { hack_id: Option<i64>, level_id: u8, retry: bool },
+    LevelExited { hack_id: Option<i64>, level_id: u8, outcome: AttemptOutcome },
+    Death { hack_id: Option<i64>, level_id: u8 },
+    ExitCleared { hack_id: Option<i64>, level_id: u8, event_id: u8, exit_type: ExitType },
+    /// The credits were reached; the completion waits in `pending_completions` for confirmation.
+    CompletionDetected { hack_id: i64, pending_id: i64, route: String },
+}
+
+/// Fans tracking events out to any number of listeners (the Tauri forwarder, tests).
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<TrackingEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TrackingEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Sends an event; it's simply dropped when nobody is listening.
+    pub fn emit(&self, event: TrackingEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_payload_schema() {
+        let event = TrackingEvent::LevelEntered { hack_id: Some(3), level_id: 5, retry: false };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"type": "level_entered", "hack_id": 3, "level_id": 5, "retry": false})
+        );
+
+        let event = TrackingEvent::SessionEnded { session_id: 7, reason: SessionEnd::RomChanged };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"type": "session_ended", "session_id": 7, "reason": "rom_changed"})
+        );
+    }
+
+    #[test]
+    fn test_bus_delivers_to_subscribers() {
+        let bus = EventBus::new();
+        // No listeners yet: nothing to deliver to, and no error either
+        bus.emit(TrackingEvent::Disconnected);
+
+        let mut events = bus.subscribe();
+        bus.emit(TrackingEvent::HackDetected { hack_id: 1 });
+        assert_eq!(events.try_recv().unwrap(), TrackingEvent::HackDetected { hack_id: 1 });
+    }
+}
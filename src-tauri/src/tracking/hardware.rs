@@ -0,0 +1,116 @@
+use crate::tracking::usb2snes::Usb2SnesClient;
+
+/// SD card folder patched ROMs are uploaded to when none is configured.
+pub const DEFAULT_HARDWARE_ROM_FOLDER: &str = "/romhacks";
+
+/// Joins the configured SD card folder and a ROM file name into an absolute remote path.
+pub fn remote_rom_path(folder: &str, file_name: &str) -> String {
+    let folder = folder.trim().replace('\\', "/");
+    let folder = folder.trim_matches('/');
+    if folder.is_empty() {
+        format!("/{}", file_name)
+    } else {
+        format!("/{}/{}", folder, file_name)
+    }
+}
+
+/// Creates every missing folder along `path`.
+async fn ensure_dir(client: &mut Usb2SnesClient, path: &str) -> Result<(), String> {
+    let mut parent = String::from("/");
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        let current = format!("{}{}", parent, component);
+        let exists = client
+            .list(&parent)
+            .await?
+            .iter()
+            .any(|e| e.is_dir && e.name.eq_ignore_ascii_case(component));
+        if !exists {
+            client.make_dir(&current).await?;
+        }
+        parent = format!("{}/", current);
+    }
+    Ok(())
+}
+
+/// Uploads `data` to `remote_path` unless an identical copy is already there, then boots it.
+/// `already_uploaded` means our upload record says this exact ROM was written to that path;
+/// the file on the card is still read back and compared, since it may have been replaced
+/// or truncated outside the app. Returns whether the ROM was uploaded.
+pub async fn upload_and_boot(
+    client: &mut Usb2SnesClient,
+    data: &[u8],
+    remote_path: &str,
+    already_uploaded: bool,
+) -> Result<bool, String> {
+    if client.has_flag("NO_FILE_CMD") {
+        return Err("The connected device doesn't support file transfers".to_string());
+    }
+    if client.has_flag("NO_CONTROL_CMD") {
+        return Err("The connected device can't boot ROMs".to_string());
+    }
+
+    let (folder, file_name) = remote_path.rsplit_once('/').unwrap_or(("", remote_path));
+    ensure_dir(client, folder).await?;
+
+    let listing_path = if folder.is_empty() { "/" } else { folder };
+    let present = client
+        .list(listing_path)
+        .await?
+        .iter()
+        .any(|e| !e.is_dir && e.name == file_name);
+
+    // `List` has no sizes, so the only way to know the copy is intact is to read it back
+    let upload = !(present && already_uploaded && client.get_file(remote_path).await? == data);
+    if upload {
+        if present {
+            client.remove(remote_path).await?;
+        }
+        client.put_file(remote_path, data).await?;
+    }
+
+    client.boot(remote_path).await?;
+    Ok(upload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::mock_usb2snes::MockUsb2Snes;
+
+    async fn attached_client(mock: &MockUsb2Snes) -> Usb2SnesClient {
+        let mut client = Usb2SnesClient::connect(mock.url()).await.unwrap();
+        client.attach("SD2SNES COM3").await.unwrap();
+        client.info().await.unwrap();
+        client
+    }
+
+    #[test]
+    fn test_remote_rom_path() {
+        assert_eq!(remote_rom_path("/romhacks/", "Hack.sfc"), "/romhacks/Hack.sfc");
+        assert_eq!(remote_rom_path("games\\smw", "Hack.sfc"), "/games/smw/Hack.sfc");
+        assert_eq!(remote_rom_path("", "Hack.sfc"), "/Hack.sfc");
+    }
+
+    #[tokio::test]
+    async fn test_upload_skipped_only_when_remote_copy_matches() {
+        let mock = MockUsb2Snes::start().await;
+        let mut client = attached_client(&mock).await;
+        let rom = vec![0x5A; 3000];
+        let path = "/romhacks/Hack.sfc";
+
+        assert!(upload_and_boot(&mut client, &rom, path, false).await.unwrap());
+        assert_eq!(mock.remote_file(path), Some(rom.clone()));
+
+        assert!(!upload_and_boot(&mut client, &rom, path, true).await.unwrap());
+        assert_eq!(mock.uploads(), 1);
+
+        // Replaced on the card outside the app, though our record still matches
+        mock.put_remote_file(path, &rom[..1000]);
+        assert!(upload_and_boot(&mut client, &rom, path, true).await.unwrap());
+        assert_eq!(mock.remote_file(path), Some(rom));
+        assert_eq!(mock.uploads(), 2);
+        // Boot has no reply; a round trip makes sure the last one was handled
+        client.info().await.unwrap();
+        assert_eq!(mock.booted(), vec![path; 3]);
+    }
+}
@@ -0,0 +1,346 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use crate::config::Config;
+use crate::tracking::smw::{AttemptOutcome, ExitType, GamePhase, LevelEvent, TrackingUpdate};
+
+/// LiveSplit Server's default port.
+pub const DEFAULT_LIVESPLIT_ADDRESS: &str = "127.0.0.1:16834";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// LiveSplit usually isn't running; don't retry the connection on every poll.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What has to happen for the next split. The configured rules form the route,
+/// one rule per split, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum SplitRule {
+    /// Any level beaten, through any exit.
+    LevelClear,
+    /// A specific exit found for the first time; `exit_type: None` accepts either.
+    Exit { level_id: u8, exit_type: Option<ExitType> },
+    /// The ending starts.
+    Credits,
+}
+
+/// Something that happened during one poll that a rule can match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trigger {
+    LevelClear,
+    Exit { level_id: u8, exit_type: ExitType },
+    Credits,
+}
+
+impl SplitRule {
+    fn matches(&self, trigger: &Trigger) -> bool {
+        match (self, trigger) {
+            (SplitRule::LevelClear, Trigger::LevelClear) => true,
+            (SplitRule::Exit { level_id, exit_type }, Trigger::Exit { level_id: found, exit_type: found_type }) => {
+                level_id == found && exit_type.is_none_or(|t| t == *found_type)
+            }
+            (SplitRule::Credits, Trigger::Credits) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Parses the JSON rule list stored in `Config::livesplit_splits`.
+pub fn parse_split_rules(json: &str) -> Result<Vec<SplitRule>, String> {
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(json).map_err(|e| format!("Invalid LiveSplit split rules: {}", e))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveSplitCommand {
+    StartTimer,
+    InitGameTime,
+    Split,
+    PauseGameTime,
+    UnpauseGameTime,
+    Reset,
+}
+
+impl LiveSplitCommand {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveSplitCommand::StartTimer => "starttimer",
+            LiveSplitCommand::InitGameTime => "initgametime",
+            LiveSplitCommand::Split => "split",
+            LiveSplitCommand::PauseGameTime => "pausegametime",
+            LiveSplitCommand::UnpauseGameTime => "unpausegametime",
+            LiveSplitCommand::Reset => "reset",
+        }
+    }
+}
+
+/// Turns tracking updates into timer commands.
+///
+/// The run starts when a save file is loaded (leaving the title screen and file
+/// select) and resets on returning to them. Game time is paused whenever the
+/// game isn't being played: pause menu, cutscenes and menus.
+#[derive(Debug)]
+pub struct AutoSplitter {
+    rules: Vec<SplitRule>,
+    next_split: usize,
+    running: bool,
+    game_time_paused: bool,
+    last_phase: Option<GamePhase>,
+}
+
+impl AutoSplitter {
+    pub fn new(rules: Vec<SplitRule>) -> Self {
+        Self {
+            rules,
+            next_split: 0,
+            running: false,
+            game_time_paused: false,
+            last_phase: None,
+        }
+    }
+
+    pub fn update(&mut self, update: &TrackingUpdate) -> Vec<LiveSplitCommand> {
+        let mut commands = Vec::new();
+        let previous_phase = self.last_phase.replace(update.phase);
+
+        if !self.running {
+            if previous_phase == Some(GamePhase::Menu) && update.phase != GamePhase::Menu {
+                self.running = true;
+                self.next_split = 0;
+                self.game_time_paused = false;
+                // A finished run has to be reset before LiveSplit starts a new one
+                commands.extend([LiveSplitCommand::Reset, LiveSplitCommand::StartTimer, LiveSplitCommand::InitGameTime]);
+            }
+            return commands;
+        }
+
+        if update.phase == GamePhase::Menu {
+            self.running = false;
+            commands.push(LiveSplitCommand::Reset);
+            return commands;
+        }
+
+        let paused = !update.is_active;
+        if paused != self.game_time_paused {
+            self.game_time_paused = paused;
+            commands.push(if paused { LiveSplitCommand::PauseGameTime } else { LiveSplitCommand::UnpauseGameTime });
+        }
+
+        let mut triggers = Vec::new();
+        for event in &update.level_events {
+            if let LevelEvent::Exit { outcome: AttemptOutcome::Clear, .. } = event {
+                triggers.push(Trigger::LevelClear);
+            }
+        }
+        for exit in &update.new_exits {
+            triggers.push(Trigger::Exit { level_id: exit.level_id, exit_type: exit.exit_type });
+        }
+        if update.phase == GamePhase::Credits && previous_phase != Some(GamePhase::Credits) {
+            triggers.push(Trigger::Credits);
+        }
+
+        // At most one split per poll: a clear is reported both as a level exit and as a new exit
+        if let Some(rule) = self.rules.get(self.next_split) {
+            if triggers.iter().any(|t| rule.matches(t)) {
+                self.next_split += 1;
+                commands.push(LiveSplitCommand::Split);
+                if self.next_split == self.rules.len() {
+                    // The last split stops LiveSplit's timer
+                    self.running = false;
+                }
+            }
+        }
+
+        commands
+    }
+}
+
+/// Client for LiveSplit Server's line based TCP command protocol.
+#[derive(Debug)]
+pub struct LiveSplitClient {
+    stream: TcpStream,
+}
+
+impl LiveSplitClient {
+    pub async fn connect(address: &str) -> Result<Self, String> {
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+            .await
+            .map_err(|_| format!("Timed out connecting to LiveSplit Server at {}", address))?
+            .map_err(|e| format!("Failed to connect to LiveSplit Server at {}: {}", address, e))?;
+        Ok(Self { stream })
+    }
+
+    pub async fn send(&mut self, command: LiveSplitCommand) -> Result<(), String> {
+        self.stream
+            .write_all(format!("{}\r\n", command.as_str()).as_bytes())
+            .await
+            .map_err(|e| format!("LiveSplit Server connection lost: {}", e))
+    }
+}
+
+/// Keeps the auto splitter and the LiveSplit Server connection in line with the config,
+/// for use from the tracking loop.
+#[derive(Debug, Default)]
+pub struct LiveSplitBridge {
+    rules_json: Option<String>,
+    splitter: Option<AutoSplitter>,
+    client: Option<LiveSplitClient>,
+    last_connect: Option<Instant>,
+}
+
+impl LiveSplitBridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn process(&mut self, config: Option<&Config>, update: &TrackingUpdate) {
+        if !config.and_then(|c| c.livesplit_enabled).unwrap_or(false) {
+            *self = Self::default();
+            return;
+        }
+
+        let rules_json = config.and_then(|c| c.livesplit_splits.clone()).unwrap_or_default();
+        if self.rules_json.as_deref() != Some(rules_json.as_str()) {
+            self.splitter = match parse_split_rules(&rules_json) {
+                Ok(rules) => Some(AutoSplitter::new(rules)),
+                Err(e) => {
+                    eprintln!("Tracking: {}", e);
+                    None
+                }
+            };
+            self.rules_json = Some(rules_json);
+        }
+        let Some(splitter) = self.splitter.as_mut() else {
+            return;
+        };
+        let commands = splitter.update(update);
+
+        if self.client.is_none() && self.last_connect.is_none_or(|t| t.elapsed() >= RECONNECT_INTERVAL) {
+            self.last_connect = Some(Instant::now());
+            let address = config
+                .and_then(|c| c.livesplit_address.clone())
+                .unwrap_or_else(|| DEFAULT_LIVESPLIT_ADDRESS.to_string());
+            self.client = LiveSplitClient::connect(&address).await.ok();
+        }
+
+        if let Some(client) = self.client.as_mut() {
+            for command in commands {
+                if let Err(e) = client.send(command).await {
+                    eprintln!("Tracking: {}", e);
+                    self.client = None;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::smw::ExitClear;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn update(phase: GamePhase) -> TrackingUpdate {
+        TrackingUpdate {
+            is_active: phase.is_gameplay(),
+            phase,
+            paused: false,
+            level_id: None,
+            new_exits: Vec::new(),
+            level_events: Vec::new(),
+            ending: None,
+            save_slot: None,
+        }
+    }
+
+    fn cleared(level_id: u8, exit_type: ExitType) -> TrackingUpdate {
+        TrackingUpdate {
+            new_exits: vec![ExitClear { level_id, event_id: 1, exit_type }],
+            level_events: vec![LevelEvent::Exit { level_id, outcome: AttemptOutcome::Clear }],
+            ..update(GamePhase::Overworld)
+        }
+    }
+
+    #[test]
+    fn test_parse_split_rules() {
+        let rules = parse_split_rules(
+            r#"[{"rule":"level_clear"},{"rule":"exit","level_id":5,"exit_type":"secret"},{"rule":"credits"}]"#,
+        )
+        .unwrap();
+        assert_eq!(rules[1], SplitRule::Exit { level_id: 5, exit_type: Some(ExitType::Secret) });
+        assert!(parse_split_rules("").unwrap().is_empty());
+        assert!(parse_split_rules(r#"[{"rule":"bogus"}]"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_splits_sent_to_livesplit_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            let mut received = Vec::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                received.push(line);
+            }
+            received
+        });
+
+        let mut splitter = AutoSplitter::new(vec![
+            SplitRule::LevelClear,
+            SplitRule::Exit { level_id: 0x05, exit_type: Some(ExitType::Secret) },
+            SplitRule::Credits,
+        ]);
+        let mut client = LiveSplitClient::connect(&address).await.unwrap();
+
+        let updates = [
+            update(GamePhase::Menu),
+            // File loaded
+            update(GamePhase::Overworld),
+            cleared(0x01, ExitType::Normal),
+            // Wrong exit type for the next rule
+            cleared(0x05, ExitType::Normal),
+            update(GamePhase::Cutscene),
+            update(GamePhase::Overworld),
+            cleared(0x05, ExitType::Secret),
+            update(GamePhase::Credits),
+        ];
+        for up in &updates {
+            for command in splitter.update(up) {
+                client.send(command).await.unwrap();
+            }
+        }
+        drop(client);
+
+        assert_eq!(
+            server.await.unwrap(),
+            vec![
+                "reset", "starttimer", "initgametime",
+                "split",
+                "pausegametime", "unpausegametime",
+                "split",
+                "pausegametime", "split",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_returning_to_title_resets_run() {
+        let mut splitter = AutoSplitter::new(vec![SplitRule::LevelClear, SplitRule::Credits]);
+        splitter.update(&update(GamePhase::Menu));
+        splitter.update(&update(GamePhase::Overworld));
+        assert_eq!(splitter.update(&update(GamePhase::Menu)), vec![LiveSplitCommand::Reset]);
+        // Nothing is sent until the next file load, which starts a fresh run
+        assert!(splitter.update(&update(GamePhase::Menu)).is_empty());
+        assert_eq!(
+            splitter.update(&update(GamePhase::Overworld)),
+            vec![LiveSplitCommand::Reset, LiveSplitCommand::StartTimer, LiveSplitCommand::InitGameTime]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::tracking::smw::{ExitType, GamePhase, SmwAnalyzer, SmwSnapshot};
+
+/// Extension of the per-hack memory map file looked for next to the patched ROM.
+pub const MEMORY_MAP_EXTENSION: &str = "memmap.json";
+
+/// A RAM location, in usb2snes addressing (WRAM at `$F50000`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    #[serde(deserialize_with = "deserialize_number")]
+    pub address: u32,
+    #[serde(default = "default_size", deserialize_with = "deserialize_number")]
+    pub size: u32,
+}
+
+fn default_size() -> u32 {
+    1
+}
+
+impl Field {
+    const fn new(address: u32, size: u32) -> Self {
+        Self { address, size }
+    }
+}
+
+/// Game modes (inclusive range) that put the game in `phase`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseRule {
+    pub phase: GamePhase,
+    pub game_modes: Vec<ModeRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ModeRange {
+    pub first: u8,
+    pub last: u8,
+}
+
+impl ModeRange {
+    const fn new(first: u8, last: u8) -> Self {
+        Self { first, last }
+    }
+
+    fn contains(&self, game_mode: u8) -> bool {
+        (self.first..=self.last).contains(&game_mode)
+    }
+}
+
+/// Accepts `14`, `"0x0E"`, `"$0E"` or a range such as `"0x0B-0x17"`.
+impl<'de> Deserialize<'de> for ModeRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let range = match NumberOrText::deserialize(deserializer)? {
+            NumberOrText::Number(n) => {
+                let mode = u8::try_from(n).map_err(serde::de::Error::custom)?;
+                ModeRange::new(mode, mode)
+            }
+            NumberOrText::Text(text) => {
+                let (first, last) = text.split_once('-').unwrap_or((&text, &text));
+                let parse = |s: &str| {
+                    parse_number(s).and_then(|n| u8::try_from(n).map_err(|e| e.to_string()))
+                };
+                ModeRange::new(
+                    parse(first).map_err(serde::de::Error::custom)?,
+                    parse(last).map_err(serde::de::Error::custom)?,
+                )
+            }
+        };
+        Ok(range)
+    }
+}
+
+/// Where the tracker finds game state in RAM and how to interpret it.
+///
+/// Files only need the entries that differ from vanilla SMW; everything else
+/// falls back to the vanilla map. Addresses may be numbers or hex strings
+/// (`"0xF50100"`, `"$F50100"`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryMap {
+    pub name: String,
+    pub game_mode: Field,
+    pub level_id: Field,
+    pub exit_type: Field,
+    pub event_flags: Field,
+    pub player_animation: Field,
+    pub pause_flag: Field,
+    pub frame_counter: Field,
+    pub controller: Field,
+    pub player_position: Field,
+    pub save_slot: Field,
+    /// Checked in order; the first rule containing the game mode wins.
+    pub phases: Vec<PhaseRule>,
+    /// Phase of game modes no rule matches.
+    pub default_phase: GamePhase,
+    /// Player animation value of the death animation.
+    pub death_animation: u8,
+    /// Level numbers that aren't gameplay (intro, title screen demo).
+    pub excluded_levels: Vec<u8>,
+    /// Exit type values meaning a normal or secret exit was taken.
+    pub normal_exit_types: Vec<u8>,
+    pub secret_exit_types: Vec<u8>,
+    /// Events that must be triggered for the credits to count as beating the hack,
+    /// e.g. one the final boss sets. Numbered like exit clears.
+    pub ending_events: Vec<u8>,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::vanilla()
+    }
+}
+
+impl MemoryMap {
+    pub fn vanilla() -> Self {
+        Self {
+            name: "Super Mario World".to_string(),
+            game_mode: Field::new(SmwAnalyzer::ADDR_GAME_MODE, 1),
+            level_id: Field::new(SmwAnalyzer::ADDR_LEVEL_ID, 1),
+            exit_type: Field::new(SmwAnalyzer::ADDR_EXIT_TYPE, 1),
+            event_flags: Field::new(SmwAnalyzer::ADDR_EVENT_FLAGS, SmwAnalyzer::EVENT_FLAGS_SIZE),
+            player_animation: Field::new(SmwAnalyzer::ADDR_PLAYER_ANIMATION, 1),
+            pause_flag: Field::new(SmwAnalyzer::ADDR_PAUSE_FLAG, 1),
+            frame_counter: Field::new(SmwAnalyzer::ADDR_FRAME_COUNTER, 1),
+            controller: Field::new(SmwAnalyzer::ADDR_CONTROLLER, SmwAnalyzer::CONTROLLER_SIZE),
+            player_position: Field::new(SmwAnalyzer::ADDR_PLAYER_POSITION, SmwAnalyzer::PLAYER_POSITION_SIZE),
+            save_slot: Field::new(SmwAnalyzer::ADDR_SAVE_SLOT, 1),
+            phases: vec![
+                PhaseRule { phase: GamePhase::Menu, game_modes: vec![ModeRange::new(0x00, 0x0A)] },
+                PhaseRule { phase: GamePhase::Overworld, game_modes: vec![ModeRange::new(0x0E, 0x0E)] },
+                PhaseRule { phase: GamePhase::Level, game_modes: vec![ModeRange::new(0x14, 0x14)] },
+                PhaseRule { phase: GamePhase::GameOver, game_modes: vec![ModeRange::new(0x15, 0x17)] },
+                PhaseRule { phase: GamePhase::Transition, game_modes: vec![ModeRange::new(0x0B, 0x13)] },
+                PhaseRule { phase: GamePhase::Cutscene, game_modes: vec![ModeRange::new(0x18, 0x1B)] },
+            ],
+            default_phase: GamePhase::Credits,
+            death_animation: 0x09,
+            excluded_levels: vec![0x00, 0xC5, 0xC7],
+            normal_exit_types: vec![1],
+            secret_exit_types: vec![2, 3, 4],
+            ending_events: Vec::new(),
+        }
+    }
+
+    /// Parses and checks a memory map file's contents.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let map: MemoryMap = serde_json::from_str(json).map_err(|e| format!("Invalid memory map: {}", e))?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let fields = [
+            ("game_mode", self.game_mode),
+            ("level_id", self.level_id),
+            ("exit_type", self.exit_type),
+            ("event_flags", self.event_flags),
+            ("player_animation", self.player_animation),
+            ("pause_flag", self.pause_flag),
+            ("frame_counter", self.frame_counter),
+            ("controller", self.controller),
+            ("player_position", self.player_position),
+            ("save_slot", self.save_slot),
+        ];
+        for (name, field) in fields {
+            if field.size == 0 || field.size > 0x100 {
+                return Err(format!("Memory map field {} has an invalid size of {}", name, field.size));
+            }
+            if field.address.checked_add(field.size).is_none_or(|end| end > 0x1000000) {
+                return Err(format!("Memory map field {} at {:06X} is out of range", name, field.address));
+            }
+        }
+        if let Some(rule) = self.phases.iter().find(|r| r.game_modes.iter().any(|m| m.first > m.last)) {
+            return Err(format!("Memory map has an empty game mode range for {:?}", rule.phase));
+        }
+        if let Some(event) = self.ending_events.iter().find(|&&e| e as u32 >= self.event_flags.size * 8) {
+            return Err(format!("Memory map ending event {} is outside the event flags", event));
+        }
+        Ok(())
+    }
+
+    pub fn phase(&self, game_mode: u8) -> GamePhase {
+        self.phases
+            .iter()
+            .find(|rule| rule.game_modes.iter().any(|m| m.contains(game_mode)))
+            .map(|rule| rule.phase)
+            .unwrap_or(self.default_phase)
+    }
+
+    pub fn exit_type(&self, value: u8) -> Option<ExitType> {
+        if self.normal_exit_types.contains(&value) {
+            Some(ExitType::Normal)
+        } else if self.secret_exit_types.contains(&value) {
+            Some(ExitType::Secret)
+        } else {
+            None
+        }
+    }
+
+    /// Every field, as `(address, size)` pairs for a batched read.
+    pub fn reads(&self) -> Vec<(u32, u32)> {
+        [
+            self.game_mode,
+            self.level_id,
+            self.event_flags,
+            self.exit_type,
+            self.player_animation,
+            self.pause_flag,
+            self.frame_counter,
+            self.controller,
+            self.player_position,
+            self.save_slot,
+        ]
+        .iter()
+        .map(|f| (f.address, f.size))
+        .collect()
+    }
+
+    /// Picks the analyzer's values out of a batched read of `reads()`.
+    pub fn snapshot(&self, ram: &HashMap<u32, Vec<u8>>) -> SmwSnapshot {
+        // Fields may share an address in custom maps, so look values up instead of taking them
+        let bytes = |field: Field| ram.get(&field.address).cloned().unwrap_or_default();
+        let byte = |field: Field| bytes(field).first().copied().unwrap_or(0);
+
+        let mut activity = bytes(self.controller);
+        activity.extend(bytes(self.player_position));
+
+        SmwSnapshot {
+            game_mode: byte(self.game_mode),
+            level_id: byte(self.level_id),
+            exit_type: byte(self.exit_type),
+            player_animation: byte(self.player_animation),
+            pause_flag: byte(self.pause_flag),
+            save_slot: byte(self.save_slot),
+            frame_counter: bytes(self.frame_counter).first().copied(),
+            activity,
+            events: bytes(self.event_flags),
+        }
+    }
+}
+
+/// The memory map for a hack: a `<rom>.memmap.json` file next to the patched ROM,
+/// else the engine map assigned to it, else vanilla SMW.
+pub fn load_for_hack(conn: &Connection, hack_id: i64) -> Result<MemoryMap, String> {
+    let (file_path, map_name): (Option<String>, Option<String>) = conn.query_row(
+        "SELECT file_path, memory_map FROM hacks WHERE id = ?1",
+        [hack_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| e.to_string())?;
+
+    if let Some(sidecar) = file_path.as_deref().map(sidecar_path).filter(|p| p.exists()) {
+        let json = std::fs::read_to_string(&sidecar)
+            .map_err(|e| format!("Failed to read {}: {}", sidecar.display(), e))?;
+        return MemoryMap::from_json(&json).map_err(|e| format!("{}: {}", sidecar.display(), e));
+    }
+
+    if let Some(name) = map_name {
+        let json: String = conn.query_row(
+            "SELECT definition FROM memory_maps WHERE name = ?1",
+            [&name],
+            |row| row.get(0),
+        ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Memory map '{}' is not installed", name))?;
+        return MemoryMap::from_json(&json);
+    }
+
+    Ok(MemoryMap::vanilla())
+}
+
+/// `Hack.sfc` -> `Hack.memmap.json`
+pub fn sidecar_path(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension(MEMORY_MAP_EXTENSION)
+}
+
+/// Moves a ROM's memory map file along with it. An existing file at the destination is kept.
+pub fn move_sidecar(from_rom: &Path, to_rom: &Path) -> Result<(), String> {
+    let from = sidecar_path(&from_rom.to_string_lossy());
+    let to = sidecar_path(&to_rom.to_string_lossy());
+    if from == to || !from.exists() || to.exists() {
+        return Ok(());
+    }
+    // Rename fails across filesystems, so fall back to copy + delete
+    if std::fs::rename(&from, &to).is_err() {
+        std::fs::copy(&from, &to).map_err(|e| format!("Failed to copy memory map: {}", e))?;
+        std::fs::remove_file(&from).map_err(|e| format!("Failed to remove old memory map: {}", e))?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrText {
+    Number(u64),
+    Text(String),
+}
+
+/// Parses decimal, `0x` prefixed or `$` prefixed (SNES style) hex numbers.
+pub fn parse_number(text: &str) -> Result<u32, String> {
+    let text = text.trim();
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).or_else(|| text.strip_prefix('$')) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        text.parse()
+    };
+    parsed.map_err(|e| format!("Invalid number '{}': {}", text, e))
+}
+
+fn deserialize_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    match NumberOrText::deserialize(deserializer)? {
+        NumberOrText::Number(n) => u32::try_from(n).map_err(serde::de::Error::custom),
+        NumberOrText::Text(text) => parse_number(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vanilla_map_matches_game_phases() {
+        let map = MemoryMap::vanilla();
+        for game_mode in 0..=u8::MAX {
+            assert_eq!(map.phase(game_mode), GamePhase::from_game_mode(game_mode), "game mode {:02X}", game_mode);
+        }
+        assert_eq!(map.exit_type(3), Some(ExitType::Secret));
+        assert_eq!(map.exit_type(0x80), None);
+    }
+
+    #[test]
+    fn test_partial_map_overrides_vanilla() {
+        let map = MemoryMap::from_json(
+            r#"{
+                "name": "Custom Engine",
+                "game_mode": { "address": "$F50200" },
+                "event_flags": { "address": "0xF51F02", "size": 20 },
+                "phases": [
+                    { "phase": "overworld", "game_modes": ["0x0E", 32] },
+                    { "phase": "level", "game_modes": ["0x14-0x15"] },
+                    { "phase": "menu", "game_modes": ["0x00-0x0A"] }
+                ],
+                "default_phase": "transition"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(map.game_mode, Field { address: 0xF50200, size: 1 });
+        assert_eq!(map.event_flags.size, 20);
+        assert_eq!(map.level_id, MemoryMap::vanilla().level_id);
+        assert_eq!(map.phase(0x20), GamePhase::Overworld);
+        assert_eq!(map.phase(0x15), GamePhase::Level);
+        assert_eq!(map.phase(0x30), GamePhase::Transition);
+    }
+
+    #[test]
+    fn test_load_for_hack_prefers_sidecar_then_engine_map() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let rom = dir.path().join("Hack.sfc");
+        conn.execute(
+            "INSERT INTO hacks (name, file_path) VALUES ('Hack', ?1)",
+            [rom.to_str().unwrap()],
+        ).unwrap();
+
+        assert_eq!(load_for_hack(&conn, 1).unwrap(), MemoryMap::vanilla());
+
+        conn.execute(
+            "INSERT INTO memory_maps (name, definition) VALUES ('Engine', '{\"name\":\"Engine\",\"death_animation\":10}')",
+            [],
+        ).unwrap();
+        conn.execute("UPDATE hacks SET memory_map = 'Engine' WHERE id = 1", []).unwrap();
+        assert_eq!(load_for_hack(&conn, 1).unwrap().death_animation, 10);
+
+        std::fs::write(sidecar_path(rom.to_str().unwrap()), r#"{"name":"Hack","death_animation":11}"#).unwrap();
+        assert_eq!(load_for_hack(&conn, 1).unwrap().death_animation, 11);
+    }
+
+    #[test]
+    fn test_invalid_maps_are_rejected() {
+        assert!(MemoryMap::from_json(r#"{ "level_id": { "address": "0xF513BF", "size": 0 } }"#).is_err());
+        assert!(MemoryMap::from_json(r#"{ "level_id": { "address": "nowhere" } }"#).is_err());
+        assert!(MemoryMap::from_json(r#"{ "phases": [{ "phase": "level", "game_modes": ["0x20-0x10"] }] }"#).is_err());
+    }
+}
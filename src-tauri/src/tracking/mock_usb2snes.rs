@@ -0,0 +1,319 @@
+//! In-process usb2snes websocket server for tests.
+//!
+//! Serves a device list, `Info` results, a sparse memory image and an SD card of
+//! in-memory files, and plays a scripted timeline one step per tracker poll, so
+//! `TrackingService` can be exercised end to end without QUsb2snes or hardware.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{accept_async, WebSocketStream};
+
+use crate::tracking::smw::SmwAnalyzer;
+
+/// One step of a scripted timeline.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Memory writes applied before the poll is answered.
+    Ram(Vec<(u32, Vec<u8>)>),
+    /// The server drops the connection instead of answering the poll.
+    Disconnect,
+}
+
+struct MockState {
+    devices: Vec<String>,
+    /// `Info` results: firmware version, server version, ROM name, then flags.
+    info: Vec<String>,
+    memory: HashMap<u32, u8>,
+    timeline: VecDeque<Step>,
+    /// Reads covering this address count as a tracker poll and advance the timeline.
+    poll_address: u32,
+    polls: usize,
+    attached: Vec<String>,
+    /// SD card contents by absolute path; folders are paths mapped to `None`.
+    files: BTreeMap<String, Option<Vec<u8>>>,
+    uploads: usize,
+    booted: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(rename = "Opcode")]
+    opcode: String,
+    #[serde(rename = "Operands", default)]
+    operands: Vec<String>,
+}
+
+/// Handle to a running mock server; the server lives until the test's runtime shuts down.
+#[derive(Clone)]
+pub struct MockUsb2Snes {
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    disconnects: watch::Sender<u64>,
+}
+
+impl MockUsb2Snes {
+    /// Starts a server offering one FXPak-like device with an empty memory image.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState {
+            devices: vec!["SD2SNES COM3".to_string()],
+            info: vec!["1.11.0".to_string(), "V1.11.0".to_string(), "/romhacks/Test Hack.sfc".to_string()],
+            memory: HashMap::new(),
+            timeline: VecDeque::new(),
+            poll_address: SmwAnalyzer::ADDR_GAME_MODE,
+            polls: 0,
+            attached: Vec::new(),
+            files: BTreeMap::new(),
+            uploads: 0,
+            booted: Vec::new(),
+        }));
+        let (disconnects, _) = watch::channel(0);
+
+        let server = Self { url, state, disconnects };
+        let handle = server.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    if let Ok(ws) = accept_async(socket).await {
+                        handle.serve(ws).await;
+                    }
+                });
+            }
+        });
+        server
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set_devices(&self, devices: &[&str]) {
+        self.state.lock().unwrap().devices = devices.iter().map(|d| d.to_string()).collect();
+    }
+
+    /// Sets the ROM name and capability flags (e.g. `NO_ROM_READ`) reported by `Info`.
+    pub fn set_info(&self, rom_name: &str, flags: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        state.info.truncate(2);
+        state.info.push(rom_name.to_string());
+        state.info.extend(flags.iter().map(|f| f.to_string()));
+    }
+
+    pub fn write(&self, address: u32, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        for (i, &byte) in data.iter().enumerate() {
+            state.memory.insert(address + i as u32, byte);
+        }
+    }
+
+    pub fn read(&self, address: u32, size: u32) -> Vec<u8> {
+        let state = self.state.lock().unwrap();
+        (address..address + size).map(|a| state.memory.get(&a).copied().unwrap_or(0)).collect()
+    }
+
+    /// Queues timeline steps, played one per poll after those already queued.
+    pub fn script(&self, steps: impl IntoIterator<Item = Step>) {
+        self.state.lock().unwrap().timeline.extend(steps);
+    }
+
+    /// Polls answered so far.
+    pub fn polls(&self) -> usize {
+        self.state.lock().unwrap().polls
+    }
+
+    /// Devices clients attached to, in order.
+    pub fn attached(&self) -> Vec<String> {
+        self.state.lock().unwrap().attached.clone()
+    }
+
+    /// Puts a file on the SD card; its folders are created implicitly.
+    pub fn put_remote_file(&self, path: &str, data: &[u8]) {
+        self.state.lock().unwrap().files.insert(path.to_string(), Some(data.to_vec()));
+    }
+
+    pub fn remote_file(&self, path: &str) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().files.get(path).cloned().flatten()
+    }
+
+    /// Files written with `PutFile` so far.
+    pub fn uploads(&self) -> usize {
+        self.state.lock().unwrap().uploads
+    }
+
+    /// Paths booted, in order.
+    pub fn booted(&self) -> Vec<String> {
+        self.state.lock().unwrap().booted.clone()
+    }
+
+    /// Closes every open connection; new connections are still accepted.
+    pub fn disconnect_all(&self) {
+        self.disconnects.send_modify(|generation| *generation += 1);
+    }
+
+    async fn serve(&self, mut ws: WebSocketStream<TcpStream>) {
+        let mut disconnects = self.disconnects.subscribe();
+        // PutAddress sends its data in the following binary message
+        let mut pending_write: Option<u32> = None;
+        // PutFile sends its data in binary messages until the announced size is reached
+        let mut pending_upload: Option<(String, usize, Vec<u8>)> = None;
+
+        loop {
+            let msg = tokio::select! {
+                msg = ws.next() => msg,
+                _ = disconnects.changed() => None,
+            };
+            let Some(Ok(msg)) = msg else {
+                let _ = ws.close(None).await;
+                return;
+            };
+
+            let replies = match msg {
+                Message::Text(text) => {
+                    let Ok(request) = serde_json::from_str::<Request>(&text) else {
+                        continue;
+                    };
+                    if request.opcode == "PutAddress" {
+                        pending_write = request.operands.first().and_then(|a| u32::from_str_radix(a, 16).ok());
+                        continue;
+                    }
+                    if request.opcode == "PutFile" {
+                        let path = request.operands.first().cloned().unwrap_or_default();
+                        let size = request.operands.get(1).and_then(|s| usize::from_str_radix(s, 16).ok()).unwrap_or(0);
+                        pending_upload = Some((path, size, Vec::new()));
+                        continue;
+                    }
+                    match self.handle(&request) {
+                        Some(replies) => replies,
+                        None => {
+                            let _ = ws.close(None).await;
+                            return;
+                        }
+                    }
+                }
+                Message::Binary(data) => {
+                    if let Some(address) = pending_write.take() {
+                        self.write(address, &data);
+                    } else if let Some((path, size, mut received)) = pending_upload.take() {
+                        received.extend_from_slice(&data);
+                        if received.len() < size {
+                            pending_upload = Some((path, size, received));
+                        } else {
+                            let mut state = self.state.lock().unwrap();
+                            state.files.insert(path, Some(received));
+                            state.uploads += 1;
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            for reply in replies {
+                if ws.send(reply).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Answers a request; `None` drops the connection.
+    fn handle(&self, request: &Request) -> Option<Vec<Message>> {
+        let mut state = self.state.lock().unwrap();
+        let results = |results: &[String]| {
+            vec![Message::Text(serde_json::json!({ "Results": results }).to_string().into())]
+        };
+
+        match request.opcode.as_str() {
+            "DeviceList" => Some(results(&state.devices)),
+            "Info" => Some(results(&state.info)),
+            "Attach" => {
+                let device = request.operands.first().cloned().unwrap_or_default();
+                state.attached.push(device);
+                Some(Vec::new())
+            }
+            "GetAddress" => {
+                let ranges: Vec<(u32, u32)> = request
+                    .operands
+                    .chunks(2)
+                    .filter_map(|pair| {
+                        Some((u32::from_str_radix(pair.first()?, 16).ok()?, u32::from_str_radix(pair.get(1)?, 16).ok()?))
+                    })
+                    .collect();
+
+                let poll_address = state.poll_address;
+                if ranges.iter().any(|&(address, size)| (address..address + size).contains(&poll_address)) {
+                    match state.timeline.pop_front() {
+                        Some(Step::Disconnect) => return None,
+                        Some(Step::Ram(writes)) => {
+                            for (address, data) in writes {
+                                for (i, byte) in data.into_iter().enumerate() {
+                                    state.memory.insert(address + i as u32, byte);
+                                }
+                            }
+                        }
+                        None => {}
+                    }
+                    state.polls += 1;
+                }
+
+                let data: Vec<u8> = ranges
+                    .iter()
+                    .flat_map(|&(address, size)| address..address + size)
+                    .map(|a| state.memory.get(&a).copied().unwrap_or(0))
+                    .collect();
+                Some(vec![Message::Binary(data.into())])
+            }
+            "List" => {
+                let folder = request.operands.first().map(|f| f.trim_end_matches('/')).unwrap_or_default();
+                let prefix = format!("{}/", folder);
+                // Folders holding files exist even when they weren't made with MakeDir
+                let mut entries: BTreeMap<String, bool> = BTreeMap::new();
+                for (path, contents) in &state.files {
+                    if let Some(rest) = path.strip_prefix(&prefix) {
+                        match rest.split_once('/') {
+                            Some((dir, _)) => entries.insert(dir.to_string(), true),
+                            None => entries.insert(rest.to_string(), contents.is_none()),
+                        };
+                    }
+                }
+                let listing: Vec<String> = entries
+                    .into_iter()
+                    .flat_map(|(name, is_dir)| [if is_dir { "0" } else { "1" }.to_string(), name])
+                    .collect();
+                Some(results(&listing))
+            }
+            "MakeDir" => {
+                let path = request.operands.first().cloned().unwrap_or_default();
+                state.files.entry(path).or_insert(None);
+                Some(Vec::new())
+            }
+            "Remove" => {
+                let path = request.operands.first().cloned().unwrap_or_default();
+                state.files.remove(&path);
+                Some(Vec::new())
+            }
+            "GetFile" => {
+                let path = request.operands.first().cloned().unwrap_or_default();
+                let data = state.files.get(&path).cloned().flatten()?;
+                let mut replies = results(&[format!("{:X}", data.len())]);
+                replies.extend(data.chunks(1024).map(|chunk| Message::Binary(chunk.to_vec().into())));
+                Some(replies)
+            }
+            "Boot" => {
+                let path = request.operands.first().cloned().unwrap_or_default();
+                state.booted.push(path);
+                Some(Vec::new())
+            }
+            // Name, Menu and Reset have no reply
+            _ => Some(Vec::new()),
+        }
+    }
+}
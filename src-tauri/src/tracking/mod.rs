@@ -2,5 +2,17 @@ pub mod usb2snes;
 pub mod smw;
 pub mod service;
 pub mod commands;
+pub mod timing;
+pub mod practice;
+pub mod hardware;
+pub mod source;
+pub mod retroarch;
+pub mod events;
+pub mod livesplit;
+pub mod memory_map;
+pub mod sram;
+pub mod recording;
+#[cfg(test)]
+pub mod mock_usb2snes;
 
 pub use service::TrackingService;
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use crate::tracking::smw::SmwAnalyzer;
+
+/// Powerup state ($19).
+pub const ADDR_POWERUP: u32 = 0xF50019;
+/// Reserve item box ($0DC2).
+pub const ADDR_ITEM_BOX: u32 = 0xF50DC2;
+/// Level timer digits ($0F31-$0F33: hundreds, tens, ones).
+pub const ADDR_TIMER: u32 = 0xF50F31;
+/// Invulnerability timer ($1497), as after taking a hit.
+pub const ADDR_INVULNERABILITY: u32 = 0xF51497;
+/// Midway point flag ($13CE).
+pub const ADDR_MIDWAY: u32 = 0xF513CE;
+
+/// Highest translevel number reachable from the overworld.
+const MAX_TRANSLEVEL: u8 = 0x5F;
+const GAME_MODE_FADE_TO_LEVEL: u8 = 0x0F;
+/// Value rewritten each poll while invincibility is on; long enough to bridge a slow poll.
+pub const INVULNERABILITY_FRAMES: u8 = 0x7F;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Powerup {
+    Small,
+    Big,
+    Cape,
+    Fire,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemBox {
+    Empty,
+    Mushroom,
+    Flower,
+    Star,
+    Feather,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PracticeAction {
+    SetPowerup { powerup: Powerup },
+    SetItemBox { item: ItemBox },
+    RefillTimer,
+    /// Kept active by the tracking loop until turned off.
+    SetInvincible { enabled: bool },
+    /// Only valid on the overworld.
+    WarpToLevel { level_id: u8 },
+    /// Restarts the current level from the beginning. Only valid inside a level.
+    ResetLevel,
+}
+
+/// Where the game has to be for an action to make sense.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    InLevel,
+    OnOverworld,
+    Anywhere,
+}
+
+impl PracticeAction {
+    pub fn requirement(&self) -> Requirement {
+        match self {
+            PracticeAction::WarpToLevel { .. } => Requirement::OnOverworld,
+            PracticeAction::SetInvincible { .. } => Requirement::Anywhere,
+            _ => Requirement::InLevel,
+        }
+    }
+
+    /// The RAM writes that perform this action, in order.
+    pub fn writes(&self) -> Result<Vec<(u32, Vec<u8>)>, String> {
+        let writes = match *self {
+            PracticeAction::SetPowerup { powerup } => vec![(ADDR_POWERUP, vec![powerup as u8])],
+            PracticeAction::SetItemBox { item } => vec![(ADDR_ITEM_BOX, vec![item as u8])],
+            PracticeAction::RefillTimer => vec![(ADDR_TIMER, vec![9, 9, 9])],
+            PracticeAction::SetInvincible { enabled } => {
+                vec![(ADDR_INVULNERABILITY, vec![if enabled { INVULNERABILITY_FRAMES } else { 0 }])]
+            }
+            PracticeAction::WarpToLevel { level_id } => {
+                if level_id == 0 || level_id > MAX_TRANSLEVEL {
+                    return Err(format!("Level {:02X} is not an overworld level", level_id));
+                }
+                vec![
+                    (SmwAnalyzer::ADDR_LEVEL_ID, vec![level_id]),
+                    (SmwAnalyzer::ADDR_GAME_MODE, vec![GAME_MODE_FADE_TO_LEVEL]),
+                ]
+            }
+            PracticeAction::ResetLevel => vec![
+                (ADDR_MIDWAY, vec![0]),
+                (SmwAnalyzer::ADDR_GAME_MODE, vec![GAME_MODE_FADE_TO_LEVEL]),
+            ],
+        };
+        Ok(writes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_practice_action_writes() {
+        let writes = PracticeAction::SetPowerup { powerup: Powerup::Cape }.writes().unwrap();
+        assert_eq!(writes, vec![(ADDR_POWERUP, vec![2])]);
+
+        let writes = PracticeAction::SetItemBox { item: ItemBox::Feather }.writes().unwrap();
+        assert_eq!(writes, vec![(ADDR_ITEM_BOX, vec![4])]);
+
+        assert!(PracticeAction::WarpToLevel { level_id: 0x60 }.writes().is_err());
+        assert_eq!(PracticeAction::WarpToLevel { level_id: 0x05 }.requirement(), Requirement::OnOverworld);
+    }
+
+    #[test]
+    fn test_practice_action_deserializes_from_frontend() {
+        let action: PracticeAction = serde_json::from_str(r#"{"action":"set_powerup","powerup":"fire"}"#).unwrap();
+        assert_eq!(action, PracticeAction::SetPowerup { powerup: Powerup::Fire });
+
+        let action: PracticeAction = serde_json::from_str(r#"{"action":"reset_level"}"#).unwrap();
+        assert_eq!(action, PracticeAction::ResetLevel);
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tracking::smw::{SmwAnalyzer, TrackingUpdate};
+use crate::tracking::source::{MemorySource, SourceFuture};
+
+/// Extension of tracking recordings.
+pub const RECORDING_EXTENSION: &str = "rhrec";
+const MAGIC: &[u8; 5] = b"RHREC";
+const VERSION: u8 = 1;
+
+/// Describes what was recorded; stored as JSON at the start of the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    /// File name of the ROM being played, used to detect the hack on replay.
+    pub rom_name: Option<String>,
+    pub memory_map: String,
+    pub recorded_at: String,
+}
+
+/// One poll: the time since the recording started and the reads whose bytes changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedPoll {
+    pub at_ms: u64,
+    pub changes: Vec<(u32, Vec<u8>)>,
+}
+
+/// Writes every polled memory snapshot to a file.
+///
+/// Layout (little endian): `RHREC`, a version byte, a u32 length and the JSON header,
+/// then one record per poll: u32 milliseconds since the start, u16 change count and
+/// per change a u32 address, u16 length and the bytes. Only reads that changed since
+/// the previous poll are stored, which keeps an hour of play to a few hundred KiB.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    last: HashMap<u32, Vec<u8>>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, header: &RecordingHeader) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        let json = serde_json::to_vec(header).map_err(|e| e.to_string())?;
+        writer.write_all(MAGIC).map_err(|e| e.to_string())?;
+        writer.write_all(&[VERSION]).map_err(|e| e.to_string())?;
+        writer.write_all(&(json.len() as u32).to_le_bytes()).map_err(|e| e.to_string())?;
+        writer.write_all(&json).map_err(|e| e.to_string())?;
+        writer.flush().map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            last: HashMap::new(),
+        })
+    }
+
+    /// Appends a poll's reads, timestamped now.
+    pub fn record(&mut self, ram: &HashMap<u32, Vec<u8>>) -> Result<(), String> {
+        let at_ms = self.started.elapsed().as_millis() as u64;
+        self.record_at(at_ms, ram)
+    }
+
+    pub fn record_at(&mut self, at_ms: u64, ram: &HashMap<u32, Vec<u8>>) -> Result<(), String> {
+        let mut changes: Vec<(&u32, &Vec<u8>)> = ram
+            .iter()
+            .filter(|(address, data)| self.last.get(address) != Some(data))
+            .collect();
+        changes.sort();
+
+        let mut record = Vec::new();
+        record.extend_from_slice(&(at_ms as u32).to_le_bytes());
+        record.extend_from_slice(&(changes.len() as u16).to_le_bytes());
+        for (address, data) in &changes {
+            record.extend_from_slice(&address.to_le_bytes());
+            record.extend_from_slice(&(data.len() as u16).to_le_bytes());
+            record.extend_from_slice(data);
+        }
+        // Flushed per poll so a crash keeps everything up to it
+        self.writer.write_all(&record).map_err(|e| e.to_string())?;
+        self.writer.flush().map_err(|e| e.to_string())?;
+
+        for (address, data) in changes {
+            self.last.insert(*address, data.clone());
+        }
+        Ok(())
+    }
+}
+
+/// A recording loaded back from disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    pub header: RecordingHeader,
+    pub polls: Vec<RecordedPoll>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let mut reader = ByteReader { data, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("Not a tracking recording".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!("Unsupported recording version {}", version));
+        }
+        let header_len = reader.u32()? as usize;
+        let header = serde_json::from_slice(reader.take(header_len)?)
+            .map_err(|e| format!("Invalid recording header: {}", e))?;
+
+        let mut polls = Vec::new();
+        while reader.pos < data.len() {
+            let at_ms = reader.u32()? as u64;
+            let count = reader.u16()?;
+            let mut changes = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let address = reader.u32()?;
+                let len = reader.u16()? as usize;
+                changes.push((address, reader.take(len)?.to_vec()));
+            }
+            polls.push(RecordedPoll { at_ms, changes });
+        }
+        Ok(Self { header, polls })
+    }
+
+    /// Runs the recording through an analyzer, one update per poll, for checking the
+    /// analyzer against recorded sessions.
+    pub fn replay_updates(&self, analyzer: &mut SmwAnalyzer) -> Vec<TrackingUpdate> {
+        let mut memory = Memory::default();
+        self.polls
+            .iter()
+            .map(|poll| {
+                memory.apply(&poll.changes);
+                let snapshot = analyzer.memory_map().snapshot(&memory.read_multi(&analyzer.memory_map().reads()));
+                analyzer.interpret(&snapshot)
+            })
+            .collect()
+    }
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or("Recording is truncated")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Sparse memory rebuilt from recorded changes.
+#[derive(Default)]
+struct Memory {
+    bytes: HashMap<u32, u8>,
+}
+
+impl Memory {
+    fn apply(&mut self, changes: &[(u32, Vec<u8>)]) {
+        for (address, data) in changes {
+            for (i, &byte) in data.iter().enumerate() {
+                self.bytes.insert(address + i as u32, byte);
+            }
+        }
+    }
+
+    fn read_multi(&self, reads: &[(u32, u32)]) -> HashMap<u32, Vec<u8>> {
+        reads
+            .iter()
+            .map(|&(address, size)| {
+                let data = (address..address + size).map(|a| self.bytes.get(&a).copied().unwrap_or(0)).collect();
+                (address, data)
+            })
+            .collect()
+    }
+}
+
+/// Plays a recording back as if a device were attached.
+///
+/// Every recorded poll is played, one per read, so the analyzer sees exactly what the
+/// tracker saw. With a speed, the replay asks the tracker to poll when the next poll
+/// comes due at the recorded times scaled by it (2.0 plays twice as fast); without
+/// one, polls follow the tracker's own interval. Reads fail once the recording is
+/// over, which the tracker handles like a lost connection.
+pub struct ReplaySource {
+    name: String,
+    recording: Recording,
+    memory: Memory,
+    next_poll: usize,
+    speed: Option<f64>,
+    started: Option<Instant>,
+}
+
+impl ReplaySource {
+    pub fn new(name: &str, recording: Recording, speed: Option<f64>) -> Result<Self, String> {
+        if speed.is_some_and(|s| !s.is_finite() || s <= 0.0) {
+            return Err("Replay speed must be positive".to_string());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            recording,
+            memory: Memory::default(),
+            next_poll: 0,
+            speed,
+            started: None,
+        })
+    }
+
+    /// Milliseconds of a poll since the first one.
+    fn offset_ms(&self, poll: usize) -> u64 {
+        let first = self.recording.polls.first().map_or(0, |p| p.at_ms);
+        self.recording.polls[poll].at_ms.saturating_sub(first)
+    }
+}
+
+impl MemorySource for ReplaySource {
+    fn description(&self) -> String {
+        format!("Replay: {}", self.name)
+    }
+
+    fn read_multi<'a>(&'a mut self, reads: &'a [(u32, u32)]) -> SourceFuture<'a, HashMap<u32, Vec<u8>>> {
+        Box::pin(async move {
+            let Some(poll) = self.recording.polls.get(self.next_poll) else {
+                return Err("Replay finished".to_string());
+            };
+            self.memory.apply(&poll.changes);
+            self.next_poll += 1;
+            self.started.get_or_insert_with(Instant::now);
+            Ok(self.memory.read_multi(reads))
+        })
+    }
+
+    fn write_memory<'a>(&'a mut self, _address: u32, _data: &'a [u8]) -> SourceFuture<'a, ()> {
+        Box::pin(async { Err("Replays are read-only".to_string()) })
+    }
+
+    fn can_read_rom(&self) -> bool {
+        false
+    }
+
+    fn can_write(&self) -> bool {
+        false
+    }
+
+    fn rom_name(&mut self) -> SourceFuture<'_, Option<String>> {
+        Box::pin(async move { Ok(self.recording.header.rom_name.clone()) })
+    }
+
+    fn is_replay(&self) -> bool {
+        true
+    }
+
+    fn recorded_time(&self) -> Option<Duration> {
+        let current = self.next_poll.checked_sub(1)?;
+        Some(Duration::from_millis(self.offset_ms(current)))
+    }
+
+    fn poll_delay(&self) -> Option<Duration> {
+        let (speed, started) = (self.speed?, self.started?);
+        if self.next_poll >= self.recording.polls.len() {
+            return Some(Duration::ZERO);
+        }
+        let due = Duration::from_secs_f64(self.offset_ms(self.next_poll) as f64 / 1000.0 / speed);
+        Some(due.saturating_sub(started.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracking::memory_map::MemoryMap;
+    use crate::tracking::smw::{ExitClear, ExitType, GamePhase, LevelEvent};
+    use crate::tracking::timing::{frames_to_ms, FrameTimer};
+    use tempfile::TempDir;
+
+    fn ram(game_mode: u8, level_id: u8, exit_type: u8, events: u8) -> HashMap<u32, Vec<u8>> {
+        let map = MemoryMap::vanilla();
+        let mut ram: HashMap<u32, Vec<u8>> = map.reads().into_iter().map(|(a, s)| (a, vec![0; s as usize])).collect();
+        ram.insert(map.game_mode.address, vec![game_mode]);
+        ram.insert(map.level_id.address, vec![level_id]);
+        ram.insert(map.exit_type.address, vec![exit_type]);
+        let mut event_flags = vec![0; map.event_flags.size as usize];
+        event_flags[0] = events;
+        ram.insert(map.event_flags.address, event_flags);
+        ram
+    }
+
+    /// Enters level 5 and takes its secret exit.
+    fn record_session(path: &Path) {
+        let header = RecordingHeader {
+            rom_name: Some("Test Hack.sfc".to_string()),
+            memory_map: "Super Mario World".to_string(),
+            recorded_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        let mut recorder = Recorder::create(path, &header).unwrap();
+        for (at_ms, poll) in [
+            (0, ram(0x0E, 0x00, 0, 0x00)),
+            (1000, ram(0x14, 0x05, 0, 0x00)),
+            (2000, ram(0x14, 0x05, 0, 0x00)),
+            (3000, ram(0x0E, 0x05, 2, 0x40)),
+        ] {
+            recorder.record_at(at_ms, &poll).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_recording_round_trip_stores_only_changes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.rhrec");
+        record_session(&path);
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.header.rom_name.as_deref(), Some("Test Hack.sfc"));
+        assert_eq!(recording.polls.len(), 4);
+        assert_eq!(recording.polls[1].changes.len(), 2, "game mode and level changed");
+        assert!(recording.polls[2].changes.is_empty());
+        assert_eq!(recording.polls[3].at_ms, 3000);
+
+        let data = std::fs::read(&path).unwrap();
+        assert!(Recording::parse(&data[..data.len() - 1]).is_err());
+        assert!(Recording::parse(b"not a recording").is_err());
+    }
+
+    #[test]
+    fn test_replay_through_analyzer() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.rhrec");
+        record_session(&path);
+
+        let updates = Recording::load(&path).unwrap().replay_updates(&mut SmwAnalyzer::new());
+        assert_eq!(updates[1].level_id, Some(5));
+        assert_eq!(updates[3].new_exits, vec![ExitClear { level_id: 5, event_id: 1, exit_type: ExitType::Secret }]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_source_steps_then_finishes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.rhrec");
+        record_session(&path);
+
+        let mut source = ReplaySource::new("session.rhrec", Recording::load(&path).unwrap(), None).unwrap();
+        assert_eq!(source.rom_name().await.unwrap().as_deref(), Some("Test Hack.sfc"));
+        let mut game_modes = Vec::new();
+        while let Ok(data) = source.read_memory(SmwAnalyzer::ADDR_GAME_MODE, 1).await {
+            game_modes.push(data[0]);
+        }
+        assert_eq!(game_modes, vec![0x0E, 0x14, 0x14, 0x0E]);
+
+        assert_eq!(source.recorded_time(), Some(Duration::from_millis(3000)));
+        assert_eq!(source.poll_delay(), None);
+
+        let mut fast = ReplaySource::new("session.rhrec", Recording::load(&path).unwrap(), Some(1000.0)).unwrap();
+        assert_eq!(fast.read_memory(SmwAnalyzer::ADDR_GAME_MODE, 1).await.unwrap(), vec![0x0E]);
+        // The next poll was recorded a second later, due after 1ms at 1000x
+        assert!(fast.poll_delay().unwrap() <= Duration::from_millis(1));
+        assert_eq!(fast.read_memory(SmwAnalyzer::ADDR_GAME_MODE, 1).await.unwrap(), vec![0x14]);
+        assert!(ReplaySource::new("x", Recording::load(&path).unwrap(), Some(0.0)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_accelerated_replay_plays_every_poll_with_recorded_timing() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("deaths.rhrec");
+        let map = MemoryMap::vanilla();
+        let poll = |game_mode: u8, animation: u8, frames: u32| {
+            let mut ram = ram(game_mode, 0x05, 0, 0x00);
+            ram.insert(map.player_animation.address, vec![animation]);
+            ram.insert(map.frame_counter.address, vec![(frames % 256) as u8]);
+            ram
+        };
+        // Polls 5s apart, 300 frames each: the 8-bit counter wraps between every two
+        let mut recorder = Recorder::create(&path, &RecordingHeader::default()).unwrap();
+        for (i, (game_mode, animation)) in [(0x0E, 0), (0x14, 0), (0x14, 0x09), (0x14, 0), (0x0E, 0)].into_iter().enumerate() {
+            recorder.record_at(i as u64 * 5000, &poll(game_mode, animation, i as u32 * 300)).unwrap();
+        }
+
+        let mut source = ReplaySource::new("deaths.rhrec", Recording::load(&path).unwrap(), Some(1000.0)).unwrap();
+        let mut analyzer = SmwAnalyzer::new();
+        let mut timer = FrameTimer::new();
+        let epoch = Instant::now();
+        let (mut updates, mut level_ms, mut deaths) = (0, 0, 0);
+        while let Ok(ram) = source.read_multi(&map.reads()).await {
+            let snapshot = map.snapshot(&ram);
+            let elapsed_ms = timer.advance(snapshot.frame_counter, epoch + source.recorded_time().unwrap());
+            let update = analyzer.interpret(&snapshot);
+            if update.phase == GamePhase::Level {
+                level_ms += elapsed_ms;
+            }
+            deaths += update.level_events.iter().filter(|e| matches!(e, LevelEvent::Death { level_id: 5 })).count();
+            updates += 1;
+            tokio::time::sleep(source.poll_delay().unwrap()).await;
+        }
+
+        assert_eq!(updates, 5);
+        assert_eq!(deaths, 1, "the one-poll death animation is seen");
+        assert_eq!(level_ms, 3 * frames_to_ms(300));
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tokio::net::UdpSocket;
+
+use crate::tracking::source::{MemorySource, SourceFuture};
+use crate::tracking::usb2snes::{plan_ranges, split_ranges};
+
+/// RetroArch's default network command port.
+pub const DEFAULT_RETROARCH_ADDRESS: &str = "127.0.0.1:55355";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Client for RetroArch's UDP network command interface (`network_cmd_enable`).
+///
+/// Memory is accessed with `READ_CORE_MEMORY`/`WRITE_CORE_MEMORY`, which use SNES bus
+/// addresses, so usb2snes style addresses are translated assuming a LoROM layout.
+#[derive(Debug)]
+pub struct RetroArchClient {
+    socket: UdpSocket,
+    address: String,
+    timeout: Duration,
+}
+
+impl RetroArchClient {
+    /// Connects and checks RetroArch answers `GET_STATUS`, since UDP itself can't tell.
+    pub async fn connect(address: &str) -> Result<Self, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| e.to_string())?;
+        socket
+            .connect(address)
+            .await
+            .map_err(|e| format!("Failed to reach RetroArch at {}: {}", address, e))?;
+
+        let mut client = Self {
+            socket,
+            address: address.to_string(),
+            timeout: REQUEST_TIMEOUT,
+        };
+        client.status().await?;
+        Ok(client)
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the `GET_STATUS` reply, e.g. `PLAYING super_nes,Super Mario World,crc32=a31bead4`.
+    pub async fn status(&mut self) -> Result<String, String> {
+        self.request("GET_STATUS".to_string(), "GET_STATUS ").await
+    }
+
+    pub async fn read_core_memory(&mut self, address: u32, size: u32) -> Result<Vec<u8>, String> {
+        let bus = to_bus_address(address)?;
+        let reply = self
+            .request(format!("READ_CORE_MEMORY {:x} {}", bus, size), &format!("READ_CORE_MEMORY {:x} ", bus))
+            .await?;
+
+        if reply.starts_with("-1") {
+            return Err(format!("RetroArch could not read {:06X}: {}", address, reply));
+        }
+        let data = reply
+            .split_whitespace()
+            .map(|b| u8::from_str_radix(b, 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| format!("Malformed READ_CORE_MEMORY reply '{}': {}", reply, e))?;
+        if data.len() != size as usize {
+            return Err(format!("RetroArch returned {} bytes at {:06X}, expected {}", data.len(), address, size));
+        }
+        Ok(data)
+    }
+
+    pub async fn write_core_memory(&mut self, address: u32, data: &[u8]) -> Result<(), String> {
+        let bus = to_bus_address(address)?;
+        let bytes = data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+        let reply = self
+            .request(format!("WRITE_CORE_MEMORY {:x} {}", bus, bytes), &format!("WRITE_CORE_MEMORY {:x} ", bus))
+            .await?;
+
+        if reply.starts_with("-1") {
+            return Err(format!("RetroArch could not write {:06X}: {}", address, reply));
+        }
+        Ok(())
+    }
+
+    /// Sends a command and returns the rest of the first reply starting with `prefix`.
+    /// Replies to earlier, timed out requests are skipped.
+    async fn request(&mut self, command: String, prefix: &str) -> Result<String, String> {
+        self.socket
+            .send(format!("{}\n", command).as_bytes())
+            .await
+            .map_err(|e| format!("RetroArch send failed: {}", e))?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = vec![0u8; 65536];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let len = tokio::time::timeout(remaining, self.socket.recv(&mut buf))
+                .await
+                .map_err(|_| format!("RetroArch at {} did not answer {}", self.address, command))?
+                .map_err(|e| format!("RetroArch receive failed: {}", e))?;
+
+            let reply = String::from_utf8_lossy(&buf[..len]);
+            if let Some(rest) = reply.trim_end().strip_prefix(prefix) {
+                return Ok(rest.to_string());
+            }
+        }
+    }
+}
+
+/// Translates a usb2snes address to the SNES bus address RetroArch expects.
+pub fn to_bus_address(address: u32) -> Result<u32, String> {
+    match address {
+        // WRAM
+        0xF50000..=0xF6FFFF => Ok(0x7E0000 + (address - 0xF50000)),
+        // SRAM, mapped at $70:0000 on LoROM
+        0xE00000..=0xEFFFFF => {
+            let offset = address - 0xE00000;
+            Ok(0x700000 + ((offset / 0x8000) << 16) + (offset % 0x8000))
+        }
+        // ROM, LoROM banks of 32 KiB at $8000
+        0x000000..=0x3DFFFF => Ok(((address / 0x8000) << 16) | 0x8000 | (address % 0x8000)),
+        _ => Err(format!("Address {:06X} can't be read through RetroArch", address)),
+    }
+}
+
+impl MemorySource for RetroArchClient {
+    fn description(&self) -> String {
+        format!("RetroArch: {}", self.address)
+    }
+
+    fn read_multi<'a>(&'a mut self, reads: &'a [(u32, u32)]) -> SourceFuture<'a, HashMap<u32, Vec<u8>>> {
+        Box::pin(async move {
+            let ranges = plan_ranges(reads);
+            let mut data = Vec::new();
+            for range in &ranges {
+                data.extend(self.read_core_memory(range.address, range.size).await?);
+            }
+            split_ranges(reads, &ranges, &data)
+        })
+    }
+
+    fn write_memory<'a>(&'a mut self, address: u32, data: &'a [u8]) -> SourceFuture<'a, ()> {
+        Box::pin(self.write_core_memory(address, data))
+    }
+
+    fn can_read_rom(&self) -> bool {
+        false
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn rom_name(&mut self) -> SourceFuture<'_, Option<String>> {
+        Box::pin(async move {
+            let status = self.status().await?;
+            // PLAYING <system>,<content name>,crc32=<crc>
+            Ok(status
+                .strip_prefix("PLAYING ")
+                .or_else(|| status.strip_prefix("PAUSED "))
+                .and_then(|rest| rest.split(',').nth(1))
+                .map(|name| name.to_string()))
+        })
+    }
+
+    fn keepalive(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async move { self.status().await.map(|_| ()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in for RetroArch answering network commands from a 128 KiB WRAM buffer.
+    async fn mock_retroarch() -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut wram = vec![0u8; 0x20000];
+            wram[0x100] = 0x14;
+            let mut buf = [0u8; 1024];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let command = String::from_utf8_lossy(&buf[..len]).trim().to_string();
+                let parts: Vec<&str> = command.split_whitespace().collect();
+                let reply = match parts.as_slice() {
+                    ["GET_STATUS"] => "GET_STATUS PLAYING super_nes,Kaizo Mario World,crc32=deadbeef".to_string(),
+                    ["READ_CORE_MEMORY", addr, size] => {
+                        let bus = u32::from_str_radix(addr, 16).unwrap();
+                        let size: usize = size.parse().unwrap();
+                        if !(0x7E0000..0x800000).contains(&bus) {
+                            format!("READ_CORE_MEMORY {} -1 no memory at address", addr)
+                        } else {
+                            let start = (bus - 0x7E0000) as usize;
+                            let bytes: Vec<String> = wram[start..start + size].iter().map(|b| format!("{:02x}", b)).collect();
+                            format!("READ_CORE_MEMORY {} {}", addr, bytes.join(" "))
+                        }
+                    }
+                    ["WRITE_CORE_MEMORY", addr, bytes @ ..] => {
+                        let start = (u32::from_str_radix(addr, 16).unwrap() - 0x7E0000) as usize;
+                        for (i, b) in bytes.iter().enumerate() {
+                            wram[start + i] = u8::from_str_radix(b, 16).unwrap();
+                        }
+                        format!("WRITE_CORE_MEMORY {} {}", addr, bytes.len())
+                    }
+                    _ => continue,
+                };
+                let _ = socket.send_to(reply.as_bytes(), peer).await;
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn test_to_bus_address() {
+        assert_eq!(to_bus_address(0xF50100).unwrap(), 0x7E0100);
+        assert_eq!(to_bus_address(0xF61F02).unwrap(), 0x7F1F02);
+        assert_eq!(to_bus_address(0x007FC0).unwrap(), 0x00FFC0);
+        assert_eq!(to_bus_address(0xE00000).unwrap(), 0x700000);
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_writes_through_udp() {
+        let address = mock_retroarch().await;
+        let mut client = RetroArchClient::connect(&address).await.unwrap();
+
+        assert_eq!(client.rom_name().await.unwrap().as_deref(), Some("Kaizo Mario World"));
+
+        client.write_core_memory(0xF50019, &[2]).await.unwrap();
+        let ram = MemorySource::read_multi(&mut client, &[(0xF50100, 1), (0xF50019, 1)]).await.unwrap();
+        assert_eq!(ram[&0xF50100], vec![0x14]);
+        assert_eq!(ram[&0xF50019], vec![2]);
+
+        assert!(client.read_core_memory(0x007FC0, 32).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_without_retroarch() {
+        // Nothing answers on this freshly bound and dropped port
+        let port = UdpSocket::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        assert!(RetroArchClient::connect(&format!("127.0.0.1:{}", port)).await.is_err());
+    }
+}
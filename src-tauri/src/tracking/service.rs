@@ -1,22 +1,52 @@
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use crate::tracking::usb2snes::Usb2SnesClient;
-use crate::tracking::smw::{SmwAnalyzer, TrackingUpdate};
+use tokio::sync::{broadcast, Mutex};
+use crate::tracking::usb2snes::{parse_endpoints, select_device, Usb2SnesClient};
+use crate::tracking::source::MemorySource;
+use crate::tracking::retroarch::{RetroArchClient, DEFAULT_RETROARCH_ADDRESS};
+use serde::Serialize;
+use crate::tracking::smw::{AttemptOutcome, ExitClear, GamePhase, LevelEvent, SmwAnalyzer, TrackingUpdate};
+use crate::tracking::events::{EventBus, TrackingEvent};
+use crate::tracking::hardware;
+use crate::tracking::livesplit::LiveSplitBridge;
+use crate::tracking::memory_map::{self, MemoryMap};
+use crate::tracking::recording::{Recorder, Recording, RecordingHeader, ReplaySource};
+use crate::tracking::practice::{self, PracticeAction, Requirement};
+use crate::tracking::sram;
+use crate::tracking::timing::{FrameTimer, IdleDetector, DEFAULT_IDLE_TIMEOUT_SECONDS};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use tokio::time::{sleep, Duration};
 use chrono::Utc;
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::OnceLock;
 
 #[derive(Clone)]
 pub struct TrackingService {
-    client: Arc<Mutex<Option<Usb2SnesClient>>>,
+    client: Arc<Mutex<Option<Box<dyn MemorySource>>>>,
     analyzer: Arc<Mutex<SmwAnalyzer>>,
     db_pool: Pool<SqliteConnectionManager>,
     active_hack_id: Arc<Mutex<Option<i64>>>,
     current_session_id: Arc<Mutex<Option<i64>>>,
+    /// The `level_attempts` row of the attempt in progress.
+    open_attempt: Arc<Mutex<Option<i64>>>,
     last_fingerprint_time: Arc<Mutex<Option<std::time::Instant>>>,
+    practice_invincible: Arc<Mutex<bool>>,
+    /// The emulator process started by `launch_hack`, while it's running.
+    emulator_pid: Arc<Mutex<Option<u32>>>,
+    events: EventBus,
+    poll_interval: Duration,
+    /// Writes every poll to a file while recording.
+    recorder: Arc<Mutex<Option<Recorder>>>,
+    /// A replay to attach to instead of the configured source on the next connect.
+    replay: Arc<Mutex<Option<Box<dyn MemorySource>>>>,
+    /// Where the save is backed up whenever a session ends; set once by the app.
+    save_backup_root: Arc<OnceLock<PathBuf>>,
 }
 
+/// Time between memory polls.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 impl TrackingService {
     pub fn new(db_pool: Pool<SqliteConnectionManager>) -> Self {
         Self {
@@ -25,12 +55,44 @@ impl TrackingService {
             db_pool,
             active_hack_id: Arc::new(Mutex::new(None)),
             current_session_id: Arc::new(Mutex::new(None)),
+            open_attempt: Arc::new(Mutex::new(None)),
             last_fingerprint_time: Arc::new(Mutex::new(None)),
+            practice_invincible: Arc::new(Mutex::new(false)),
+            emulator_pid: Arc::new(Mutex::new(None)),
+            events: EventBus::new(),
+            poll_interval: POLL_INTERVAL,
+            recorder: Arc::new(Mutex::new(None)),
+            replay: Arc::new(Mutex::new(None)),
+            save_backup_root: Arc::new(OnceLock::new()),
         }
     }
 
+    /// Polls faster or slower than once a second, e.g. to run tests quickly.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Backs up the hack's save into `root` whenever a play session ends. Done where the
+    /// session is closed rather than from an event listener, which could miss it.
+    pub fn back_up_saves_to(&self, root: PathBuf) {
+        let _ = self.save_backup_root.set(root);
+    }
+
+    /// Live tracking events, forwarded to the frontend as `TRACKING_EVENT`.
+    pub fn subscribe(&self) -> broadcast::Receiver<TrackingEvent> {
+        self.events.subscribe()
+    }
+
+    /// Switching to a different hack closes the session of the previous one.
     pub async fn set_active_hack(&self, hack_id: i64) {
         let mut guard = self.active_hack_id.lock().await;
+        if *guard != Some(hack_id) {
+            let mut sess_guard = self.current_session_id.lock().await;
+            let mut attempt_guard = self.open_attempt.lock().await;
+            finish_session(&self.db_pool, &self.events, self.save_backup_root.get(), &mut sess_guard, &mut attempt_guard, SessionEnd::HackChanged);
+            self.events.emit(TrackingEvent::HackDetected { hack_id });
+        }
         *guard = Some(hack_id);
     }
     
@@ -39,31 +101,92 @@ impl TrackingService {
         *guard = None;
     }
 
+    /// Closes the open play session, if any, and forgets the active hack.
+    pub async fn end_session(&self, reason: SessionEnd) {
+        let mut guard = self.active_hack_id.lock().await;
+        let mut sess_guard = self.current_session_id.lock().await;
+        let mut attempt_guard = self.open_attempt.lock().await;
+        finish_session(&self.db_pool, &self.events, self.save_backup_root.get(), &mut sess_guard, &mut attempt_guard, reason);
+        *guard = None;
+    }
+
+    /// Waits for an emulator started by `launch_hack` to exit, then ends its session.
+    /// A newer launch takes over supervision, so an older emulator exiting is ignored.
+    pub async fn supervise_emulator(&self, mut child: Child) {
+        let pid = child.id();
+        *self.emulator_pid.lock().await = Some(pid);
+
+        match tokio::task::spawn_blocking(move || child.wait()).await {
+            Ok(Ok(status)) => eprintln!("Tracking: Emulator (pid {}) exited with {}", pid, status),
+            Ok(Err(e)) => eprintln!("Tracking: Failed waiting on emulator (pid {}): {}", pid, e),
+            Err(e) => eprintln!("Tracking: Emulator watcher failed: {}", e),
+        }
+
+        {
+            let mut pid_guard = self.emulator_pid.lock().await;
+            if *pid_guard != Some(pid) {
+                return;
+            }
+            *pid_guard = None;
+        }
+        self.end_session(SessionEnd::EmulatorExited).await;
+    }
+
     pub async fn start_background_task(&self) {
         let client_state = self.client.clone();
         let analyzer = self.analyzer.clone();
         let db_pool = self.db_pool.clone();
         let active_hack = self.active_hack_id.clone();
         let session_id = self.current_session_id.clone();
+        let open_attempt = self.open_attempt.clone();
         let last_fingerprint = self.last_fingerprint_time.clone();
+        let practice_invincible = self.practice_invincible.clone();
+        let emulator_pid = self.emulator_pid.clone();
+        let events = self.events.clone();
+        let poll_interval = self.poll_interval;
+        let recorder = self.recorder.clone();
+        let replay = self.replay.clone();
+        let save_backup_root = self.save_backup_root.clone();
 
         tokio::spawn(async move {
             let mut rom_block_until: Option<std::time::Instant> = None;
+            let mut frame_timer = FrameTimer::new();
+            let mut idle_detector = IdleDetector::new();
+            let mut loaded_rom: Option<LoadedRom> = None;
+            let mut disconnected_since: Option<std::time::Instant> = None;
+            let mut connected = false;
+            // Replays only emit events; nothing is written to the user's stats
+            let mut replaying = false;
+            // The live ROM and hack, set aside while a replay runs and restored after it
+            let mut live_state: Option<(Option<LoadedRom>, Option<i64>)> = None;
+            let mut livesplit = LiveSplitBridge::new();
+            // The hack whose memory map the analyzer uses
+            let mut map_hack: Option<Option<i64>> = None;
+            // Replays report when each poll was recorded; the timer counts from here
+            let replay_epoch = std::time::Instant::now();
             loop {
                 // 0. Check Config
-                let (enable_tracking, debug_logging) = {
-                     let conn = db_pool.get();
-                     if let Ok(conn) = conn {
-                         if let Ok(config) = crate::config::Config::load(&conn) {
-                             (config.enable_auto_tracking.unwrap_or(false), config.enable_debug_logging.unwrap_or(false))
-                         } else { (false, false) }
-                     } else { (false, false) }
-                };
+                let config = db_pool.get().ok().and_then(|conn| crate::config::Config::load(&conn).ok());
+                let enable_tracking = config.as_ref().and_then(|c| c.enable_auto_tracking).unwrap_or(false);
+                let debug_logging = config.as_ref().and_then(|c| c.enable_debug_logging).unwrap_or(false);
+                let idle_timeout_seconds = config.as_ref()
+                    .and_then(|c| c.idle_timeout_seconds)
+                    .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECONDS);
 
                 if !enable_tracking {
                     // Ensure disconnected
                      let mut cl_guard = client_state.lock().await;
                      *cl_guard = None;
+                     if replaying {
+                         replaying = false;
+                         end_replay(&mut live_state, &mut loaded_rom, &active_hack).await;
+                         map_hack = None;
+                     }
+                     frame_timer.reset();
+                     if connected {
+                         connected = false;
+                         events.emit(TrackingEvent::Disconnected);
+                     }
                      sleep(Duration::from_secs(5)).await;
                      continue;
                 }
@@ -73,193 +196,223 @@ impl TrackingService {
                     let mut cl_guard = client_state.lock().await;
                     
                     if cl_guard.is_none() {
-                         // Connect to modern port 23074
-                         match Usb2SnesClient::connect("ws://127.0.0.1:23074").await {
-                             Ok(mut client) => {
-                                 if debug_logging { eprintln!("Tracking: Connected to usb2snes (23074)"); }
-                                 let _ = client.register_app("ROM Hack Manager").await;
-                                 match client.list_devices().await {
-                                     Ok(devices) => {
-                                         if let Some(first) = devices.first() {
-                                             if debug_logging { eprintln!("Tracking: Attaching to {}", first); }
-                                             if let Err(e) = client.attach(first).await {
-                                                 if debug_logging { eprintln!("Tracking: Attach failed: {}", e); }
-                                             } else {
-                                                 // Get device info (flags)
-                                                 match client.info().await {
-                                                     Ok(info) => {
-                                                         eprintln!("Tracking: Full Device Info: {:?}", info);
-                                                         // info[2] is usually the ROM name/path
-                                                     },
-                                                     Err(e) => eprintln!("Tracking: Info failed: {}", e),
-                                                 }
-                                                 *cl_guard = Some(client);
-                                             }
-                                         }
-                                     }
-                                     Err(_e) => {
-                                         // eprintln!("Tracking: ListDevices failed: {}", _e);
-                                     }
+                         let source = match replay.lock().await.take() {
+                             Some(source) => Ok(source),
+                             None => connect_source(config.as_ref()).await,
+                         };
+                         match source {
+                             Ok(client) => {
+                                 if debug_logging {
+                                     eprintln!("Tracking: Attached to {}", client.description());
+                                 }
+                                 connected = true;
+                                 if client.is_replay() && !replaying {
+                                     // The replay identifies its own hack; the live one comes back after it
+                                     live_state = Some((loaded_rom.take(), active_hack.lock().await.take()));
+                                     *last_fingerprint.lock().await = None;
+                                     map_hack = None;
                                  }
+                                 replaying = client.is_replay();
+                                 frame_timer.reset();
+                                 events.emit(TrackingEvent::Connected { source: client.description() });
+                                 *cl_guard = Some(client);
                              }
-                             Err(_e) => {
-                                 // Log periodically/silently?
-                                 // eprintln!("Tracking: Connection failed: {}", e);
+                             Err(e) => {
+                                 // Servers are often simply not running; stay quiet and retry next tick
+                                 if debug_logging { eprintln!("Tracking: {}", e); }
                              }
                          }
                     }
                 }
 
-                // 2. Passive Tracking (Auto-Detection)
+                // 2. Passive Tracking (Auto-Detection, ROM changes)
                 {
                     let mut cl_guard = client_state.lock().await; 
                     if let Some(client) = cl_guard.as_mut() {
                         let mut active_id_guard = active_hack.lock().await;
+                        let mut last_fp = last_fingerprint.lock().await;
+                        let should_check = match *last_fp {
+                            Some(t) => t.elapsed() > Duration::from_secs(5),
+                            None => true,
+                        };
 
-                        if active_id_guard.is_none() {
-                             let mut last_fp = last_fingerprint.lock().await;
-                             let should_check = match *last_fp {
-                                 Some(t) => t.elapsed() > Duration::from_secs(5),
-                                 None => true,
-                             };
-                             
-                             if should_check {
-                                 // Check blockade
-                                 if let Some(until) = rom_block_until {
-                                     if std::time::Instant::now() >= until {
-                                         rom_block_until = None;
-                                     }
-                                 }
+                        // Check blockade
+                        if let Some(until) = rom_block_until {
+                            if std::time::Instant::now() >= until {
+                                rom_block_until = None;
+                            }
+                        }
 
-                                 if rom_block_until.is_none() {
-                                     // Check capabilities
-                                     let can_read_rom = !client.flags.iter().any(|f| f == "NO_ROM_READ");
-                                     
-                                     if can_read_rom {
-                                         *last_fp = Some(std::time::Instant::now());
-                                         
-                                         // Read Title (FFC0, 21 bytes) + Checksum (FFDC, 4 bytes)
-                                         match client.read_memory(0x00FFC0, 32).await {
-                                     Ok(data) => {
-                                          if data.len() >= 32 {
-                                              // Checksum at offset 0x1E (FFDE) is 2 bytes checksum
-                                              // But typically we used FFDC-FFDF.
-                                              // Low byte at 30, High at 31.
-                                              
-                                              let checksum = ((data[31] as u16) << 8) | (data[30] as u16);
-                                              let checksum_hex = format!("{:04X}", checksum);
-                                              
-                                              let conn = db_pool.get();
-                                              if let Ok(conn) = conn {
-                                                  // Find by checksum
-                                                  let found_id: Result<i64, _> = conn.query_row(
-                                                      "SELECT id FROM hacks WHERE rom_checksum = ?1 LIMIT 1",
-                                                      [&checksum_hex],
-                                                      |row| row.get(0)
-                                                  );
-                                                  
-                                                  if let Ok(id) = found_id {
-                                                      eprintln!("Tracking: Auto-detected hack ID: {} (Checksum: {})", id, checksum_hex);
-                                                      *active_id_guard = Some(id);
-                                                  }
-                                              }
-                                          }
-                                     }
-                                     Err(_) => {
-                                         // If read fails (likely connection abort on some emulators),
-                                         // block passive detection for a while to avoid flapping.
-                                         eprintln!("Tracking: ROM Read failed, pausing passive detection for 60s");
-                                         rom_block_until = Some(std::time::Instant::now() + Duration::from_secs(60));
-                                         *cl_guard = None;
-                                     }
-                                 }
-                                 } else {
-                                     // Fallback: Poll Info for ROM Name (RetroArch support)
-                                     *last_fp = Some(std::time::Instant::now());
-                                     
-                                     // client.info() is cheap/text-based
-                                     match client.info().await {
-                                         Ok(info_vec) => {
-                                             if info_vec.len() > 2 {
-                                                 let rom_name = &info_vec[2];
-                                                  let path = std::path::Path::new(rom_name);
-                                                  if let Some(file_name) = path.file_name() {
-                                                      if let Some(name_str) = file_name.to_str() {
-                                                          if name_str != "No Info" && !name_str.contains("menu.bin") && !name_str.is_empty() {
-                                                              let conn = db_pool.get();
-                                                              if let Ok(conn) = conn {
-                                                                  eprintln!("Tracking: Searching for hack with name/file containing '{}'", name_str);
-                                                                  // Try fuzzy match on file_path OR name
-                                                                  let found_id: Result<i64, _> = conn.query_row(
-                                                                      "SELECT id FROM hacks WHERE file_path LIKE '%' || ?1 || '%' OR name LIKE '%' || ?1 || '%' LIMIT 1",
-                                                                      [name_str],
-                                                                      |row| row.get(0)
-                                                                  );
-                                                                  
-                                                                  if let Ok(id) = found_id {
-                                                                      eprintln!("Tracking: Auto-detected hack ID via Info: {} (Query: {})", id, name_str);
-                                                                      *active_id_guard = Some(id);
-                                                                  } else {
-                                                                      eprintln!("Tracking: No match found for '{}'", name_str);
-                                                                  }
-                                                              }
-                                                          }
-                                                      }
-                                                  }
-                                             }
-                                         }
-                                         Err(_) => {}
-                                     }
-                                 }
-                             }
-                             }
+                        if should_check && rom_block_until.is_none() {
+                            *last_fp = Some(std::time::Instant::now());
+                            let can_read_rom = client.can_read_rom();
+
+                            match identify_rom(client.as_mut()).await {
+                                Ok(Some(rom)) => {
+                                    // A replay plays one ROM; the live session isn't its to end
+                                    if !replaying && loaded_rom.as_ref().is_some_and(|previous| previous != &rom) {
+                                        eprintln!("Tracking: Loaded ROM changed, ending session");
+                                        let mut sess_guard = session_id.lock().await;
+                                        let mut attempt_guard = open_attempt.lock().await;
+                                        finish_session(&db_pool, &events, save_backup_root.get(), &mut sess_guard, &mut attempt_guard, SessionEnd::RomChanged);
+                                        *active_id_guard = None;
+                                    }
+
+                                    if active_id_guard.is_none() {
+                                        if let Ok(conn) = db_pool.get() {
+                                            match find_hack_for_rom(&conn, &rom) {
+                                                Some(id) => {
+                                                    eprintln!("Tracking: Auto-detected hack ID: {} ({:?})", id, rom);
+                                                    *active_id_guard = Some(id);
+                                                    events.emit(TrackingEvent::HackDetected { hack_id: id });
+                                                }
+                                                None => {
+                                                    if debug_logging { eprintln!("Tracking: No match found for {:?}", rom); }
+                                                }
+                                            }
+                                        }
+                                    }
+                                    loaded_rom = Some(rom);
+                                }
+                                Ok(None) => {}
+                                Err(_) if can_read_rom => {
+                                    // If read fails (likely connection abort on some emulators),
+                                    // block passive detection for a while to avoid flapping.
+                                    eprintln!("Tracking: ROM Read failed, pausing passive detection for 60s");
+                                    rom_block_until = Some(std::time::Instant::now() + Duration::from_secs(60));
+                                    *cl_guard = None;
+                                }
+                                Err(_) => {}
+                            }
                         }
                     }
                 }
 
+                // Follow the memory map of the active hack
+                {
+                    let hack_id = *active_hack.lock().await;
+                    if map_hack != Some(hack_id) {
+                        // The new analyzer never ends the previous hack's attempt. A live attempt
+                        // is left open during a replay and closed once the live hack is back.
+                        if !replaying {
+                            let mut attempt_guard = open_attempt.lock().await;
+                            if let Ok(conn) = db_pool.get() {
+                                close_attempt(&conn, &mut attempt_guard);
+                            }
+                        }
+                        let map = memory_map_for_hack(&db_pool, hack_id);
+                        if debug_logging { eprintln!("Tracking: Using memory map '{}'", map.name); }
+                        analyzer.lock().await.set_memory_map(map);
+                        map_hack = Some(hack_id);
+                    }
+                }
+
                 // 3. Poll Memory (Active Tracking)
                 let mut update: Option<TrackingUpdate> = None;
+                let mut elapsed_ms: u64 = 0;
+                let mut idle = false;
+                let mut poll_delay = poll_interval;
                 
                 {
                     let mut cl_guard = client_state.lock().await;
                     if let Some(client) = cl_guard.as_mut() {
-                         // Sequential reads
-                         let gamemode = client.read_memory(SmwAnalyzer::ADDR_GAME_MODE, 1).await;
-                         if let Ok(gm) = gamemode {
-                             if let Ok(lvl) = client.read_memory(SmwAnalyzer::ADDR_LEVEL_ID, 1).await {
-                                 if let Ok(ev) = client.read_memory(SmwAnalyzer::ADDR_EVENT_FLAGS, SmwAnalyzer::EVENT_FLAGS_SIZE).await {
-                                     if !gm.is_empty() && !lvl.is_empty() && !ev.is_empty() {
-                                          let mut an = analyzer.lock().await;
-                                          update = Some(an.interpret(gm[0], lvl[0], &ev));
+                         let mut an = analyzer.lock().await;
+                         let ram = match client.keepalive().await {
+                             Ok(()) => client.read_multi(&an.memory_map().reads()).await,
+                             Err(e) => Err(e),
+                         };
+                         match ram {
+                             Ok(ram) => {
+                                 let mut rec_guard = recorder.lock().await;
+                                 if let Some(rec) = rec_guard.as_mut() {
+                                     if let Err(e) = rec.record(&ram) {
+                                         eprintln!("Tracking: Recording failed, stopping it: {}", e);
+                                         *rec_guard = None;
                                      }
-                                 } else { *cl_guard = None; }
-                             } else { *cl_guard = None; }
-                         } else { 
-                             *cl_guard = None; 
+                                 }
+                                 drop(rec_guard);
+                                 let snapshot = an.memory_map().snapshot(&ram);
+                                 if *practice_invincible.lock().await && an.memory_map().phase(snapshot.game_mode) == GamePhase::Level {
+                                     let _ = client.write_memory(practice::ADDR_INVULNERABILITY, &[practice::INVULNERABILITY_FRAMES]).await;
+                                 }
+                                 let now = match client.recorded_time() {
+                                     Some(at) => replay_epoch + at,
+                                     None => std::time::Instant::now(),
+                                 };
+                                 elapsed_ms = frame_timer.advance(snapshot.frame_counter, now);
+                                 idle = idle_detector.update(&snapshot.activity, elapsed_ms, idle_timeout_seconds);
+                                 update = Some(an.interpret(&snapshot));
+                                 if let Some(delay) = client.poll_delay() {
+                                     poll_delay = delay;
+                                 }
+                             }
+                             Err(e) => {
+                                 if debug_logging { eprintln!("Tracking: Memory read failed: {}", e); }
+                                 *cl_guard = None;
+                             }
                          }
                     }
+                    if cl_guard.is_none() {
+                        frame_timer.reset();
+                        if replaying {
+                            replaying = false;
+                            end_replay(&mut live_state, &mut loaded_rom, &active_hack).await;
+                            // The analyzer holds the replay's state; start the live one afresh
+                            map_hack = None;
+                        }
+                        if connected {
+                            connected = false;
+                            events.emit(TrackingEvent::Disconnected);
+                        }
+                        let since = *disconnected_since.get_or_insert_with(std::time::Instant::now);
+                        if since.elapsed() > DISCONNECT_GRACE {
+                            let mut active_id_guard = active_hack.lock().await;
+                            let mut sess_guard = session_id.lock().await;
+                            if sess_guard.is_some() {
+                                eprintln!("Tracking: Disconnected for over {}s, ending session", DISCONNECT_GRACE.as_secs());
+                                let mut attempt_guard = open_attempt.lock().await;
+                                finish_session(&db_pool, &events, save_backup_root.get(), &mut sess_guard, &mut attempt_guard, SessionEnd::Disconnected);
+                                // A hack we launched stays active until its emulator exits
+                                if emulator_pid.lock().await.is_none() {
+                                    *active_id_guard = None;
+                                }
+                            }
+                        }
+                    } else {
+                        disconnected_since = None;
+                    }
                 }
 
-                // 4. Process Update
+                // 4. Auto-Splitting (LiveSplit Server)
+                if let Some(up) = update.as_ref().filter(|_| !replaying) {
+                    livesplit.process(config.as_ref(), up).await;
+                }
+
+                // 5. Process Update
                 if let Some(up) = update {
                     let hack_id_opt = *active_hack.lock().await;
+                    emit_update_events(&events, hack_id_opt, &up);
                     
-                    if let Some(hack_id) = hack_id_opt {
+                    if let Some(hack_id) = hack_id_opt.filter(|_| !replaying) {
                          // Session Logic
                          let mut sess_guard = session_id.lock().await;
+                         let mut attempt_guard = open_attempt.lock().await;
                          
-                         if up.is_active {
+                         // Paused, menus, cutscenes, game over screens and idle stretches don't count as play time
+                         if up.is_active && !idle {
                              if sess_guard.is_none() {
                                  let conn = db_pool.get();
                                  if let Ok(conn) = conn {
                                      let now = Utc::now();
                                      let res = conn.execute(
-                                         "INSERT INTO play_sessions (hack_id, start_time) VALUES (?1, ?2)",
-                                         (hack_id, now.to_rfc3339())
+                                         "INSERT INTO play_sessions (hack_id, start_time, save_slot) VALUES (?1, ?2, ?3)",
+                                         (hack_id, now.to_rfc3339(), up.save_slot)
                                      );
                                      if let Ok(count) = res {
                                          if count > 0 {
-                                            *sess_guard = Some(conn.last_insert_rowid());
+                                            let sid = conn.last_insert_rowid();
+                                            *sess_guard = Some(sid);
+                                            events.emit(TrackingEvent::SessionStarted { session_id: sid, hack_id });
                                          }
                                      }
                                  }
@@ -269,8 +422,12 @@ impl TrackingService {
                                      if let Ok(conn) = conn {
                                          let now = Utc::now();
                                          let _ = conn.execute(
-                                             "UPDATE play_sessions SET end_time = ?1, duration_seconds = duration_seconds + 1 WHERE id = ?2",
-                                             (now.to_rfc3339(), sid)
+                                             "UPDATE play_sessions SET end_time = ?1,
+                                              duration_ms = duration_ms + ?2,
+                                              duration_seconds = (duration_ms + ?2) / 1000,
+                                              save_slot = ?4
+                                              WHERE id = ?3",
+                                             (now.to_rfc3339(), elapsed_ms as i64, sid, up.save_slot)
                                          );
                                      }
                                  }
@@ -281,24 +438,222 @@ impl TrackingService {
                                  let conn = db_pool.get();
                                  if let Ok(conn) = conn {
                                      let _ = conn.execute(
-                                         "INSERT INTO level_timings (hack_id, level_id, duration_seconds, visit_count)
-                                          VALUES (?1, ?2, 1, 1)
+                                         "INSERT INTO level_timings (hack_id, level_id, duration_ms, duration_seconds, visit_count)
+                                          VALUES (?1, ?2, ?3, ?3 / 1000, 0)
                                           ON CONFLICT(hack_id, level_id) DO UPDATE SET
-                                          duration_seconds = duration_seconds + 1", 
-                                          (hack_id, lvl)
+                                          duration_ms = duration_ms + excluded.duration_ms,
+                                          duration_seconds = (duration_ms + excluded.duration_ms) / 1000", 
+                                          (hack_id, lvl, elapsed_ms as i64)
                                      );
+                                     if let Some(attempt_id) = *attempt_guard {
+                                         let _ = conn.execute(
+                                             "UPDATE level_attempts SET duration_ms = duration_ms + ?1 WHERE id = ?2",
+                                             (elapsed_ms as i64, attempt_id)
+                                         );
+                                     }
                                  }
                              }
                              
+                             idle_detector.counted(elapsed_ms);
+                         }
+                         
+                         // The stretch before the idle timeout was counted; take it back
+                         let idle_counted_ms = idle_detector.take_idle_counted();
+                         if idle_counted_ms > 0 {
+                             if let Ok(conn) = db_pool.get() {
+                                 if let Err(e) = refund_idle_time(&conn, hack_id, *sess_guard, up.level_id, *attempt_guard, idle_counted_ms) {
+                                     eprintln!("Tracking: Failed to remove idle time: {}", e);
+                                 } else if debug_logging {
+                                     eprintln!("Tracking: Idle, removed {}ms of play time", idle_counted_ms);
+                                 }
+                             }
+                         }
+                         
+                         // Attempts & Deaths
+                         if !up.level_events.is_empty() {
+                             if let Ok(conn) = db_pool.get() {
+                                 if let Err(e) = record_level_events(&conn, hack_id, *sess_guard, &mut attempt_guard, &up.level_events) {
+                                     eprintln!("Tracking: Failed to record level events: {}", e);
+                                 } else if debug_logging {
+                                     eprintln!("Tracking: {:?}", up.level_events);
+                                 }
+                             }
+                         }
+                         
+                         // Exit Clears (events can fire during the overworld transition too)
+                         if !up.new_exits.is_empty() {
+                             if let Ok(conn) = db_pool.get() {
+                                 if let Err(e) = record_exit_clears(&conn, hack_id, *sess_guard, &up.new_exits) {
+                                     eprintln!("Tracking: Failed to record exit clears: {}", e);
+                                 } else if debug_logging {
+                                     eprintln!("Tracking: Recorded {} exit clear(s) for hack {}", up.new_exits.len(), hack_id);
+                                 }
+                             }
+                         }
+
+                         // Credits: queue a completion for the user to confirm
+                         if let Some(ending) = up.ending {
+                             if let Ok(conn) = db_pool.get() {
+                                 match record_pending_completion(&conn, hack_id, *sess_guard, ending.events_triggered) {
+                                     Ok((pending_id, route)) => {
+                                         events.emit(TrackingEvent::CompletionDetected { hack_id, pending_id, route });
+                                     }
+                                     Err(e) => eprintln!("Tracking: Failed to record completion: {}", e),
+                                 }
+                             }
                          }
                     }
                 } 
 
-                sleep(Duration::from_secs(1)).await;
+                sleep(poll_delay).await;
             }
         });
     }
 
+    /// Uploads a ROM to the connected cart (if needed) and boots it. Uses a dedicated
+    /// usb2snes connection when tracking isn't attached through usb2snes. Returns whether
+    /// the ROM was uploaded.
+    pub async fn boot_on_hardware(&self, data: &[u8], remote_path: &str, already_uploaded: bool) -> Result<bool, String> {
+        let config = self.load_config()?;
+        let mut cl_guard = self.client.lock().await;
+        if let Some(client) = cl_guard.as_mut().and_then(|c| c.as_usb2snes()) {
+            let result = hardware::upload_and_boot(client, data, remote_path, already_uploaded).await;
+            if result.is_err() {
+                // Let the tracking loop reconnect from a clean state
+                *cl_guard = None;
+            }
+            return result;
+        }
+        drop(cl_guard);
+
+        let mut client = connect_device(Some(&config)).await?;
+        hardware::upload_and_boot(&mut client, data, remote_path, already_uploaded).await
+    }
+
+    /// Lists the devices every configured usb2snes endpoint currently offers.
+    pub async fn list_devices(&self) -> Result<Vec<Usb2SnesDevice>, String> {
+        let config = self.load_config()?;
+        let mut devices = Vec::new();
+        for endpoint in parse_endpoints(config.usb2snes_endpoints.as_deref()) {
+            let Ok(mut client) = Usb2SnesClient::connect(&endpoint).await else {
+                continue;
+            };
+            let _ = client.register_app("ROM Hack Manager").await;
+            if let Ok(names) = client.list_devices().await {
+                devices.extend(names.into_iter().map(|name| Usb2SnesDevice {
+                    endpoint: endpoint.clone(),
+                    name,
+                }));
+            }
+        }
+        Ok(devices)
+    }
+
+    /// Drops the current connection so the next tick reconnects with the latest settings.
+    pub async fn reconnect(&self) {
+        *self.client.lock().await = None;
+    }
+
+    fn load_config(&self) -> Result<crate::config::Config, String> {
+        let conn = self.db_pool.get().map_err(|e| e.to_string())?;
+        crate::config::Config::load(&conn).map_err(|e| e.to_string())
+    }
+
+    /// Reloads the active hack's memory map, e.g. after a different one was assigned.
+    pub async fn reload_memory_map(&self) {
+        let hack_id = *self.active_hack_id.lock().await;
+        let map = memory_map_for_hack(&self.db_pool, hack_id);
+        self.analyzer.lock().await.set_memory_map(map);
+    }
+
+    /// Reads the save data of the running game, if it's the given hack.
+    pub async fn read_save_data(&self, hack_id: i64) -> Result<Option<Vec<u8>>, String> {
+        let mut cl_guard = self.client.lock().await;
+        let Some(client) = cl_guard.as_mut() else {
+            return Ok(None);
+        };
+        if *self.active_hack_id.lock().await != Some(hack_id) {
+            return Ok(None);
+        }
+        client.read_memory(sram::ADDR_SRAM, sram::SAVE_DATA_SIZE).await.map(Some)
+    }
+
+    /// Starts writing every poll to `path`, replacing a recording in progress.
+    pub async fn start_recording(&self, path: &std::path::Path) -> Result<(), String> {
+        let hack_id = *self.active_hack_id.lock().await;
+        let rom_name = match hack_id {
+            Some(hack_id) => {
+                let conn = self.db_pool.get().map_err(|e| e.to_string())?;
+                conn.query_row("SELECT file_path FROM hacks WHERE id = ?1", [hack_id], |row| row.get::<_, Option<String>>(0))
+                    .ok()
+                    .flatten()
+                    .as_deref()
+                    .and_then(rom_file_name)
+            }
+            None => None,
+        };
+        let header = RecordingHeader {
+            rom_name,
+            memory_map: self.analyzer.lock().await.memory_map().name.clone(),
+            recorded_at: Utc::now().to_rfc3339(),
+        };
+        *self.recorder.lock().await = Some(Recorder::create(path, &header)?);
+        Ok(())
+    }
+
+    /// Stops recording; returns whether a recording was in progress.
+    pub async fn stop_recording(&self) -> bool {
+        self.recorder.lock().await.take().is_some()
+    }
+
+    /// Replaces the current connection with a replay of a recording. The tracker then
+    /// runs as if the recorded game were being played, until the replay runs out and
+    /// it reconnects to the configured source. Replays only emit tracking events: no
+    /// sessions, attempts or clears are stored and LiveSplit isn't driven. The live
+    /// session stays open, and the live ROM and hack are restored afterwards.
+    pub async fn replay(&self, path: &std::path::Path, speed: Option<f64>) -> Result<(), String> {
+        let recording = Recording::load(path)?;
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("recording");
+        let source = ReplaySource::new(name, recording, speed)?;
+        *self.replay.lock().await = Some(Box::new(source));
+        *self.client.lock().await = None;
+        Ok(())
+    }
+
+    /// Applies a practice toolkit action through the connected device.
+    pub async fn apply_practice(&self, action: PracticeAction) -> Result<(), String> {
+        let mut cl_guard = self.client.lock().await;
+        let client = cl_guard.as_mut().ok_or("Not connected to a device")?;
+        if !client.can_write() {
+            return Err("The connected device doesn't support memory writes".to_string());
+        }
+
+        let map = self.analyzer.lock().await.memory_map().clone();
+        let game_mode = client.read_memory(map.game_mode.address, 1).await?
+            .first()
+            .copied()
+            .unwrap_or(0);
+        let phase = map.phase(game_mode);
+        match action.requirement() {
+            Requirement::InLevel if phase != GamePhase::Level => {
+                return Err("This action is only available inside a level".to_string());
+            }
+            Requirement::OnOverworld if phase != GamePhase::Overworld => {
+                return Err("This action is only available on the overworld".to_string());
+            }
+            _ => {}
+        }
+
+        for (address, data) in action.writes()? {
+            client.write_memory(address, &data).await?;
+        }
+
+        if let PracticeAction::SetInvincible { enabled } = action {
+            *self.practice_invincible.lock().await = enabled;
+        }
+        Ok(())
+    }
+
     pub async fn get_status(&self) -> (bool, bool, bool, u8) {
         let client_guard = self.client.lock().await;
         let analyzer = self.analyzer.lock().await;
@@ -314,3 +669,891 @@ impl TrackingService {
         )
     }
 }
+
+/// Values of `Config::memory_source`.
+pub const MEMORY_SOURCE_USB2SNES: &str = "usb2snes";
+pub const MEMORY_SOURCE_RETROARCH: &str = "retroarch";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Usb2SnesDevice {
+    pub endpoint: String,
+    pub name: String,
+}
+
+/// How long the device may stay unreachable before the open session is closed.
+const DISCONNECT_GRACE: Duration = Duration::from_secs(60);
+
+/// Why a play session was closed, stored in `play_sessions.end_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEnd {
+    EmulatorExited,
+    RomChanged,
+    Disconnected,
+    HackChanged,
+}
+
+impl SessionEnd {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionEnd::EmulatorExited => "emulator_exited",
+            SessionEnd::RomChanged => "rom_changed",
+            SessionEnd::Disconnected => "disconnected",
+            SessionEnd::HackChanged => "hack_changed",
+        }
+    }
+}
+
+/// Publishes the level and exit events of one poll.
+fn emit_update_events(events: &EventBus, hack_id: Option<i64>, update: &TrackingUpdate) {
+    for event in &update.level_events {
+        events.emit(match *event {
+            LevelEvent::Attempt { level_id, retry } => TrackingEvent::LevelEntered { hack_id, level_id, retry },
+            LevelEvent::Death { level_id } => TrackingEvent::Death { hack_id, level_id },
+            LevelEvent::Exit { level_id, outcome } => TrackingEvent::LevelExited { hack_id, level_id, outcome },
+        });
+    }
+    for exit in &update.new_exits {
+        events.emit(TrackingEvent::ExitCleared {
+            hack_id,
+            level_id: exit.level_id,
+            event_id: exit.event_id,
+            exit_type: exit.exit_type,
+        });
+    }
+}
+
+/// Stamps a session's end reason. `end_time` already holds the last tick of active play.
+pub fn close_session(conn: &rusqlite::Connection, session_id: i64, reason: SessionEnd) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE play_sessions SET end_time = COALESCE(end_time, ?1), end_reason = ?2 WHERE id = ?3",
+        rusqlite::params![Utc::now().to_rfc3339(), reason.as_str(), session_id],
+    )?;
+    Ok(())
+}
+
+/// Takes time counted while the player was in fact idle back off the session, the
+/// level and the attempt in progress.
+fn refund_idle_time(
+    conn: &rusqlite::Connection,
+    hack_id: i64,
+    session_id: Option<i64>,
+    level_id: Option<u8>,
+    attempt_id: Option<i64>,
+    ms: u64,
+) -> rusqlite::Result<()> {
+    let ms = ms as i64;
+    if let Some(sid) = session_id {
+        conn.execute(
+            "UPDATE play_sessions SET duration_ms = MAX(duration_ms - ?1, 0),
+             duration_seconds = MAX(duration_ms - ?1, 0) / 1000
+             WHERE id = ?2",
+            (ms, sid),
+        )?;
+    }
+    if let Some(level_id) = level_id {
+        conn.execute(
+            "UPDATE level_timings SET duration_ms = MAX(duration_ms - ?1, 0),
+             duration_seconds = MAX(duration_ms - ?1, 0) / 1000
+             WHERE hack_id = ?2 AND level_id = ?3",
+            (ms, hack_id, level_id),
+        )?;
+    }
+    if let Some(attempt_id) = attempt_id {
+        conn.execute(
+            "UPDATE level_attempts SET duration_ms = MAX(duration_ms - ?1, 0) WHERE id = ?2",
+            (ms, attempt_id),
+        )?;
+    }
+    Ok(())
+}
+
+/// Puts back the live ROM and hack set aside when a replay started.
+async fn end_replay(
+    live_state: &mut Option<(Option<LoadedRom>, Option<i64>)>,
+    loaded_rom: &mut Option<LoadedRom>,
+    active_hack: &Mutex<Option<i64>>,
+) {
+    if let Some((rom, hack_id)) = live_state.take() {
+        *loaded_rom = rom;
+        *active_hack.lock().await = hack_id;
+    }
+}
+
+/// Ends the attempt in progress as an exit, since no level event will end it anymore.
+fn close_attempt(conn: &rusqlite::Connection, attempt: &mut Option<i64>) {
+    let Some(attempt_id) = attempt.take() else {
+        return;
+    };
+    if let Err(e) = conn.execute(
+        "UPDATE level_attempts SET end_time = ?1, outcome = ?2 WHERE id = ?3 AND outcome IS NULL",
+        rusqlite::params![Utc::now().to_rfc3339(), AttemptOutcome::Exit.as_str(), attempt_id],
+    ) {
+        eprintln!("Tracking: Failed to close attempt {}: {}", attempt_id, e);
+    }
+}
+
+/// Closes and forgets the session in `session` and the attempt in `attempt`, if open,
+/// then backs up the session's save into `backup_root`.
+fn finish_session(
+    db_pool: &Pool<SqliteConnectionManager>,
+    events: &EventBus,
+    backup_root: Option<&PathBuf>,
+    session: &mut Option<i64>,
+    attempt: &mut Option<i64>,
+    reason: SessionEnd,
+) {
+    if attempt.is_some() {
+        match db_pool.get() {
+            Ok(conn) => close_attempt(&conn, attempt),
+            Err(e) => eprintln!("Tracking: Failed to close attempt: {}", e),
+        }
+        *attempt = None;
+    }
+    let Some(sid) = session.take() else {
+        return;
+    };
+    events.emit(TrackingEvent::SessionEnded { session_id: sid, reason });
+    let conn = match db_pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Tracking: Failed to close session {}: {}", sid, e);
+            return;
+        }
+    };
+    if let Err(e) = close_session(&conn, sid, reason) {
+        eprintln!("Tracking: Failed to close session {}: {}", sid, e);
+    }
+    if let Some(root) = backup_root {
+        if let Err(e) = crate::commands::saves::backup_after_session(&conn, root, sid) {
+            eprintln!("Tracking: Failed to back up save after session {}: {}", sid, e);
+        }
+    }
+}
+
+/// What the device has loaded: the header checksum when the ROM can be read, else its file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LoadedRom {
+    Checksum(String),
+    FileName(String),
+}
+
+async fn identify_rom(client: &mut dyn MemorySource) -> Result<Option<LoadedRom>, String> {
+    if client.can_read_rom() {
+        // Read Title (FFC0, 21 bytes) + Checksum (FFDC, 4 bytes)
+        let data = client.read_memory(0x00FFC0, 32).await?;
+        if data.len() < 32 {
+            return Ok(None);
+        }
+        // Checksum low byte at 30 (FFDE), high at 31
+        let checksum = ((data[31] as u16) << 8) | (data[30] as u16);
+        return Ok(Some(LoadedRom::Checksum(format!("{:04X}", checksum))));
+    }
+
+    // Fallback: ROM name, cheap/text-based on both usb2snes and RetroArch
+    Ok(client.rom_name().await?.as_deref().and_then(rom_file_name).map(LoadedRom::FileName))
+}
+
+/// The file name part of a reported ROM path, skipping placeholders and the FXPak menu.
+fn rom_file_name(rom_name: &str) -> Option<String> {
+    let name = std::path::Path::new(rom_name).file_name()?.to_str()?;
+    if name.is_empty() || name == "No Info" || name.contains("menu.bin") {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+fn find_hack_for_rom(conn: &rusqlite::Connection, rom: &LoadedRom) -> Option<i64> {
+    match rom {
+        LoadedRom::Checksum(checksum) => conn.query_row(
+            "SELECT id FROM hacks WHERE rom_checksum = ?1 LIMIT 1",
+            [checksum],
+            |row| row.get(0),
+        ).ok(),
+        // Try fuzzy match on file_path OR name
+        LoadedRom::FileName(name) => conn.query_row(
+            "SELECT id FROM hacks WHERE file_path LIKE '%' || ?1 || '%' OR name LIKE '%' || ?1 || '%' LIMIT 1",
+            [name],
+            |row| row.get(0),
+        ).ok(),
+    }
+}
+
+/// Connects to the memory source selected in the config.
+async fn connect_source(config: Option<&crate::config::Config>) -> Result<Box<dyn MemorySource>, String> {
+    match config.and_then(|c| c.memory_source.as_deref()) {
+        Some(MEMORY_SOURCE_RETROARCH) => {
+            let address = config
+                .and_then(|c| c.retroarch_address.clone())
+                .unwrap_or_else(|| DEFAULT_RETROARCH_ADDRESS.to_string());
+            Ok(Box::new(RetroArchClient::connect(&address).await?))
+        }
+        _ => Ok(Box::new(connect_device(config).await?)),
+    }
+}
+
+/// Tries each configured endpoint in order and attaches to the preferred device.
+async fn connect_device(config: Option<&crate::config::Config>) -> Result<Usb2SnesClient, String> {
+    let endpoints = parse_endpoints(config.and_then(|c| c.usb2snes_endpoints.as_deref()));
+    let pattern = config.and_then(|c| c.usb2snes_device.as_deref());
+
+    let mut last_error = "No usb2snes endpoints configured".to_string();
+    for endpoint in endpoints {
+        let mut client = match Usb2SnesClient::connect(&endpoint).await {
+            Ok(client) => client,
+            Err(e) => {
+                last_error = format!("{}: {}", endpoint, e);
+                continue;
+            }
+        };
+        let _ = client.register_app("ROM Hack Manager").await;
+        let devices = match client.list_devices().await {
+            Ok(devices) => devices,
+            Err(e) => {
+                last_error = format!("{}: {}", endpoint, e);
+                continue;
+            }
+        };
+        let Some(device) = select_device(&devices, pattern) else {
+            last_error = format!("{}: no matching device in {:?}", endpoint, devices);
+            continue;
+        };
+        let device = device.clone();
+        client.attach(&device).await?;
+        // Get device info (flags); info[2] is usually the ROM name/path
+        client.info().await?;
+        return Ok(client);
+    }
+    Err(last_error)
+}
+
+/// Loads the memory map of the given hack, falling back to vanilla SMW when it can't be used.
+fn memory_map_for_hack(db_pool: &Pool<SqliteConnectionManager>, hack_id: Option<i64>) -> MemoryMap {
+    let Some(hack_id) = hack_id else {
+        return MemoryMap::vanilla();
+    };
+    let map = db_pool
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| memory_map::load_for_hack(&conn, hack_id));
+    match map {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Tracking: Using the vanilla memory map for hack {}: {}", hack_id, e);
+            MemoryMap::vanilla()
+        }
+    }
+}
+
+fn record_level_events(
+    conn: &rusqlite::Connection,
+    hack_id: i64,
+    session_id: Option<i64>,
+    open_attempt: &mut Option<i64>,
+    events: &[LevelEvent],
+) -> rusqlite::Result<()> {
+    for event in events {
+        let (level_id, attempts, deaths, retries, clears) = match *event {
+            LevelEvent::Attempt { level_id, retry } => {
+                conn.execute(
+                    "INSERT INTO level_attempts (hack_id, session_id, level_id, start_time) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![hack_id, session_id, level_id, Utc::now().to_rfc3339()],
+                )?;
+                *open_attempt = Some(conn.last_insert_rowid());
+                (level_id, 1, 0, retry as i64, 0)
+            }
+            LevelEvent::Death { level_id } => (level_id, 0, 1, 0, 0),
+            LevelEvent::Exit { level_id, outcome } => {
+                if let Some(attempt_id) = open_attempt.take() {
+                    conn.execute(
+                        "UPDATE level_attempts SET end_time = ?1, outcome = ?2 WHERE id = ?3",
+                        rusqlite::params![Utc::now().to_rfc3339(), outcome.as_str(), attempt_id],
+                    )?;
+                }
+                // Beating an exit found before sets no event flag, so clears are counted here
+                if outcome != AttemptOutcome::Clear {
+                    continue;
+                }
+                (level_id, 0, 0, 0, 1)
+            }
+        };
+        conn.execute(
+            "INSERT INTO level_timings (hack_id, level_id, duration_seconds, visit_count, death_count, retry_count, clear_count)
+             VALUES (?1, ?2, 0, ?3, ?4, ?5, ?6)
+             ON CONFLICT(hack_id, level_id) DO UPDATE SET
+             visit_count = visit_count + excluded.visit_count,
+             death_count = death_count + excluded.death_count,
+             retry_count = retry_count + excluded.retry_count,
+             clear_count = clear_count + excluded.clear_count",
+            (hack_id, level_id, attempts, deaths, retries, clears),
+        )?;
+        if let Some(sid) = session_id {
+            conn.execute(
+                "INSERT INTO session_level_stats (session_id, hack_id, level_id, attempts, deaths, retries, clears)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(session_id, level_id) DO UPDATE SET
+                 attempts = attempts + excluded.attempts,
+                 deaths = deaths + excluded.deaths,
+                 retries = retries + excluded.retries,
+                 clears = clears + excluded.clears",
+                (sid, hack_id, level_id, attempts, deaths, retries, clears),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Records newly found exits. Clear counts come from the attempt outcome in
+/// `record_level_events`, which also covers exits beaten before.
+fn record_exit_clears(
+    conn: &rusqlite::Connection,
+    hack_id: i64,
+    session_id: Option<i64>,
+    clears: &[ExitClear],
+) -> rusqlite::Result<()> {
+    let now = Utc::now().to_rfc3339();
+    for clear in clears {
+        conn.execute(
+            "INSERT INTO level_clears (hack_id, session_id, level_id, event_id, exit_type, cleared_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (hack_id, session_id, clear.level_id, clear.event_id, clear.exit_type.as_str(), &now),
+        )?;
+    }
+
+    if let Some(sid) = session_id {
+        conn.execute(
+            "UPDATE play_sessions SET exit_count = exit_count + ?1 WHERE id = ?2",
+            (clears.len() as i64, sid),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Names the route for a run that reached the credits with `exits_found` exits:
+/// "96 exit" style once every exit of the hack is found, "any%" otherwise.
+pub fn infer_route(exits_found: u32, total_exits: Option<i64>) -> String {
+    match total_exits {
+        Some(total) if total > 0 && i64::from(exits_found) >= total => format!("{} exit", total),
+        _ => "any%".to_string(),
+    }
+}
+
+/// Queues a completion at the credits. Play time covers the run that reached them: sessions
+/// started since the hack's last confirmed completion, on the same save file when it's known.
+/// Reaching the credits again on the same route refreshes the pending entry.
+/// Returns the pending completion's id and route.
+fn record_pending_completion(
+    conn: &rusqlite::Connection,
+    hack_id: i64,
+    session_id: Option<i64>,
+    exits_found: u32,
+) -> rusqlite::Result<(i64, String)> {
+    let total_exits: Option<i64> = conn.query_row(
+        "SELECT total_exits FROM hacks WHERE id = ?1",
+        [hack_id],
+        |row| row.get(0),
+    )?;
+    let play_time_ms: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(duration_ms), 0) FROM play_sessions
+         WHERE hack_id = ?1
+         AND CAST(strftime('%s', start_time) AS INTEGER) >= COALESCE(
+             (SELECT MAX(COALESCE(completed_at, created_at)) FROM hack_completions WHERE hack_id = ?1), 0)
+         AND (save_slot IS NULL OR save_slot = COALESCE(
+             (SELECT save_slot FROM play_sessions WHERE id = ?2), save_slot))",
+        (hack_id, session_id),
+        |row| row.get(0),
+    )?;
+    let route = infer_route(exits_found, total_exits);
+
+    let id = conn.query_row(
+        "INSERT INTO pending_completions (hack_id, session_id, route, completed_at, play_time_seconds, exits_found)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(hack_id, route) DO UPDATE SET
+         session_id = excluded.session_id,
+         completed_at = excluded.completed_at,
+         play_time_seconds = excluded.play_time_seconds,
+         exits_found = excluded.exits_found
+         RETURNING id",
+        (hack_id, session_id, &route, Utc::now().timestamp(), play_time_ms / 1000, exits_found),
+        |row| row.get(0),
+    )?;
+    Ok((id, route))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::tracking::mock_usb2snes::{MockUsb2Snes, Step};
+    use crate::tracking::smw::ExitType;
+    use crate::tracking::timing::frames_to_ms;
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+
+    /// A fast polling service tracking through `mock`. The database is a file so every
+    /// pooled connection sees the same data.
+    fn mock_service(mock: &MockUsb2Snes, dir: &TempDir, device: Option<&str>) -> TrackingService {
+        let pool = Pool::new(crate::db::connection_manager(dir.path().join("test.db"))).unwrap();
+        let conn = pool.get().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO config (key, value) VALUES ('enable_auto_tracking', 'true'), ('usb2snes_endpoints', ?1)",
+            [mock.url()],
+        ).unwrap();
+        if let Some(device) = device {
+            conn.execute("INSERT INTO config (key, value) VALUES ('usb2snes_device', ?1)", [device]).unwrap();
+        }
+        TrackingService::new(pool).with_poll_interval(Duration::from_millis(10))
+    }
+
+    /// One poll's worth of game state.
+    fn frame(game_mode: u8, level_id: u8, frame_counter: u8) -> Step {
+        Step::Ram(vec![
+            (SmwAnalyzer::ADDR_GAME_MODE, vec![game_mode]),
+            (SmwAnalyzer::ADDR_LEVEL_ID, vec![level_id]),
+            (SmwAnalyzer::ADDR_FRAME_COUNTER, vec![frame_counter]),
+        ])
+    }
+
+    async fn wait_for(events: &mut broadcast::Receiver<TrackingEvent>, expected: impl Fn(&TrackingEvent) -> bool) -> TrackingEvent {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let event = events.recv().await.unwrap();
+                if expected(&event) {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for a tracking event")
+    }
+
+    async fn wait_for_polls(mock: &MockUsb2Snes, polls: usize) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while mock.polls() < polls {
+                sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for polls");
+    }
+
+    #[test]
+    fn test_record_exit_clears_updates_session_count() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-01T00:00:00Z')", []).unwrap();
+
+        let clears = vec![
+            ExitClear { level_id: 0x05, event_id: 3, exit_type: ExitType::Normal },
+            ExitClear { level_id: 0x05, event_id: 4, exit_type: ExitType::Secret },
+        ];
+        record_exit_clears(&conn, 1, Some(1), &clears).unwrap();
+
+        let exit_count: i64 = conn.query_row("SELECT exit_count FROM play_sessions WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(exit_count, 2);
+
+        let secret: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM level_clears WHERE hack_id = 1 AND exit_type = 'secret'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(secret, 1);
+    }
+
+    #[test]
+    fn test_close_session_keeps_last_active_time() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute(
+            "INSERT INTO play_sessions (hack_id, start_time, end_time) VALUES (1, '2026-01-01T00:00:00Z', '2026-01-01T00:30:00Z')",
+            [],
+        ).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-02T00:00:00Z')", []).unwrap();
+
+        close_session(&conn, 1, SessionEnd::EmulatorExited).unwrap();
+        close_session(&conn, 2, SessionEnd::RomChanged).unwrap();
+
+        let (end_time, reason): (String, String) = conn.query_row(
+            "SELECT end_time, end_reason FROM play_sessions WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(end_time, "2026-01-01T00:30:00Z");
+        assert_eq!(reason, "emulator_exited");
+
+        // A session that never got an active tick is closed at the current time
+        let end_time: Option<String> = conn.query_row("SELECT end_time FROM play_sessions WHERE id = 2", [], |row| row.get(0)).unwrap();
+        assert!(end_time.is_some());
+    }
+
+    #[test]
+    fn test_rom_file_name_skips_menu_and_placeholders() {
+        assert_eq!(rom_file_name("/romhacks/Kaizo Mario.sfc").as_deref(), Some("Kaizo Mario.sfc"));
+        assert_eq!(rom_file_name("No Info"), None);
+        assert_eq!(rom_file_name("/sd2snes/m3nu.bin"), Some("m3nu.bin".to_string()));
+        assert_eq!(rom_file_name("/sd2snes/menu.bin"), None);
+    }
+
+    #[test]
+    fn test_record_level_events_counts_per_level_and_session() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-01T00:00:00Z')", []).unwrap();
+
+        let events = vec![
+            LevelEvent::Attempt { level_id: 0x05, retry: false },
+            LevelEvent::Death { level_id: 0x05 },
+            LevelEvent::Exit { level_id: 0x05, outcome: AttemptOutcome::Death },
+            LevelEvent::Attempt { level_id: 0x05, retry: true },
+        ];
+        let mut open_attempt = None;
+        record_level_events(&conn, 1, Some(1), &mut open_attempt, &events).unwrap();
+        assert_eq!(open_attempt, Some(2));
+        // A new exit is found and the attempt ends in a clear; it's counted once
+        record_exit_clears(&conn, 1, Some(1), &[ExitClear { level_id: 0x05, event_id: 3, exit_type: ExitType::Normal }]).unwrap();
+        record_level_events(&conn, 1, Some(1), &mut open_attempt, &[LevelEvent::Exit { level_id: 0x05, outcome: AttemptOutcome::Clear }]).unwrap();
+
+        let totals: (i64, i64, i64, i64) = conn.query_row(
+            "SELECT visit_count, death_count, retry_count, clear_count FROM level_timings WHERE hack_id = 1 AND level_id = 5",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).unwrap();
+        assert_eq!(totals, (2, 1, 1, 1));
+
+        let session: (i64, i64, i64) = conn.query_row(
+            "SELECT attempts, deaths, clears FROM session_level_stats WHERE session_id = 1 AND level_id = 5",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!(session, (2, 1, 1));
+
+        let outcomes: Vec<Option<String>> = conn
+            .prepare("SELECT outcome FROM level_attempts WHERE hack_id = 1 ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(outcomes, vec![Some("death".to_string()), Some("clear".to_string())]);
+    }
+
+    #[test]
+    fn test_beating_a_level_again_counts_as_a_clear() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-01T00:00:00Z')", []).unwrap();
+
+        // The second clear finds no new exit, so only the attempt outcome reports it
+        let clear = [
+            LevelEvent::Attempt { level_id: 0x05, retry: false },
+            LevelEvent::Exit { level_id: 0x05, outcome: AttemptOutcome::Clear },
+        ];
+        let mut open_attempt = None;
+        record_level_events(&conn, 1, Some(1), &mut open_attempt, &clear).unwrap();
+        record_exit_clears(&conn, 1, Some(1), &[ExitClear { level_id: 0x05, event_id: 3, exit_type: ExitType::Normal }]).unwrap();
+        record_level_events(&conn, 1, Some(1), &mut open_attempt, &clear).unwrap();
+
+        let clear_count: i64 = conn.query_row(
+            "SELECT clear_count FROM level_timings WHERE hack_id = 1 AND level_id = 5",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(clear_count, 2);
+        let (session_clears, exit_count): (i64, i64) = conn.query_row(
+            "SELECT s.clears, p.exit_count FROM session_level_stats s JOIN play_sessions p ON p.id = s.session_id WHERE s.level_id = 5",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((session_clears, exit_count), (2, 1));
+    }
+
+    #[test]
+    fn test_refund_idle_time_never_goes_negative() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time, duration_ms) VALUES (1, '2026-01-01T00:00:00Z', 400000)", []).unwrap();
+        conn.execute("INSERT INTO level_timings (hack_id, level_id, duration_ms, duration_seconds) VALUES (1, 5, 100000, 100)", []).unwrap();
+        conn.execute("INSERT INTO level_attempts (hack_id, session_id, level_id, start_time, duration_ms) VALUES (1, 1, 5, '2026-01-01T00:00:00Z', 250000)", []).unwrap();
+
+        refund_idle_time(&conn, 1, Some(1), Some(5), Some(1), 300000).unwrap();
+
+        let session: (i64, i64) = conn.query_row("SELECT duration_ms, duration_seconds FROM play_sessions WHERE id = 1", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!(session, (100000, 100));
+        let level: (i64, i64) = conn.query_row("SELECT duration_ms, duration_seconds FROM level_timings WHERE level_id = 5", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!(level, (0, 0));
+        let attempt: i64 = conn.query_row("SELECT duration_ms FROM level_attempts WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(attempt, 0);
+    }
+
+    #[test]
+    fn test_finishing_session_closes_open_attempt() {
+        let dir = TempDir::new().unwrap();
+        let pool = Pool::new(crate::db::connection_manager(dir.path().join("test.db"))).unwrap();
+        let conn = pool.get().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name) VALUES ('Test Hack')", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-01T00:00:00Z')", []).unwrap();
+        let mut open_attempt = None;
+        record_level_events(&conn, 1, Some(1), &mut open_attempt, &[LevelEvent::Attempt { level_id: 0x05, retry: false }]).unwrap();
+
+        let mut session = Some(1);
+        finish_session(&pool, &EventBus::new(), None, &mut session, &mut open_attempt, SessionEnd::HackChanged);
+        assert_eq!((session, open_attempt), (None, None));
+        let (outcome, end_time): (Option<String>, Option<String>) = conn
+            .query_row("SELECT outcome, end_time FROM level_attempts WHERE id = 1", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(outcome.as_deref(), Some("exit"));
+        assert!(end_time.is_some());
+
+        // Without a session the attempt is still closed
+        record_level_events(&conn, 1, None, &mut open_attempt, &[LevelEvent::Attempt { level_id: 0x06, retry: false }]).unwrap();
+        finish_session(&pool, &EventBus::new(), None, &mut None, &mut open_attempt, SessionEnd::Disconnected);
+        assert_eq!(open_attempt, None);
+        let open: i64 = conn.query_row("SELECT COUNT(*) FROM level_attempts WHERE outcome IS NULL", [], |row| row.get(0)).unwrap();
+        assert_eq!(open, 0);
+    }
+
+    #[test]
+    fn test_finishing_session_backs_up_the_save() {
+        let dir = TempDir::new().unwrap();
+        let pool = Pool::new(crate::db::connection_manager(dir.path().join("test.db"))).unwrap();
+        let conn = pool.get().unwrap();
+        init_db(&conn).unwrap();
+        let rom = dir.path().join("Hack.sfc");
+        std::fs::write(sram::srm_path(&rom), b"save").unwrap();
+        conn.execute("INSERT INTO hacks (name, file_path) VALUES ('Test Hack', ?1)", [rom.to_string_lossy()]).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time) VALUES (1, '2026-01-01T00:00:00Z')", []).unwrap();
+
+        let backups = dir.path().join("backups");
+        finish_session(&pool, &EventBus::new(), Some(&backups), &mut Some(1), &mut None, SessionEnd::EmulatorExited);
+        let reason: String = conn.query_row("SELECT reason FROM save_backups WHERE hack_id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(reason, crate::commands::saves::REASON_SESSION_END);
+    }
+
+    #[test]
+    fn test_pending_completion_infers_route_and_play_time() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute("INSERT INTO hacks (name, total_exits) VALUES ('Test Hack', 12)", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time, duration_ms) VALUES (1, '2026-01-01T00:00:00Z', 3600000)", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time, duration_ms) VALUES (1, '2026-01-02T00:00:00Z', 1800500)", []).unwrap();
+
+        let (first, route) = record_pending_completion(&conn, 1, Some(2), 7).unwrap();
+        assert_eq!(route, "any%");
+        let (full, route) = record_pending_completion(&conn, 1, Some(2), 12).unwrap();
+        assert_eq!(route, "12 exit");
+        assert_ne!(first, full);
+
+        // Watching the credits again on the same route updates the pending entry
+        let (again, _) = record_pending_completion(&conn, 1, None, 8).unwrap();
+        assert_eq!(again, first);
+
+        let (play_time, exits): (i64, i64) = conn.query_row(
+            "SELECT play_time_seconds, exits_found FROM pending_completions WHERE id = ?1",
+            [first],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((play_time, exits), (5400, 8));
+
+        // After a confirmed completion, only the new run on the same save file counts
+        conn.execute(
+            "INSERT INTO hack_completions (hack_id, route, completed_at, created_at, updated_at)
+             VALUES (1, 'any%', 1767315600, 1767315600, 1767315600)",
+            [],
+        ).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time, duration_ms, save_slot) VALUES (1, '2026-01-03T00:00:00Z', 600000, 1)", []).unwrap();
+        conn.execute("INSERT INTO play_sessions (hack_id, start_time, duration_ms, save_slot) VALUES (1, '2026-01-03T01:00:00Z', 900000, 0)", []).unwrap();
+        let (rerun, _) = record_pending_completion(&conn, 1, Some(3), 7).unwrap();
+        assert_eq!(rerun, first);
+        let play_time: i64 = conn.query_row(
+            "SELECT play_time_seconds FROM pending_completions WHERE id = ?1",
+            [first],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(play_time, 600);
+
+        assert_eq!(infer_route(96, None), "any%");
+    }
+
+    #[tokio::test]
+    async fn test_tracks_level_through_usb2snes() {
+        let dir = TempDir::new().unwrap();
+        let mock = MockUsb2Snes::start().await;
+        // Header checksum $ABCD identifies the hack
+        mock.write(0x00FFDE, &[0xCD, 0xAB]);
+        mock.script([
+            frame(0x0E, 0x00, 0),
+            frame(0x14, 0x05, 60),
+            frame(0x14, 0x05, 120),
+            frame(0x14, 0x05, 180),
+            frame(0x0E, 0x05, 240),
+        ]);
+
+        let service = mock_service(&mock, &dir, None);
+        service.db_pool.get().unwrap()
+            .execute("INSERT INTO hacks (name, rom_checksum) VALUES ('Test Hack', 'ABCD')", [])
+            .unwrap();
+        let mut events = service.subscribe();
+        service.start_background_task().await;
+
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { source } if source == "usb2snes: SD2SNES COM3")).await;
+        wait_for(&mut events, |e| *e == TrackingEvent::HackDetected { hack_id: 1 }).await;
+        let started = wait_for(&mut events, |e| matches!(e, TrackingEvent::SessionStarted { .. })).await;
+        assert_eq!(started, TrackingEvent::SessionStarted { session_id: 1, hack_id: 1 });
+        wait_for(&mut events, |e| *e == TrackingEvent::LevelEntered { hack_id: Some(1), level_id: 5, retry: false }).await;
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::LevelExited { level_id: 5, outcome: AttemptOutcome::Exit, .. })).await;
+        // The frame counter stops once the script runs out, so no more time is counted
+        wait_for_polls(&mock, 8).await;
+
+        let conn = service.db_pool.get().unwrap();
+        let level_ms: i64 = conn.query_row(
+            "SELECT duration_ms FROM level_timings WHERE hack_id = 1 AND level_id = 5",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(level_ms as u64, 3 * frames_to_ms(60));
+
+        let (session_ms, save_slot): (i64, Option<i64>) = conn.query_row(
+            "SELECT duration_ms, save_slot FROM play_sessions WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(session_ms as u64, 4 * frames_to_ms(60));
+        assert_eq!(save_slot, Some(0));
+
+        let attempts: i64 = conn.query_row("SELECT COUNT(*) FROM level_attempts WHERE outcome = 'exit'", [], |row| row.get(0)).unwrap();
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_server_drops_connection() {
+        let dir = TempDir::new().unwrap();
+        let mock = MockUsb2Snes::start().await;
+        mock.script([frame(0x0E, 0x00, 0), Step::Disconnect]);
+
+        let service = mock_service(&mock, &dir, None);
+        let mut events = service.subscribe();
+        service.start_background_task().await;
+
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { .. })).await;
+        wait_for(&mut events, |e| *e == TrackingEvent::Disconnected).await;
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { .. })).await;
+
+        mock.disconnect_all();
+        wait_for(&mut events, |e| *e == TrackingEvent::Disconnected).await;
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { .. })).await;
+        assert_eq!(mock.attached().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_attaches_preferred_device_and_detects_by_file_name() {
+        let dir = TempDir::new().unwrap();
+        let mock = MockUsb2Snes::start().await;
+        mock.set_devices(&["EMU SNES9X", "SD2SNES COM4"]);
+        mock.set_info("/romhacks/Kaizo Mario.sfc", &["NO_ROM_READ"]);
+
+        let service = mock_service(&mock, &dir, Some("com4"));
+        service.db_pool.get().unwrap()
+            .execute("INSERT INTO hacks (name, file_path) VALUES ('Kaizo Mario World', '/roms/Kaizo Mario.sfc')", [])
+            .unwrap();
+        let mut events = service.subscribe();
+        service.start_background_task().await;
+
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { source } if source == "usb2snes: SD2SNES COM4")).await;
+        wait_for(&mut events, |e| *e == TrackingEvent::HackDetected { hack_id: 1 }).await;
+        assert_eq!(mock.attached(), vec!["SD2SNES COM4".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_records_and_replays_a_session() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.rhrec");
+        let mock = MockUsb2Snes::start().await;
+        mock.script([
+            frame(0x0E, 0x00, 0),
+            frame(0x14, 0x05, 60),
+            Step::Ram(vec![(SmwAnalyzer::ADDR_PLAYER_ANIMATION, vec![0x09]), (SmwAnalyzer::ADDR_FRAME_COUNTER, vec![120])]),
+            Step::Ram(vec![(SmwAnalyzer::ADDR_PLAYER_ANIMATION, vec![0x00]), (SmwAnalyzer::ADDR_FRAME_COUNTER, vec![180])]),
+            frame(0x0E, 0x05, 240),
+        ]);
+
+        let service = mock_service(&mock, &dir, None);
+        service.db_pool.get().unwrap()
+            .execute("INSERT INTO hacks (name, file_path) VALUES ('Test Hack', '/roms/Test Hack.sfc')", [])
+            .unwrap();
+        service.set_active_hack(1).await;
+        service.start_recording(&path).await.unwrap();
+        let mut events = service.subscribe();
+        service.start_background_task().await;
+
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::LevelExited { level_id: 5, .. })).await;
+        assert!(service.stop_recording().await);
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.header.rom_name.as_deref(), Some("Test Hack.sfc"));
+        assert!(recording.polls.len() >= 4);
+
+        let stored_rows = || {
+            let conn = service.db_pool.get().unwrap();
+            ["play_sessions", "level_timings", "level_attempts", "level_clears", "pending_completions", "save_backups"]
+                .map(|table| conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0)).unwrap())
+        };
+
+        // The live session stays open through the replay
+        let session_open = || {
+            service.db_pool.get().unwrap()
+                .query_row("SELECT COUNT(*) FROM play_sessions WHERE end_reason IS NULL", [], |row| row.get::<_, i64>(0))
+                .unwrap()
+        };
+        assert_eq!(session_open(), 1);
+        let mut all_events = service.subscribe();
+
+        // The replay identifies the hack by the recorded ROM name and plays the level again
+        service.replay(&path, Some(4.0)).await.unwrap();
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { source } if source == "Replay: session.rhrec")).await;
+        let before = stored_rows();
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::LevelEntered { hack_id: Some(1), level_id: 5, .. })).await;
+        wait_for(&mut events, |e| *e == TrackingEvent::Death { hack_id: Some(1), level_id: 5 }).await;
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::LevelExited { level_id: 5, .. })).await;
+        // Running out of recording disconnects, and tracking goes back to the device
+        wait_for(&mut events, |e| *e == TrackingEvent::Disconnected).await;
+        wait_for(&mut events, |e| matches!(e, TrackingEvent::Connected { source } if source == "usb2snes: SD2SNES COM3")).await;
+        assert_eq!(stored_rows(), before, "replays don't touch the stats");
+        assert_eq!(session_open(), 1);
+        assert_eq!(*service.active_hack_id.lock().await, Some(1));
+        while let Ok(event) = all_events.try_recv() {
+            assert!(!matches!(event, TrackingEvent::SessionEnded { .. }), "replay ended the live session");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_practice_checks_game_mode_through_memory_map() {
+        let dir = TempDir::new().unwrap();
+        let mock = MockUsb2Snes::start().await;
+        let service = mock_service(&mock, &dir, None);
+        let client = connect_device(Some(&service.load_config().unwrap())).await.unwrap();
+        *service.client.lock().await = Some(Box::new(client));
+        service.analyzer.lock().await.set_memory_map(MemoryMap::from_json(
+            r#"{
+                "name": "Custom Engine",
+                "game_mode": { "address": "$F50200" },
+                "phases": [{ "phase": "level", "game_modes": ["0x20"] }],
+                "default_phase": "transition"
+            }"#,
+        ).unwrap());
+
+        // Vanilla's $0100 says overworld, the custom game mode says level
+        mock.write(SmwAnalyzer::ADDR_GAME_MODE, &[0x0E]);
+        mock.write(0xF50200, &[0x20]);
+        assert!(service.apply_practice(PracticeAction::ResetLevel).await.is_ok());
+        assert!(service.apply_practice(PracticeAction::WarpToLevel { level_id: 5 }).await.is_err());
+    }
+}
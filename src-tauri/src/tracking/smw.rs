@@ -1,55 +1,493 @@
+use serde::{Deserialize, Serialize};
+use crate::tracking::memory_map::MemoryMap;
+use crate::tracking::sram;
+
 pub struct SmwAnalyzer {
     pub in_level: bool,
     pub last_game_mode: u8,
     pub current_level_id: Option<u8>,
+    last_events: Option<Vec<u8>>,
+    attempt: Option<LevelAttempt>,
+    map: MemoryMap,
+}
+
+/// The level attempt in progress, kept until the next one starts so retries can be recognised.
+#[derive(Debug, Clone, Copy)]
+struct LevelAttempt {
+    level_id: u8,
+    died: bool,
+    finished: bool,
+}
+
+/// Raw RAM values read from the game on each poll.
+#[derive(Debug, Clone, Default)]
+pub struct SmwSnapshot {
+    pub game_mode: u8,
+    pub level_id: u8,
+    pub exit_type: u8,
+    pub player_animation: u8,
+    pub pause_flag: u8,
+    pub save_slot: u8,
+    /// `None` when the frame counter couldn't be read; timing then uses the wall clock.
+    pub frame_counter: Option<u8>,
+    /// Controller and player position bytes; unchanged values mean the player is idle.
+    pub activity: Vec<u8>,
+    pub events: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitType {
+    Normal,
+    Secret,
+}
+
+impl ExitType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitType::Normal => "normal",
+            ExitType::Secret => "secret",
+        }
+    }
+}
+
+/// How a level attempt ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttemptOutcome {
+    /// A goal was reached (normal or secret exit).
+    Clear,
+    Death,
+    /// Left without beating it, usually start+select.
+    Exit,
+}
+
+impl AttemptOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttemptOutcome::Clear => "clear",
+            AttemptOutcome::Death => "death",
+            AttemptOutcome::Exit => "exit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitClear {
+    pub level_id: u8,
+    pub event_id: u8,
+    pub exit_type: ExitType,
+}
+
+/// What the game is showing, derived from the game mode ($0100).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GamePhase {
+    /// Title screen, intro demo and file select.
+    Menu,
+    Overworld,
+    Level,
+    /// Fades and loading screens between the overworld and levels.
+    Transition,
+    /// Game over and time up screens.
+    #[serde(rename = "game_over")]
+    GameOver,
+    /// Castle destruction and other story scenes.
+    Cutscene,
+    /// Ending sequence and credits.
+    Credits,
+}
+
+impl GamePhase {
+    pub fn from_game_mode(game_mode: u8) -> Self {
+        match game_mode {
+            0x00..=0x0A => GamePhase::Menu,
+            0x0E => GamePhase::Overworld,
+            0x14 => GamePhase::Level,
+            0x15..=0x17 => GamePhase::GameOver,
+            0x0B..=0x13 => GamePhase::Transition,
+            0x18..=0x1B => GamePhase::Cutscene,
+            _ => GamePhase::Credits,
+        }
+    }
+
+    /// Whether time in this phase is actual gameplay.
+    pub fn is_gameplay(&self) -> bool {
+        matches!(self, GamePhase::Overworld | GamePhase::Level | GamePhase::Transition)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelEvent {
+    /// The player entered a level. `retry` is set when the previous attempt at the same level ended in a death.
+    Attempt { level_id: u8, retry: bool },
+    Death { level_id: u8 },
+    /// The attempt ended: back on the overworld or title screen, an exit was beaten, or a new attempt started.
+    Exit { level_id: u8, outcome: AttemptOutcome },
+}
+
+/// The ending was reached from gameplay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndingReached {
+    /// Overworld events triggered on the loaded save file, i.e. exits beaten.
+    pub events_triggered: u32,
 }
 
 #[derive(Debug, Clone)]
 pub struct TrackingUpdate {
     pub is_active: bool,
+    pub phase: GamePhase,
+    pub paused: bool,
     pub level_id: Option<u8>,
+    pub new_exits: Vec<ExitClear>,
+    pub level_events: Vec<LevelEvent>,
+    pub ending: Option<EndingReached>,
+    /// The save file being played, 0-2; `None` on the title screen and file select.
+    pub save_slot: Option<u8>,
 }
 
 impl SmwAnalyzer {
     pub const ADDR_GAME_MODE: u32 = 0xF50100;
     pub const ADDR_LEVEL_ID: u32 = 0xF513BF;
-    pub const ADDR_EVENT_FLAGS: u32 = 0xF51EA2;
+    /// How the last level was left: 1 = normal exit, 2-4 = secret exits, 0x80 = death or start+select.
+    pub const ADDR_EXIT_TYPE: u32 = 0xF50DD5;
+    /// Overworld event flags ($1F02), one bit per event. Beating an exit triggers its event.
+    pub const ADDR_EVENT_FLAGS: u32 = 0xF51F02;
     pub const EVENT_FLAGS_SIZE: u32 = 15;
-    
+    /// Player animation state ($71); 0x09 is the death animation.
+    pub const ADDR_PLAYER_ANIMATION: u32 = 0xF50071;
+    /// Frame counter ($13), incremented once per game frame and wrapping at 256.
+    pub const ADDR_FRAME_COUNTER: u32 = 0xF50013;
+    /// Non-zero while the game is paused with start ($13D4).
+    pub const ADDR_PAUSE_FLAG: u32 = 0xF513D4;
+    /// Controller state ($15-$18: held and newly pressed buttons), used for idle detection.
+    pub const ADDR_CONTROLLER: u32 = 0xF50015;
+    pub const CONTROLLER_SIZE: u32 = 4;
+    /// Player X/Y position ($94-$97), used for idle detection.
+    pub const ADDR_PLAYER_POSITION: u32 = 0xF50094;
+    pub const PLAYER_POSITION_SIZE: u32 = 4;
+    /// Save file loaded from the file select screen ($010A), 0-2.
+    pub const ADDR_SAVE_SLOT: u32 = 0xF5010A;
+
+    /// More new events than this in one poll means a save file was loaded, not an exit beaten.
+    const MAX_EVENTS_PER_POLL: usize = 2;
+
     pub fn new() -> Self {
+        Self::with_map(MemoryMap::vanilla())
+    }
+
+    /// An analyzer for a hack with a custom engine or relocated RAM.
+    pub fn with_map(map: MemoryMap) -> Self {
         Self {
             in_level: false,
             last_game_mode: 0,
             current_level_id: None,
+            last_events: None,
+            attempt: None,
+            map,
         }
     }
-    
-    pub fn interpret(&mut self, game_mode: u8, level_id: u8, _events: &[u8]) -> TrackingUpdate {
-        // Game Modes:
-        // 0x0E = Overworld
-        // 0x14 = Level
-        
-        // Excluded level IDs (non-gameplay):
-        const EXCLUDED_LEVELS: [u8; 3] = [0x00, 0xC5, 0xC7];
-        
-        let is_level = game_mode == 0x14;
-        let is_overworld = game_mode == 0x0E;
-        let is_active = is_level || is_overworld;
-        
-        let current_level = if is_level && !EXCLUDED_LEVELS.contains(&level_id) { 
-            Some(level_id) 
-        } else { 
-            None 
+
+    pub fn memory_map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Switches memory maps, forgetting everything tracked with the previous one.
+    pub fn set_memory_map(&mut self, map: MemoryMap) {
+        *self = Self::with_map(map);
+    }
+
+    pub fn interpret(&mut self, snapshot: &SmwSnapshot) -> TrackingUpdate {
+        let game_mode = snapshot.game_mode;
+        let level_id = snapshot.level_id;
+        let phase = self.map.phase(game_mode);
+        let is_level = phase == GamePhase::Level;
+        let paused = is_level && snapshot.pause_flag != 0;
+        let is_active = phase.is_gameplay() && !paused;
+
+        // Excluded level IDs are non-gameplay
+        let current_level = if is_level && !self.map.excluded_levels.contains(&level_id) {
+            Some(level_id)
+        } else {
+            None
         };
-        
+
+        let new_exits = self.diff_events(snapshot);
+        let level_events = self.track_attempts(snapshot, current_level, !new_exits.is_empty());
+
+        // Garbage RAM at boot can look like an ending game mode, so require coming from
+        // the game, with progress on the save file and the map's final events triggered
+        let previous_phase = self.map.phase(self.last_game_mode);
+        let events_triggered: u32 = snapshot.events.iter().map(|b| b.count_ones()).sum();
+        let ending = (phase == GamePhase::Credits
+            && !matches!(previous_phase, GamePhase::Credits | GamePhase::Menu)
+            && events_triggered > 0
+            && self.map.ending_events.iter().all(|&event| event_triggered(&snapshot.events, event)))
+            .then_some(EndingReached { events_triggered });
+
+        let save_slot = Some(snapshot.save_slot)
+            .filter(|&slot| phase != GamePhase::Menu && (slot as usize) < sram::SLOT_COUNT);
+
         self.in_level = is_level;
         self.last_game_mode = game_mode;
         self.current_level_id = current_level;
 
         TrackingUpdate {
             is_active,
+            phase,
+            paused,
             level_id: current_level,
+            new_exits,
+            level_events,
+            ending,
+            save_slot,
+        }
+    }
+
+    /// Follows level entries and deaths. Sublevel transitions (doors, pipes) also pass through
+    /// the loading game modes, so only an entry after the previous attempt ended counts.
+    fn track_attempts(&mut self, snapshot: &SmwSnapshot, current_level: Option<u8>, cleared: bool) -> Vec<LevelEvent> {
+        let mut events = Vec::new();
+
+        let phase = self.map.phase(snapshot.game_mode);
+        let on_overworld = phase == GamePhase::Overworld;
+        if cleared || on_overworld || phase == GamePhase::Menu {
+            if let Some(attempt) = self.attempt.as_mut() {
+                if !attempt.finished {
+                    attempt.finished = true;
+                    // Beating an exit that was already found sets no new event, but still sets the exit type
+                    let outcome = if attempt.died {
+                        AttemptOutcome::Death
+                    } else if cleared || (on_overworld && self.map.exit_type(snapshot.exit_type).is_some()) {
+                        AttemptOutcome::Clear
+                    } else {
+                        AttemptOutcome::Exit
+                    };
+                    events.push(LevelEvent::Exit { level_id: attempt.level_id, outcome });
+                }
+            }
+        }
+
+        let Some(level_id) = current_level else {
+            return events;
+        };
+
+        let entered = !self.in_level;
+        let starts_attempt = match self.attempt {
+            None => true,
+            Some(prev) => prev.level_id != level_id || (entered && (prev.died || prev.finished)),
+        };
+
+        if starts_attempt {
+            let retry = matches!(self.attempt, Some(prev) if prev.level_id == level_id && prev.died);
+            if let Some(prev) = self.attempt.filter(|prev| !prev.finished) {
+                let outcome = if prev.died { AttemptOutcome::Death } else { AttemptOutcome::Exit };
+                events.push(LevelEvent::Exit { level_id: prev.level_id, outcome });
+            }
+            events.push(LevelEvent::Attempt { level_id, retry });
+            self.attempt = Some(LevelAttempt { level_id, died: false, finished: false });
+        }
+
+        if snapshot.player_animation == self.map.death_animation {
+            if let Some(attempt) = self.attempt.as_mut() {
+                if !attempt.died {
+                    attempt.died = true;
+                    events.push(LevelEvent::Death { level_id });
+                }
+            }
         }
+
+        events
+    }
+
+    /// Compares event flags with the previous poll and reports newly set events as exit clears.
+    fn diff_events(&mut self, snapshot: &SmwSnapshot) -> Vec<ExitClear> {
+        let previous = self.last_events.replace(snapshot.events.clone());
+        // Past the title screen and file select
+        let was_in_game = self.map.phase(self.last_game_mode) != GamePhase::Menu;
+        let is_in_game = self.map.phase(snapshot.game_mode) != GamePhase::Menu;
+
+        let previous = match previous {
+            Some(prev) if prev.len() == snapshot.events.len() && was_in_game && is_in_game => prev,
+            // First poll, or the title screen / file select may swap in a different save
+            _ => return Vec::new(),
+        };
+
+        let mut new_events = Vec::new();
+        for (byte_index, (old, new)) in previous.iter().zip(&snapshot.events).enumerate() {
+            if old & !new != 0 {
+                // Flags were cleared: a different save file is loaded
+                return Vec::new();
+            }
+            let set = new & !old;
+            for bit in 0..8 {
+                // Events are numbered from the most significant bit of each byte
+                if set & (0x80 >> bit) != 0 {
+                    new_events.push((byte_index * 8 + bit) as u8);
+                }
+            }
+        }
+
+        if new_events.len() > Self::MAX_EVENTS_PER_POLL {
+            return Vec::new();
+        }
+
+        let exit_type = self.map.exit_type(snapshot.exit_type).unwrap_or(ExitType::Normal);
+
+        new_events
+            .into_iter()
+            .map(|event_id| ExitClear {
+                level_id: snapshot.level_id,
+                event_id,
+                exit_type,
+            })
+            .collect()
+    }
+}
+
+impl Default for SmwAnalyzer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// Whether an event flag is set, numbered the same way as in `diff_events`.
+fn event_triggered(events: &[u8], event: u8) -> bool {
+    events
+        .get(event as usize / 8)
+        .is_some_and(|byte| byte & (0x80 >> (event % 8)) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(game_mode: u8, level_id: u8, exit_type: u8, events: &[u8]) -> SmwSnapshot {
+        let mut padded = events.to_vec();
+        padded.resize(SmwAnalyzer::EVENT_FLAGS_SIZE as usize, 0);
+        SmwSnapshot { game_mode, level_id, exit_type, events: padded, ..Default::default() }
+    }
+
+    fn in_level(level_id: u8, player_animation: u8) -> SmwSnapshot {
+        SmwSnapshot { player_animation, ..snapshot(0x14, level_id, 0, &[]) }
+    }
+
+    #[test]
+    fn test_detects_new_exit_on_overworld() {
+        let mut analyzer = SmwAnalyzer::new();
+
+        let first = analyzer.interpret(&snapshot(0x14, 0x05, 0, &[0x80]));
+        assert!(first.new_exits.is_empty(), "first poll only sets the baseline");
+
+        let update = analyzer.interpret(&snapshot(0x0E, 0x05, 2, &[0xC0]));
+        assert_eq!(update.new_exits, vec![ExitClear { level_id: 0x05, event_id: 1, exit_type: ExitType::Secret }]);
+
+        let update = analyzer.interpret(&snapshot(0x0E, 0x05, 2, &[0xC0]));
+        assert!(update.new_exits.is_empty(), "an exit is only reported once");
+    }
+
+    #[test]
+    fn test_ignores_save_file_loads() {
+        let mut analyzer = SmwAnalyzer::new();
+        analyzer.interpret(&snapshot(0x0E, 0, 0, &[]));
+
+        // Going through file select swaps the whole event table
+        analyzer.interpret(&snapshot(0x07, 0, 0, &[]));
+        let update = analyzer.interpret(&snapshot(0x0E, 0, 1, &[0x80]));
+        assert!(update.new_exits.is_empty());
+
+        // Many events at once while in game is also treated as a load
+        let update = analyzer.interpret(&snapshot(0x0E, 0, 1, &[0xFF, 0xFF]));
+        assert!(update.new_exits.is_empty());
+    }
+
+    #[test]
+    fn test_counts_attempts_deaths_and_retries() {
+        let mut analyzer = SmwAnalyzer::new();
+        analyzer.interpret(&snapshot(0x0E, 0x05, 0, &[]));
+
+        let update = analyzer.interpret(&in_level(0x05, 0));
+        assert_eq!(update.level_events, vec![LevelEvent::Attempt { level_id: 0x05, retry: false }]);
+
+        // A pipe transition goes through the loading modes without starting a new attempt
+        analyzer.interpret(&snapshot(0x11, 0x05, 0, &[]));
+        assert!(analyzer.interpret(&in_level(0x05, 0)).level_events.is_empty());
+
+        // The death animation lasts several polls but is only counted once
+        let update = analyzer.interpret(&in_level(0x05, 0x09));
+        assert_eq!(update.level_events, vec![LevelEvent::Death { level_id: 0x05 }]);
+        assert!(analyzer.interpret(&in_level(0x05, 0x09)).level_events.is_empty());
+
+        // Respawning in the same level is a retry
+        analyzer.interpret(&snapshot(0x11, 0x05, 0, &[]));
+        let update = analyzer.interpret(&in_level(0x05, 0));
+        assert_eq!(
+            update.level_events,
+            vec![
+                LevelEvent::Exit { level_id: 0x05, outcome: AttemptOutcome::Death },
+                LevelEvent::Attempt { level_id: 0x05, retry: true },
+            ]
+        );
+
+        // Leaving to the overworld without dying and coming back is a fresh attempt
+        let update = analyzer.interpret(&snapshot(0x0E, 0x05, 0, &[]));
+        assert_eq!(update.level_events, vec![LevelEvent::Exit { level_id: 0x05, outcome: AttemptOutcome::Exit }]);
+        let update = analyzer.interpret(&in_level(0x05, 0));
+        assert_eq!(update.level_events, vec![LevelEvent::Attempt { level_id: 0x05, retry: false }]);
+
+        // Back on the overworld with a normal exit type: beaten again, even without a new event
+        let update = analyzer.interpret(&snapshot(0x0E, 0x05, 1, &[]));
+        assert_eq!(update.level_events, vec![LevelEvent::Exit { level_id: 0x05, outcome: AttemptOutcome::Clear }]);
+    }
+
+    #[test]
+    fn test_ending_reached_from_gameplay() {
+        let mut analyzer = SmwAnalyzer::new();
+
+        // Attaching while the credits already roll doesn't count
+        assert_eq!(analyzer.interpret(&snapshot(0x1C, 0, 0, &[0xFF])).ending, None);
+
+        let update = analyzer.interpret(&SmwSnapshot { save_slot: 2, ..snapshot(0x0E, 0, 0, &[0xFF, 0x0F]) });
+        assert_eq!(update.save_slot, Some(2));
+        let update = analyzer.interpret(&snapshot(0x1C, 0, 0, &[0xFF, 0x0F]));
+        assert_eq!(update.ending, Some(EndingReached { events_triggered: 12 }));
+        assert_eq!(analyzer.interpret(&snapshot(0x1D, 0, 0, &[0xFF, 0x0F])).ending, None);
+
+        // Reaching the credits phase without any progress isn't an ending
+        let mut analyzer = SmwAnalyzer::new();
+        analyzer.interpret(&snapshot(0x0E, 0, 0, &[]));
+        assert_eq!(analyzer.interpret(&snapshot(0x1C, 0, 0, &[])).ending, None);
+
+        // A map whose default phase swallows unknown modes needs its final event set
+        let map = MemoryMap { ending_events: vec![8], ..MemoryMap::vanilla() };
+        let mut analyzer = SmwAnalyzer::with_map(map);
+        analyzer.interpret(&snapshot(0x0E, 0, 0, &[0xFF]));
+        assert_eq!(analyzer.interpret(&snapshot(0x30, 0, 0, &[0xFF])).ending, None);
+        analyzer.interpret(&snapshot(0x0E, 0, 0, &[0xFF, 0x80]));
+        let update = analyzer.interpret(&snapshot(0x30, 0, 0, &[0xFF, 0x80]));
+        assert_eq!(update.ending, Some(EndingReached { events_triggered: 9 }));
+    }
+
+    #[test]
+    fn test_pause_and_non_gameplay_phases_are_inactive() {
+        let mut analyzer = SmwAnalyzer::new();
+
+        assert!(analyzer.interpret(&in_level(0x05, 0)).is_active);
+        let paused = SmwSnapshot { pause_flag: 1, ..in_level(0x05, 0) };
+        let update = analyzer.interpret(&paused);
+        assert!(update.paused);
+        assert!(!update.is_active);
+
+        for (game_mode, phase) in [
+            (0x07, GamePhase::Menu),
+            (0x16, GamePhase::GameOver),
+            (0x19, GamePhase::Cutscene),
+            (0x26, GamePhase::Credits),
+        ] {
+            let update = analyzer.interpret(&snapshot(game_mode, 0, 0, &[]));
+            assert_eq!(update.phase, phase);
+            assert!(!update.is_active);
+        }
+        assert!(analyzer.interpret(&snapshot(0x11, 0x05, 0, &[])).is_active);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::tracking::usb2snes::Usb2SnesClient;
+
+pub type SourceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// Something `TrackingService` can read game RAM from.
+///
+/// Addresses use the usb2snes layout (WRAM at `$F50000`, ROM from `$000000`),
+/// and each implementation translates them as needed.
+pub trait MemorySource: Send {
+    /// Human readable description of what we're attached to.
+    fn description(&self) -> String;
+
+    /// Reads several address/size pairs; the result maps each requested address to its bytes.
+    fn read_multi<'a>(&'a mut self, reads: &'a [(u32, u32)]) -> SourceFuture<'a, HashMap<u32, Vec<u8>>>;
+
+    fn write_memory<'a>(&'a mut self, address: u32, data: &'a [u8]) -> SourceFuture<'a, ()>;
+
+    /// Whether the ROM header can be read for checksum based hack detection.
+    fn can_read_rom(&self) -> bool;
+
+    /// Whether RAM writes (practice toolkit) are supported.
+    fn can_write(&self) -> bool;
+
+    /// Name or path of the loaded ROM, used for hack detection when the ROM can't be read.
+    fn rom_name(&mut self) -> SourceFuture<'_, Option<String>>;
+
+    /// Checks the connection is still alive between polls.
+    fn keepalive(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// The underlying usb2snes client, for operations only usb2snes offers (file transfers, booting).
+    fn as_usb2snes(&mut self) -> Option<&mut Usb2SnesClient> {
+        None
+    }
+
+    /// Whether this plays back a recording; the tracker then persists nothing.
+    fn is_replay(&self) -> bool {
+        false
+    }
+
+    /// For replays, when the last read's data was originally polled (relative to the
+    /// recording's start). The tracker times polls by it instead of the wall clock.
+    fn recorded_time(&self) -> Option<Duration> {
+        None
+    }
+
+    /// How long the tracker should wait before the next poll, when the source sets the pace.
+    fn poll_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    fn read_memory(&mut self, address: u32, size: u32) -> SourceFuture<'_, Vec<u8>> {
+        Box::pin(async move {
+            let reads = [(address, size)];
+            let mut data = self.read_multi(&reads).await?;
+            data.remove(&address).ok_or_else(|| format!("No data returned for {:06X}", address))
+        })
+    }
+}
+
+impl MemorySource for Usb2SnesClient {
+    fn description(&self) -> String {
+        format!("usb2snes: {}", self.device_name.as_deref().unwrap_or("unknown device"))
+    }
+
+    fn read_multi<'a>(&'a mut self, reads: &'a [(u32, u32)]) -> SourceFuture<'a, HashMap<u32, Vec<u8>>> {
+        Box::pin(async move { Ok(Usb2SnesClient::read_multi(self, reads).await?) })
+    }
+
+    fn write_memory<'a>(&'a mut self, address: u32, data: &'a [u8]) -> SourceFuture<'a, ()> {
+        Box::pin(async move { Ok(Usb2SnesClient::write_memory(self, address, data).await?) })
+    }
+
+    fn can_read_rom(&self) -> bool {
+        !self.has_flag("NO_ROM_READ")
+    }
+
+    fn can_write(&self) -> bool {
+        // WRAM writes on the FXPak go through the command space
+        !self.has_flag("NO_CONTROL_CMD")
+    }
+
+    fn rom_name(&mut self) -> SourceFuture<'_, Option<String>> {
+        // info[2] is usually the ROM name/path
+        Box::pin(async move { Ok(self.info().await?.get(2).cloned()) })
+    }
+
+    fn keepalive(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async move { Ok(Usb2SnesClient::keepalive(self).await?) })
+    }
+
+    fn as_usb2snes(&mut self) -> Option<&mut Usb2SnesClient> {
+        Some(self)
+    }
+}
@@ -0,0 +1,154 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// SRAM in usb2snes addressing; save files start at its beginning.
+pub const ADDR_SRAM: u32 = 0xE00000;
+/// Bytes covering all three save files and their backup copies.
+pub const SAVE_DATA_SIZE: u32 = BACKUP_OFFSET as u32 * 2;
+
+pub const SLOT_COUNT: usize = 3;
+/// A save file is a copy of RAM $1EA2-$1F2E followed by a 16-bit checksum.
+const SLOT_DATA_SIZE: usize = 0x8D;
+const SLOT_SIZE: usize = SLOT_DATA_SIZE + 2;
+/// The game keeps a second copy of every save file after the first three.
+const BACKUP_OFFSET: usize = SLOT_SIZE * SLOT_COUNT;
+/// All data bytes plus the checksum word add up to this.
+const CHECKSUM_TARGET: u16 = 0x5A5A;
+
+/// Offsets within a save file.
+const LEVEL_FLAGS: usize = 0x00;
+const LEVEL_COUNT: usize = 0x60;
+const EVENT_FLAGS: usize = 0x60;
+const EVENT_FLAGS_SIZE: usize = 15;
+const SWITCH_PALACES: usize = 0x85;
+const EXIT_COUNT: usize = 0x8C;
+
+/// Bit 7 of a level's overworld flags is set once it has been beaten.
+const LEVEL_BEATEN: u8 = 0x80;
+
+/// Progress stored in one save file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SaveSlot {
+    /// 0-2, shown in game as files A-C.
+    pub slot: u8,
+    /// The exit counter shown on the file select screen.
+    pub exit_count: u8,
+    /// Overworld events triggered; every exit beaten triggers one.
+    pub events_triggered: u32,
+    pub levels_beaten: u32,
+    /// Green, yellow, blue and red switch palaces.
+    pub switch_palaces: [bool; 4],
+    pub events: Vec<u8>,
+    /// The primary copy failed its checksum and the backup copy was used.
+    pub from_backup: bool,
+}
+
+/// Parses SMW's save data. Slots that were never saved (or are corrupt in both
+/// copies) are `None`.
+pub fn parse_sram(data: &[u8]) -> Result<[Option<SaveSlot>; SLOT_COUNT], String> {
+    if data.len() < SAVE_DATA_SIZE as usize {
+        return Err(format!(
+            "Save data is {} bytes, expected at least {}",
+            data.len(),
+            SAVE_DATA_SIZE
+        ));
+    }
+
+    let mut slots: [Option<SaveSlot>; SLOT_COUNT] = Default::default();
+    for (index, slot) in slots.iter_mut().enumerate() {
+        let primary = &data[index * SLOT_SIZE..][..SLOT_SIZE];
+        let backup = &data[BACKUP_OFFSET + index * SLOT_SIZE..][..SLOT_SIZE];
+        *slot = if checksum_valid(primary) {
+            Some(parse_slot(index as u8, primary, false))
+        } else if checksum_valid(backup) {
+            Some(parse_slot(index as u8, backup, true))
+        } else {
+            None
+        };
+    }
+    Ok(slots)
+}
+
+/// Whether a save file's data bytes and checksum add up to the expected value.
+pub fn checksum_valid(slot: &[u8]) -> bool {
+    let (data, checksum) = slot.split_at(SLOT_DATA_SIZE);
+    let sum = data.iter().fold(0u16, |sum, &b| sum.wrapping_add(b as u16));
+    sum.wrapping_add(u16::from_le_bytes([checksum[0], checksum[1]])) == CHECKSUM_TARGET
+}
+
+fn parse_slot(slot: u8, data: &[u8], from_backup: bool) -> SaveSlot {
+    let events = data[EVENT_FLAGS..][..EVENT_FLAGS_SIZE].to_vec();
+    let mut switch_palaces = [false; 4];
+    for (i, pressed) in switch_palaces.iter_mut().enumerate() {
+        *pressed = data[SWITCH_PALACES + i] != 0;
+    }
+    SaveSlot {
+        slot,
+        exit_count: data[EXIT_COUNT],
+        events_triggered: events.iter().map(|b| b.count_ones()).sum(),
+        levels_beaten: data[LEVEL_FLAGS..][..LEVEL_COUNT].iter().filter(|&&f| f & LEVEL_BEATEN != 0).count() as u32,
+        switch_palaces,
+        events,
+        from_backup,
+    }
+}
+
+/// `Hack.sfc` -> `Hack.srm`, where emulators keep battery saves by default.
+pub fn srm_path(rom_path: impl AsRef<Path>) -> PathBuf {
+    rom_path.as_ref().with_extension("srm")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a save file with a correct checksum into `sram`.
+    fn write_slot(sram: &mut [u8], offset: usize, events: &[u8], exit_count: u8) {
+        let slot = &mut sram[offset..offset + SLOT_SIZE];
+        slot[EVENT_FLAGS..EVENT_FLAGS + events.len()].copy_from_slice(events);
+        slot[LEVEL_FLAGS + 0x05] = LEVEL_BEATEN | 0x03;
+        slot[SWITCH_PALACES + 1] = 1;
+        slot[EXIT_COUNT] = exit_count;
+        let sum = slot[..SLOT_DATA_SIZE].iter().fold(0u16, |sum, &b| sum.wrapping_add(b as u16));
+        let checksum = CHECKSUM_TARGET.wrapping_sub(sum);
+        slot[SLOT_DATA_SIZE..].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn test_parses_valid_slots_and_skips_empty_ones() {
+        let mut sram = vec![0u8; 0x800];
+        write_slot(&mut sram, 0, &[0xFF, 0x01], 9);
+
+        let slots = parse_sram(&sram).unwrap();
+        let first = slots[0].as_ref().unwrap();
+        assert_eq!(first.exit_count, 9);
+        assert_eq!(first.events_triggered, 9);
+        assert_eq!(first.levels_beaten, 1);
+        assert_eq!(first.switch_palaces, [false, true, false, false]);
+        assert!(!first.from_backup);
+        // Never saved: all zero, which fails the checksum
+        assert!(slots[1].is_none());
+        assert!(slots[2].is_none());
+    }
+
+    #[test]
+    fn test_falls_back_to_backup_copy() {
+        let mut sram = vec![0u8; 0x800];
+        write_slot(&mut sram, 2 * SLOT_SIZE, &[0x0F], 4);
+        write_slot(&mut sram, BACKUP_OFFSET + 2 * SLOT_SIZE, &[0x07], 3);
+        // Corrupt the primary copy
+        sram[2 * SLOT_SIZE + EXIT_COUNT] ^= 0xFF;
+
+        let slot = parse_sram(&sram).unwrap()[2].clone().unwrap();
+        assert!(slot.from_backup);
+        assert_eq!(slot.exit_count, 3);
+        assert_eq!(slot.slot, 2);
+    }
+
+    #[test]
+    fn test_rejects_short_data() {
+        assert!(parse_sram(&[0u8; 0x100]).is_err());
+        assert_eq!(srm_path("/roms/Hack.sfc"), PathBuf::from("/roms/Hack.srm"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+/// NTSC SNES frame rate.
+const FRAMES_PER_SECOND: f64 = 60.0988;
+/// Gaps longer than this are treated as a stall (suspended emulator, lost connection) and not counted.
+const MAX_POLL_GAP: Duration = Duration::from_secs(30);
+
+/// Measures time between polls from SMW's 8-bit frame counter ($13).
+///
+/// The counter wraps every 256 frames (about 4.3 seconds), so the wall clock is
+/// used to pick how many wraps happened between two samples. When no frame
+/// counter is available, or it disagrees badly with the wall clock (a reset or
+/// savestate load), the monotonic wall clock is used instead. Lag frames do
+/// not advance $13, so they are left out the same way in-game timers do.
+#[derive(Debug, Default)]
+pub struct FrameTimer {
+    last: Option<(Option<u8>, Instant)>,
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Forgets the previous sample, e.g. after a disconnect or when tracking is paused.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Records a sample and returns the milliseconds elapsed since the previous one.
+    pub fn advance(&mut self, frame_counter: Option<u8>, now: Instant) -> u64 {
+        let previous = self.last.replace((frame_counter, now));
+        let Some((last_frame, last_time)) = previous else {
+            return 0;
+        };
+
+        let wall = now.saturating_duration_since(last_time);
+        if wall > MAX_POLL_GAP {
+            return 0;
+        }
+        let wall_ms = wall.as_millis() as u64;
+
+        match (last_frame, frame_counter) {
+            (Some(old), Some(new)) => {
+                let frames = resolve_frames(new.wrapping_sub(old), wall_ms);
+                let frame_ms = frames_to_ms(frames);
+                // More than half a wrap off means the counter was reset or a savestate was loaded
+                if frame_ms.abs_diff(wall_ms) > frames_to_ms(128) {
+                    wall_ms
+                } else {
+                    frame_ms
+                }
+            }
+            _ => wall_ms,
+        }
+    }
+}
+
+/// Default for `Config::idle_timeout_seconds`.
+pub const DEFAULT_IDLE_TIMEOUT_SECONDS: u32 = 300;
+
+/// Flags the player as idle once the watched RAM (controller and player position)
+/// hasn't changed for the configured timeout.
+///
+/// Idleness is only known once the timeout has passed, by which time the start of
+/// the stretch was already counted as play. The tracker reports what it counted with
+/// `counted`, and `take_idle_counted` hands that back once the stretch turns idle so
+/// it can be taken off the totals again.
+#[derive(Debug, Default)]
+pub struct IdleDetector {
+    last_activity: Option<Vec<u8>>,
+    unchanged_ms: u64,
+    idle: bool,
+    /// Play time counted since the watched RAM last changed.
+    counted_ms: u64,
+    /// Counted time of a stretch that turned idle, waiting to be taken back.
+    idle_counted_ms: u64,
+}
+
+impl IdleDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the latest activity bytes and returns whether the player is idle.
+    /// A timeout of 0 disables idle detection.
+    pub fn update(&mut self, activity: &[u8], elapsed_ms: u64, timeout_seconds: u32) -> bool {
+        if self.last_activity.as_deref() == Some(activity) {
+            self.unchanged_ms += elapsed_ms;
+        } else {
+            self.last_activity = Some(activity.to_vec());
+            self.unchanged_ms = 0;
+            self.counted_ms = 0;
+        }
+
+        let idle = timeout_seconds > 0 && self.unchanged_ms >= timeout_seconds as u64 * 1000;
+        if idle && !self.idle {
+            self.idle_counted_ms += std::mem::take(&mut self.counted_ms);
+        }
+        self.idle = idle;
+        idle
+    }
+
+    /// Notes play time counted for the latest poll.
+    pub fn counted(&mut self, ms: u64) {
+        // The poll where the RAM changed still had the player active
+        if self.unchanged_ms > 0 {
+            self.counted_ms += ms;
+        }
+    }
+
+    /// Play time that was counted during a stretch that has since turned idle.
+    pub fn take_idle_counted(&mut self) -> u64 {
+        std::mem::take(&mut self.idle_counted_ms)
+    }
+}
+
+/// Picks the total frame count `delta + 256 * n` closest to the wall clock estimate.
+pub fn resolve_frames(delta: u8, wall_ms: u64) -> u64 {
+    let expected = (wall_ms as f64 * FRAMES_PER_SECOND / 1000.0).round() as u64;
+    let delta = delta as u64;
+    if expected <= delta {
+        return delta;
+    }
+
+    let wraps = (expected - delta + 128) / 256;
+    delta + wraps * 256
+}
+
+pub fn frames_to_ms(frames: u64) -> u64 {
+    (frames as f64 * 1000.0 / FRAMES_PER_SECOND).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_frames_across_wraps() {
+        // One second of polling is about 60 frames
+        assert_eq!(resolve_frames(60, 1000), 60);
+        // A slow read of 5 seconds wraps the counter once
+        assert_eq!(resolve_frames(44, 5000), 300);
+        assert_eq!(resolve_frames(0, 0), 0);
+    }
+
+    #[test]
+    fn test_frame_timer_prefers_frame_counter() {
+        let start = Instant::now();
+        let mut timer = FrameTimer::new();
+        assert_eq!(timer.advance(Some(10), start), 0);
+
+        // Lag frames: only 50 game frames ran during 1.02s of wall time
+        let elapsed = timer.advance(Some(60), start + Duration::from_millis(1020));
+        assert_eq!(elapsed, frames_to_ms(50));
+
+        // Without a frame counter the wall clock is used
+        let elapsed = timer.advance(None, start + Duration::from_millis(2020));
+        assert_eq!(elapsed, 1000);
+
+        // Long stalls are not counted at all
+        let elapsed = timer.advance(Some(0), start + Duration::from_secs(120));
+        assert_eq!(elapsed, 0);
+    }
+
+    #[test]
+    fn test_idle_detector_times_out_on_unchanged_ram() {
+        let mut idle = IdleDetector::new();
+        assert!(!idle.update(&[0, 0, 1, 2], 1000, 2));
+        assert!(!idle.update(&[0, 0, 1, 2], 1000, 2));
+        assert!(idle.update(&[0, 0, 1, 2], 1000, 2));
+
+        // Any input wakes it up again
+        assert!(!idle.update(&[0x80, 0, 1, 2], 1000, 2));
+        // Disabled with a timeout of 0
+        let mut idle = IdleDetector::new();
+        assert!(!idle.update(&[0], 10_000_000, 0));
+    }
+
+    #[test]
+    fn test_idle_detector_hands_back_time_counted_before_timeout() {
+        let mut idle = IdleDetector::new();
+        // The poll with input counts as play
+        assert!(!idle.update(&[1], 1000, 3));
+        idle.counted(1000);
+        for _ in 0..2 {
+            assert!(!idle.update(&[1], 1000, 3));
+            idle.counted(1000);
+        }
+        assert_eq!(idle.take_idle_counted(), 0);
+
+        // Three seconds without change: the two seconds counted so far weren't play
+        assert!(idle.update(&[1], 1000, 3));
+        assert_eq!(idle.take_idle_counted(), 2000);
+        assert_eq!(idle.take_idle_counted(), 0);
+        assert!(idle.update(&[1], 1000, 3));
+        assert_eq!(idle.take_idle_counted(), 0);
+
+        // Input before the timeout keeps what was counted
+        assert!(!idle.update(&[2], 1000, 3));
+        assert!(!idle.update(&[2], 1000, 3));
+        idle.counted(1000);
+        assert!(!idle.update(&[3], 1000, 3));
+        assert_eq!(idle.take_idle_counted(), 0);
+    }
+}
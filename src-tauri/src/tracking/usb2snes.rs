@@ -1,15 +1,60 @@
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
 
 use tokio::net::TcpStream;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tokio_tungstenite::tungstenite::Message;
 
+/// How long a single request may wait for its reply before giving up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Idle connections are pinged after this long so dead servers are noticed.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+/// Writing a ROM to the SD card can take a while; this bounds waiting for it to finish.
+const FILE_TIMEOUT: Duration = Duration::from_secs(120);
+/// Size of the binary frames a file upload is split into.
+const PUT_FILE_CHUNK: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Usb2SnesError {
+    /// No reply arrived within the request timeout.
+    Timeout(String),
+    /// The server replied with something that doesn't match the request.
+    ProtocolError(String),
+    /// The websocket connection was lost.
+    Disconnected(String),
+    /// The server closed the connection while attached, which it does when the device goes away.
+    DeviceGone(String),
+}
+
+impl fmt::Display for Usb2SnesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Usb2SnesError::Timeout(op) => write!(f, "usb2snes {} timed out", op),
+            Usb2SnesError::ProtocolError(msg) => write!(f, "usb2snes protocol error: {}", msg),
+            Usb2SnesError::Disconnected(msg) => write!(f, "usb2snes disconnected: {}", msg),
+            Usb2SnesError::DeviceGone(device) => write!(f, "usb2snes device gone: {}", device),
+        }
+    }
+}
+
+impl std::error::Error for Usb2SnesError {}
+
+impl From<Usb2SnesError> for String {
+    fn from(e: Usb2SnesError) -> Self {
+        e.to_string()
+    }
+}
+
 #[derive(Debug)]
 pub struct Usb2SnesClient {
     stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
     pub device_name: Option<String>,
     pub flags: Vec<String>,
+    timeout: Duration,
+    last_io: Instant,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -22,6 +67,25 @@ struct Command {
     operands: Option<Vec<String>>,
 }
 
+/// The sd2snes firmware accepts at most this many address/size pairs per `GetAddress`.
+const MAX_OPERAND_PAIRS: usize = 8;
+/// Requested ranges this close together are read as one range.
+const MERGE_GAP: u32 = 16;
+
+/// A contiguous range that covers one or more requested reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ReadRange {
+    pub address: u32,
+    pub size: u32,
+}
+
+/// An entry returned by `List`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
 #[derive(Deserialize, Debug)]
 struct DeviceListResponse {
     #[serde(rename = "Results")]
@@ -29,131 +93,527 @@ struct DeviceListResponse {
 }
 
 impl Usb2SnesClient {
-    pub async fn connect(url: &str) -> Result<Self, String> {
-        let (stream, _) = connect_async(url)
+    pub async fn connect(url: &str) -> Result<Self, Usb2SnesError> {
+        let (stream, _) = tokio::time::timeout(DEFAULT_TIMEOUT, connect_async(url))
             .await
-            .map_err(|e| format!("WebSocket connection failed: {}", e))?;
+            .map_err(|_| Usb2SnesError::Timeout("Connect".to_string()))?
+            .map_err(|e| Usb2SnesError::Disconnected(format!("WebSocket connection failed: {}", e)))?;
 
         Ok(Self {
             stream,
             device_name: None,
             flags: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            last_io: Instant::now(),
         })
     }
 
-    pub async fn list_devices(&mut self) -> Result<Vec<String>, String> {
-        let cmd = Command {
-            opcode: "DeviceList".to_string(),
-            space: "SNES".to_string(),
-            operands: None,
-        };
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub async fn list_devices(&mut self) -> Result<Vec<String>, Usb2SnesError> {
+        self.send_command("DeviceList", None).await?;
+        self.recv_results("DeviceList").await
+    }
+
+    pub async fn attach(&mut self, device: &str) -> Result<(), Usb2SnesError> {
+        self.send_command("Attach", Some(vec![device.to_string()])).await?;
+        self.device_name = Some(device.to_string());
+        Ok(())
+    }
 
-        let json = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
-        self.send_text(json).await?;
+    pub async fn register_app(&mut self, app_name: &str) -> Result<(), Usb2SnesError> {
+        self.send_command("Name", Some(vec![app_name.to_string()])).await
+    }
+
+    pub async fn info(&mut self) -> Result<Vec<String>, Usb2SnesError> {
+        self.send_command("Info", None).await?;
+        let results = self.recv_results("Info").await?;
+        // Store flags (indices 3 onwards usually, but let's just store all results)
+        if results.len() > 3 {
+            self.flags = results[3..].to_vec();
+        }
+        Ok(results)
+    }
+
+    /// Lists a folder on the SD card.
+    pub async fn list(&mut self, path: &str) -> Result<Vec<RemoteEntry>, Usb2SnesError> {
+        self.send_command("List", Some(vec![path.to_string()])).await?;
+        let results = self.recv_results("List").await?;
+        // Results alternate between a type ("0" = folder, "1" = file) and a name
+        Ok(results
+            .chunks(2)
+            .filter(|pair| pair.len() == 2 && pair[1] != "." && pair[1] != "..")
+            .map(|pair| RemoteEntry { name: pair[1].clone(), is_dir: pair[0] == "0" })
+            .collect())
+    }
+
+    pub async fn make_dir(&mut self, path: &str) -> Result<(), Usb2SnesError> {
+        self.send_command("MakeDir", Some(vec![path.to_string()])).await
+    }
+
+    pub async fn remove(&mut self, path: &str) -> Result<(), Usb2SnesError> {
+        self.send_command("Remove", Some(vec![path.to_string()])).await
+    }
+
+    /// Uploads a file to the SD card and waits until the device has finished writing it.
+    pub async fn put_file(&mut self, path: &str, data: &[u8]) -> Result<(), Usb2SnesError> {
+        self.send_command("PutFile", Some(vec![path.to_string(), format!("{:X}", data.len())])).await?;
+        for chunk in data.chunks(PUT_FILE_CHUNK) {
+            self.send(Message::Binary(chunk.to_vec().into())).await?;
+        }
 
-        // Wait for response
-        if let Some(msg) = self.stream.next().await {
-            let msg = msg.map_err(|e| e.to_string())?;
-            if let Message::Text(text) = msg {
-                let response: DeviceListResponse = serde_json::from_str(&text)
-                    .map_err(|e| format!("Failed to parse device list: {}", e))?;
-                return Ok(response.results);
+        // PutFile has no reply; the next request is only answered once the write is done
+        let timeout = self.timeout;
+        self.timeout = FILE_TIMEOUT;
+        let result = self.info().await;
+        self.timeout = timeout;
+        result.map(|_| ())
+    }
+
+    /// Downloads a file from the SD card. The reply gives the size, then the data follows in binary frames.
+    pub async fn get_file(&mut self, path: &str) -> Result<Vec<u8>, Usb2SnesError> {
+        self.send_command("GetFile", Some(vec![path.to_string()])).await?;
+        let results = self.recv_results("GetFile").await?;
+        let size = results
+            .first()
+            .and_then(|size| usize::from_str_radix(size, 16).ok())
+            .ok_or_else(|| Usb2SnesError::ProtocolError(format!("Invalid GetFile reply: {:?}", results)))?;
+
+        let mut data = Vec::with_capacity(size);
+        let deadline = Instant::now() + FILE_TIMEOUT;
+        while data.len() < size {
+            match self.next_message("GetFile", deadline).await? {
+                Message::Binary(chunk) => data.extend_from_slice(&chunk),
+                Message::Text(text) => {
+                    return Err(Usb2SnesError::ProtocolError(format!("Unexpected text reply to GetFile: {}", text)));
+                }
+                _ => {}
             }
         }
 
-        Err("No response received for DeviceList".to_string())
+        if data.len() != size {
+            return Err(Usb2SnesError::ProtocolError(format!(
+                "GetFile returned {} bytes, expected {}",
+                data.len(),
+                size
+            )));
+        }
+        Ok(data)
     }
 
-    pub async fn attach(&mut self, device: &str) -> Result<(), String> {
-        let cmd = Command {
-            opcode: "Attach".to_string(),
-            space: "SNES".to_string(),
-            operands: Some(vec![device.to_string()]),
-        };
+    pub async fn boot(&mut self, path: &str) -> Result<(), Usb2SnesError> {
+        self.send_command("Boot", Some(vec![path.to_string()])).await
+    }
 
-        let json = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
-        self.send_text(json).await?;
+    pub async fn reset(&mut self) -> Result<(), Usb2SnesError> {
+        self.send_command("Reset", None).await
+    }
 
-        self.device_name = Some(device.to_string());
-        Ok(())
+    pub async fn menu(&mut self) -> Result<(), Usb2SnesError> {
+        self.send_command("Menu", None).await
     }
 
-    pub async fn register_app(&mut self, app_name: &str) -> Result<(), String> {
-        let cmd = Command {
-            opcode: "Name".to_string(),
-            space: "SNES".to_string(),
-            operands: Some(vec![app_name.to_string()]),
-        };
+    /// Whether `info()` reported the given capability flag, e.g. `NO_CONTROL_CMD`.
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
 
-        let json = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
-        self.send_text(json).await?;
-        Ok(())
+    /// Pings the server if the connection has been quiet for a while and waits for the pong.
+    pub async fn keepalive(&mut self) -> Result<(), Usb2SnesError> {
+        if self.last_io.elapsed() < KEEPALIVE_INTERVAL {
+            return Ok(());
+        }
+        self.ping().await
     }
 
-    pub async fn info(&mut self) -> Result<Vec<String>, String> {
+    pub async fn ping(&mut self) -> Result<(), Usb2SnesError> {
+        self.send(Message::Ping(Vec::new().into())).await?;
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Message::Pong(_) = self.next_message("Ping", deadline).await? {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn send_command(&mut self, opcode: &str, operands: Option<Vec<String>>) -> Result<(), Usb2SnesError> {
         let cmd = Command {
-            opcode: "Info".to_string(),
+            opcode: opcode.to_string(),
             space: "SNES".to_string(),
-            operands: None,
+            operands,
         };
+        let json = serde_json::to_string(&cmd).map_err(|e| Usb2SnesError::ProtocolError(e.to_string()))?;
+        self.send(Message::Text(json.into())).await
+    }
+
+    async fn send(&mut self, message: Message) -> Result<(), Usb2SnesError> {
+        tokio::time::timeout(self.timeout, self.stream.send(message))
+            .await
+            .map_err(|_| Usb2SnesError::Timeout("send".to_string()))?
+            .map_err(|e| Usb2SnesError::Disconnected(e.to_string()))?;
+        self.last_io = Instant::now();
+        Ok(())
+    }
 
-        let json = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
-        self.send_text(json).await?;
-
-        // Wait for response
-        if let Some(msg) = self.stream.next().await {
-            let msg = msg.map_err(|e| e.to_string())?;
-            if let Message::Text(text) = msg {
-                let response: DeviceListResponse = serde_json::from_str(&text)
-                    .map_err(|e| format!("Failed to parse info: {}", e))?;
-                // Store flags (indices 3 onwards usually, but let's just store all results)
-                if response.results.len() > 3 {
-                    self.flags = response.results[3..].to_vec();
+    /// Waits for the next text reply and parses its `Results` list.
+    async fn recv_results(&mut self, opcode: &str) -> Result<Vec<String>, Usb2SnesError> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            match self.next_message(opcode, deadline).await? {
+                Message::Text(text) => {
+                    let response: DeviceListResponse = serde_json::from_str(&text).map_err(|e| {
+                        Usb2SnesError::ProtocolError(format!("Failed to parse {} reply: {}", opcode, e))
+                    })?;
+                    return Ok(response.results);
                 }
-                return Ok(response.results);
+                Message::Binary(data) => {
+                    return Err(Usb2SnesError::ProtocolError(format!(
+                        "Unexpected {} byte binary reply to {}",
+                        data.len(),
+                        opcode
+                    )));
+                }
+                _ => {}
             }
         }
-        Err("No response received for Info".to_string())
     }
 
-    async fn send_text(&mut self, text: String) -> Result<(), String> {
-        // eprintln!("DEBUG: Sending: {}", text);
-        self.stream
-            .send(Message::Text(text.into()))
+    /// Returns the next message before `deadline`, mapping closes and transport errors.
+    async fn next_message(&mut self, opcode: &str, deadline: Instant) -> Result<Message, Usb2SnesError> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let next = tokio::time::timeout(remaining, self.stream.next())
             .await
-            .map_err(|e| e.to_string())
+            .map_err(|_| Usb2SnesError::Timeout(opcode.to_string()))?;
+
+        match next {
+            Some(Ok(Message::Close(frame))) => {
+                let reason = frame.map(|f| f.reason.to_string()).unwrap_or_default();
+                Err(match &self.device_name {
+                    Some(device) => Usb2SnesError::DeviceGone(device.clone()),
+                    None => Usb2SnesError::Disconnected(format!("server closed the connection {}", reason).trim().to_string()),
+                })
+            }
+            Some(Ok(msg)) => {
+                self.last_io = Instant::now();
+                Ok(msg)
+            }
+            Some(Err(e)) => Err(Usb2SnesError::Disconnected(e.to_string())),
+            None => Err(Usb2SnesError::Disconnected("connection closed".to_string())),
+        }
     }
 
-    pub async fn read_memory(&mut self, address: u32, size: u32) -> Result<Vec<u8>, String> {
-        let cmd = Command {
-            opcode: "GetAddress".to_string(),
-            space: "SNES".to_string(),
-            operands: Some(vec![
-                format!("{:06X}", address),
-                format!("{:X}", size),
-            ]),
-        };
+    pub async fn read_memory(&mut self, address: u32, size: u32) -> Result<Vec<u8>, Usb2SnesError> {
+        self.get_address(&[ReadRange { address, size }]).await
+    }
 
-        let json = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
-        self.send_text(json).await?;
+    /// Reads several address/size pairs in as few `GetAddress` round-trips as possible.
+    /// Nearby ranges are merged, and the result maps each requested address to its bytes.
+    pub async fn read_multi(&mut self, reads: &[(u32, u32)]) -> Result<HashMap<u32, Vec<u8>>, Usb2SnesError> {
+        let ranges = plan_ranges(reads);
+        let mut data = Vec::with_capacity(ranges.iter().map(|r| r.size as usize).sum());
+        for chunk in ranges.chunks(MAX_OPERAND_PAIRS) {
+            data.extend(self.get_address(chunk).await?);
+        }
+        split_ranges(reads, &ranges, &data).map_err(Usb2SnesError::ProtocolError)
+    }
 
-        while let Some(msg) = self.stream.next().await {
-            let msg = msg.map_err(|e| e.to_string())?;
-            match msg {
-                Message::Binary(data) => {
-                    if data.len() as u32 != size {
-                        return Ok(data.to_vec());
-                    }
-                    return Ok(data.to_vec());
-                }
-                Message::Text(_text) => {
-                    // eprintln!("DEBUG: Received Text in read_memory: {}", text);
-                    // Could be an error or info?
+    /// Writes `data` at `address`. usb2snes sends no reply to `PutAddress`.
+    pub async fn write_memory(&mut self, address: u32, data: &[u8]) -> Result<(), Usb2SnesError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.send_command(
+            "PutAddress",
+            Some(vec![format!("{:06X}", address), format!("{:X}", data.len())]),
+        ).await?;
+        self.send(Message::Binary(data.to_vec().into())).await
+    }
+
+    /// Sends one `GetAddress` and collects the binary reply, which the server may
+    /// split across several frames, until exactly the requested length has arrived.
+    async fn get_address(&mut self, ranges: &[ReadRange]) -> Result<Vec<u8>, Usb2SnesError> {
+        let operands = ranges
+            .iter()
+            .flat_map(|r| [format!("{:06X}", r.address), format!("{:X}", r.size)])
+            .collect();
+        self.send_command("GetAddress", Some(operands)).await?;
+
+        let expected: usize = ranges.iter().map(|r| r.size as usize).sum();
+        let mut data = Vec::with_capacity(expected);
+        let deadline = Instant::now() + self.timeout;
+        while data.len() < expected {
+            match self.next_message("GetAddress", deadline).await? {
+                Message::Binary(chunk) => data.extend_from_slice(&chunk),
+                Message::Text(text) => {
+                    return Err(Usb2SnesError::ProtocolError(format!("Unexpected text reply to GetAddress: {}", text)));
                 }
                 _ => {}
             }
         }
 
-        Err("No binary data received".to_string())
+        if data.len() != expected {
+            return Err(Usb2SnesError::ProtocolError(format!(
+                "GetAddress returned {} bytes, expected {}",
+                data.len(),
+                expected
+            )));
+        }
+        Ok(data)
+    }
+}
+
+/// Endpoints tried when none are configured: current servers first, then legacy QUsb2snes.
+pub const DEFAULT_ENDPOINTS: [&str; 2] = ["ws://127.0.0.1:23074", "ws://127.0.0.1:8080"];
+
+/// Parses a comma or newline separated endpoint list, falling back to `DEFAULT_ENDPOINTS`.
+pub fn parse_endpoints(configured: Option<&str>) -> Vec<String> {
+    let endpoints: Vec<String> = configured
+        .unwrap_or_default()
+        .split([',', '\n'])
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .map(|e| if e.contains("://") { e.to_string() } else { format!("ws://{}", e) })
+        .collect();
+
+    if endpoints.is_empty() {
+        DEFAULT_ENDPOINTS.iter().map(|e| e.to_string()).collect()
+    } else {
+        endpoints
+    }
+}
+
+/// Picks the first device whose name contains `pattern` (case-insensitive), or the first
+/// device when no preference is set. A preference that matches nothing selects nothing,
+/// so tracking never silently attaches to the wrong console.
+pub fn select_device<'a>(devices: &'a [String], pattern: Option<&str>) -> Option<&'a String> {
+    match pattern.map(|p| p.trim().to_lowercase()).filter(|p| !p.is_empty()) {
+        Some(pattern) => devices.iter().find(|d| d.to_lowercase().contains(&pattern)),
+        None => devices.first(),
+    }
+}
+
+/// Sorts the requested reads and merges overlapping or nearby ones into contiguous ranges.
+pub(crate) fn plan_ranges(reads: &[(u32, u32)]) -> Vec<ReadRange> {
+    let mut sorted: Vec<(u32, u32)> = reads.iter().copied().filter(|(_, size)| *size > 0).collect();
+    sorted.sort();
+
+    let mut ranges: Vec<ReadRange> = Vec::new();
+    for (address, size) in sorted {
+        let end = address + size;
+        match ranges.last_mut() {
+            Some(last) if address <= last.address + last.size + MERGE_GAP => {
+                last.size = last.size.max(end - last.address);
+            }
+            _ => ranges.push(ReadRange { address, size }),
+        }
+    }
+    ranges
+}
+
+/// Slices the concatenated reply for `ranges` back into the individual requested reads.
+pub(crate) fn split_ranges(reads: &[(u32, u32)], ranges: &[ReadRange], data: &[u8]) -> Result<HashMap<u32, Vec<u8>>, String> {
+    let mut offsets = Vec::with_capacity(ranges.len());
+    let mut offset = 0usize;
+    for range in ranges {
+        offsets.push(offset);
+        offset += range.size as usize;
+    }
+    if data.len() != offset {
+        return Err(format!("Batched read returned {} bytes, expected {}", data.len(), offset));
+    }
+
+    let mut result = HashMap::with_capacity(reads.len());
+    for &(address, size) in reads {
+        let (range, start) = ranges
+            .iter()
+            .zip(&offsets)
+            .find(|(r, _)| address >= r.address && address + size <= r.address + r.size)
+            .ok_or_else(|| format!("No range covers read at {:06X}", address))?;
+        let begin = start + (address - range.address) as usize;
+        result.insert(address, data[begin..begin + size as usize].to_vec());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    /// Starts a one-connection websocket server that answers each text command with the
+    /// messages produced by `reply`, and returns its URL.
+    async fn mock_server<F>(reply: F) -> String
+    where
+        F: Fn(&str) -> Vec<Message> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(socket).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Text(text) = msg {
+                    for response in reply(&text) {
+                        if ws.send(response).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_read_accumulates_split_frames() {
+        let url = mock_server(|_| vec![
+            Message::Binary(vec![1u8, 2].into()),
+            Message::Binary(vec![3u8].into()),
+        ]).await;
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+
+        let data = client.read_memory(0xF50000, 3).await.unwrap();
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_read_rejects_text_and_overlong_replies() {
+        let url = mock_server(|cmd| {
+            if cmd.contains("F50010") {
+                vec![Message::Text("{\"error\":\"bad address\"}".into())]
+            } else {
+                vec![Message::Binary(vec![0u8; 4].into())]
+            }
+        }).await;
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+
+        let err = client.read_memory(0xF50010, 1).await.unwrap_err();
+        assert!(matches!(err, Usb2SnesError::ProtocolError(_)));
+        let err = client.read_memory(0xF50000, 2).await.unwrap_err();
+        assert!(matches!(err, Usb2SnesError::ProtocolError(_)));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_request_times_out() {
+        let url = mock_server(|_| Vec::new()).await;
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+        client.set_timeout(Duration::from_millis(100));
+
+        let err = client.read_memory(0xF50000, 1).await.unwrap_err();
+        assert_eq!(err, Usb2SnesError::Timeout("GetAddress".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_device_list_and_ping() {
+        let url = mock_server(|cmd| {
+            if cmd.contains("DeviceList") {
+                vec![Message::Text(r#"{"Results":["SD2SNES COM3"]}"#.into())]
+            } else {
+                Vec::new()
+            }
+        }).await;
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+
+        assert_eq!(client.list_devices().await.unwrap(), vec!["SD2SNES COM3".to_string()]);
+        client.ping().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_memory_sends_command_then_data() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(socket).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                let _ = tx.send(msg);
+            }
+        });
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+
+        client.write_memory(0xF50019, &[2]).await.unwrap();
+        match rx.recv().await.unwrap() {
+            Message::Text(text) => assert!(text.contains("PutAddress") && text.contains("F50019")),
+            other => panic!("expected command, got {:?}", other),
+        }
+        assert_eq!(rx.recv().await.unwrap(), Message::Binary(vec![2u8].into()));
+    }
+
+    #[tokio::test]
+    async fn test_list_parses_entries() {
+        let url = mock_server(|cmd| {
+            if cmd.contains("List") {
+                vec![Message::Text(r#"{"Results":["0",".","0","romhacks","1","smw.sfc"]}"#.into())]
+            } else {
+                Vec::new()
+            }
+        }).await;
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+
+        let entries = client.list("/").await.unwrap();
+        assert_eq!(entries, vec![
+            RemoteEntry { name: "romhacks".to_string(), is_dir: true },
+            RemoteEntry { name: "smw.sfc".to_string(), is_dir: false },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_close_while_attached_reports_device_gone() {
+        let url = mock_server(|cmd| {
+            if cmd.contains("GetAddress") {
+                vec![Message::Close(None)]
+            } else {
+                Vec::new()
+            }
+        }).await;
+        let mut client = Usb2SnesClient::connect(&url).await.unwrap();
+        client.attach("SD2SNES COM3").await.unwrap();
+
+        let err = client.read_memory(0xF50000, 1).await.unwrap_err();
+        assert_eq!(err, Usb2SnesError::DeviceGone("SD2SNES COM3".to_string()));
+    }
+
+    #[test]
+    fn test_parse_endpoints_and_select_device() {
+        assert_eq!(parse_endpoints(None), vec!["ws://127.0.0.1:23074", "ws://127.0.0.1:8080"]);
+        assert_eq!(
+            parse_endpoints(Some("192.168.1.20:23074,\n ws://localhost:8080 ")),
+            vec!["ws://192.168.1.20:23074", "ws://localhost:8080"]
+        );
+
+        let devices = vec!["EMUNWA snes9x".to_string(), "SD2SNES COM3".to_string()];
+        assert_eq!(select_device(&devices, None), Some(&devices[0]));
+        assert_eq!(select_device(&devices, Some("sd2snes")), Some(&devices[1]));
+        assert_eq!(select_device(&devices, Some("retroarch")), None);
+    }
+
+    #[test]
+    fn test_plan_ranges_merges_nearby_reads() {
+        let reads = [(0xF50100, 1), (0xF50013, 1), (0xF50015, 4), (0xF51F02, 15), (0xF50094, 4)];
+        let ranges = plan_ranges(&reads);
+        assert_eq!(ranges, vec![
+            ReadRange { address: 0xF50013, size: 6 },
+            ReadRange { address: 0xF50094, size: 4 },
+            ReadRange { address: 0xF50100, size: 1 },
+            ReadRange { address: 0xF51F02, size: 15 },
+        ]);
+    }
+
+    #[test]
+    fn test_split_ranges_returns_each_read() {
+        let reads = [(0x10, 2), (0x13, 1), (0x100, 1)];
+        let ranges = plan_ranges(&reads);
+        let data = [0xA0, 0xA1, 0xA2, 0xA3, 0xFF];
+
+        let result = split_ranges(&reads, &ranges, &data).unwrap();
+        assert_eq!(result[&0x10], vec![0xA0, 0xA1]);
+        assert_eq!(result[&0x13], vec![0xA3]);
+        assert_eq!(result[&0x100], vec![0xFF]);
+
+        assert!(split_ranges(&reads, &ranges, &data[..4]).is_err());
     }
 }
@@ -26,12 +26,20 @@ interface Hack {
 interface LevelTiming {
   level_id: number;
   seconds: number;
+  milliseconds: number;
+  attempts: number;
+  deaths: number;
+  retries: number;
+  clears: number;
 }
 
 interface HackStats {
   total_play_time_seconds: number;
+  total_play_time_ms: number;
   session_count: number;
   level_timings: LevelTiming[];
+  exits_found: number;
+  total_exits: number | null;
 }
 
 interface HackDetailsProps {
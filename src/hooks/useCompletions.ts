@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { useTrackingEvents } from "./useTrackingEvents";
 
 export interface HackCompletion {
   id: number;
@@ -11,6 +12,17 @@ export interface HackCompletion {
   updated_at: number;
 }
 
+/** A completion detected at the credits, waiting for the user to confirm it. */
+export interface PendingCompletion {
+  id: number;
+  hack_id: number;
+  session_id: number | null;
+  route: string;
+  completed_at: number;  // UNIX timestamp in seconds
+  play_time_seconds: number | null;
+  exits_found: number;
+}
+
 export interface CompletionSummary {
   total_completions: number;
   routes: string[];
@@ -147,3 +159,64 @@ export function useCompletionSummary(hackId: number | null) {
   };
 }
 
+export function usePendingCompletions(hackId: number | null) {
+  const [pending, setPending] = useState<PendingCompletion[]>([]);
+  const [error, setError] = useState<string | null>(null);
+
+  const loadPending = useCallback(async () => {
+    setError(null);
+    try {
+      const result = await invoke<PendingCompletion[]>("get_pending_completions", {
+        hackId: hackId,
+      });
+      setPending(result);
+    } catch (e: any) {
+      setError(e?.message || "Failed to load pending completions");
+      console.error("Failed to load pending completions:", e);
+    }
+  }, [hackId]);
+
+  useEffect(() => {
+    loadPending();
+  }, [loadPending]);
+
+  useTrackingEvents((event) => {
+    if (event.type === "completion_detected" && (!hackId || event.hack_id === hackId)) {
+      loadPending();
+    }
+  });
+
+  const confirmCompletion = useCallback(async (id: number, route?: string) => {
+    try {
+      const result = await invoke<HackCompletion>("confirm_pending_completion", {
+        id,
+        route: route ?? null,
+      });
+      await loadPending();
+      return result;
+    } catch (e: any) {
+      const errorMsg = e?.message || "Failed to confirm completion";
+      console.error("Failed to confirm completion:", e);
+      throw new Error(errorMsg);
+    }
+  }, [loadPending]);
+
+  const dismissCompletion = useCallback(async (id: number) => {
+    try {
+      await invoke("dismiss_pending_completion", { id });
+      await loadPending();
+    } catch (e: any) {
+      const errorMsg = e?.message || "Failed to dismiss completion";
+      console.error("Failed to dismiss completion:", e);
+      throw new Error(errorMsg);
+    }
+  }, [loadPending]);
+
+  return {
+    pending,
+    error,
+    confirmCompletion,
+    dismissCompletion,
+    refresh: loadPending,
+  };
+}
@@ -18,6 +18,7 @@ interface HackFilters {
 
 export function useHacks(filters: HackFilters, enabled: boolean = true) {
   const [hacks, setHacks] = useState<any[]>([]);
+  const [total, setTotal] = useState(0);
   const [loading, setLoading] = useState(false);
 
   useEffect(() => {
@@ -64,8 +65,12 @@ export function useHacks(filters: HackFilters, enabled: boolean = true) {
           author: filters.author || undefined,
           min_rating: filters.minRating ? parseFloat(filters.minRating) : undefined,
         }
-      }) as any[];
-      setHacks(result);
+      }) as { items: any[]; total: number | null };
+      setHacks(result.items);
+      // Only the first page carries the total
+      if (result.total !== null) {
+        setTotal(result.total);
+      }
     } catch (e) {
       console.error("Failed to load hacks:", e);
     } finally {
@@ -73,6 +78,6 @@ export function useHacks(filters: HackFilters, enabled: boolean = true) {
     }
   }
 
-  return { hacks, loading, loadHacks };
+  return { hacks, total, loading, loadHacks };
 }
 
@@ -0,0 +1,87 @@
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+export interface SaveBackup {
+  id: number;
+  hack_id: number;
+  created_at: string;
+  reason: "session_end" | "manual" | "delete" | "repatch" | "restore";
+  size: number;
+  md5: string;
+}
+
+export function useSaveBackups(hackId: number | null) {
+  const [backups, setBackups] = useState<SaveBackup[]>([]);
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const loadBackups = useCallback(async () => {
+    if (!hackId) {
+      setBackups([]);
+      return;
+    }
+
+    setLoading(true);
+    setError(null);
+    try {
+      const result = await invoke<SaveBackup[]>("get_save_backups", {
+        hackId: hackId,
+      });
+      setBackups(result);
+    } catch (e: any) {
+      setError(e?.message || "Failed to load save backups");
+      console.error("Failed to load save backups:", e);
+    } finally {
+      setLoading(false);
+    }
+  }, [hackId]);
+
+  useEffect(() => {
+    loadBackups();
+  }, [loadBackups]);
+
+  const createBackup = useCallback(async () => {
+    try {
+      const result = await invoke<SaveBackup | null>("create_save_backup", {
+        hackId: hackId,
+      });
+      await loadBackups();
+      return result;
+    } catch (e: any) {
+      const errorMsg = e?.message || "Failed to back up save";
+      console.error("Failed to back up save:", e);
+      throw new Error(errorMsg);
+    }
+  }, [hackId, loadBackups]);
+
+  const restoreBackup = useCallback(async (backupId: number) => {
+    try {
+      await invoke("restore_save_backup", { backupId });
+      await loadBackups();
+    } catch (e: any) {
+      const errorMsg = e?.message || "Failed to restore save";
+      console.error("Failed to restore save:", e);
+      throw new Error(errorMsg);
+    }
+  }, [loadBackups]);
+
+  const exportBackup = useCallback(async (backupId: number, destination: string) => {
+    try {
+      await invoke("export_save_backup", { backupId, destination });
+    } catch (e: any) {
+      const errorMsg = e?.message || "Failed to export save";
+      console.error("Failed to export save:", e);
+      throw new Error(errorMsg);
+    }
+  }, []);
+
+  return {
+    backups,
+    loading,
+    error,
+    createBackup,
+    restoreBackup,
+    exportBackup,
+    refresh: loadBackups,
+  };
+}
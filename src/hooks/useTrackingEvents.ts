@@ -0,0 +1,67 @@
+import { useEffect, useRef } from "react";
+import { listen } from "@tauri-apps/api/event";
+
+export type SessionEndReason =
+  | "emulator_exited"
+  | "rom_changed"
+  | "disconnected"
+  | "hack_changed";
+
+/** Payload of the "tracking-event" event emitted by the tracking service. */
+export type TrackingEvent =
+  | { type: "connected"; source: string }
+  | { type: "disconnected" }
+  | { type: "hack_detected"; hack_id: number }
+  | { type: "session_started"; session_id: number; hack_id: number }
+  | { type: "session_ended"; session_id: number; reason: SessionEndReason }
+  | { type: "level_entered"; hack_id: number | null; level_id: number; retry: boolean }
+  | {
+      type: "level_exited";
+      hack_id: number | null;
+      level_id: number;
+      outcome: "clear" | "death" | "exit";
+    }
+  | { type: "death"; hack_id: number | null; level_id: number }
+  | {
+      type: "exit_cleared";
+      hack_id: number | null;
+      level_id: number;
+      event_id: number;
+      exit_type: "normal" | "secret";
+    }
+  | { type: "completion_detected"; hack_id: number; pending_id: number; route: string };
+
+export function useTrackingEvents(onEvent: (event: TrackingEvent) => void) {
+  // Keep the latest callback without re-subscribing on every render
+  const onEventRef = useRef(onEvent);
+  onEventRef.current = onEvent;
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let cancelled = false;
+
+    async function setupListener() {
+      try {
+        const stop = await listen<TrackingEvent>("tracking-event", (event) => {
+          onEventRef.current(event.payload);
+        });
+        if (cancelled) {
+          stop();
+        } else {
+          unlisten = stop;
+        }
+      } catch (error) {
+        console.error("Failed to set up tracking event listener:", error);
+      }
+    }
+
+    setupListener();
+
+    return () => {
+      cancelled = true;
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+}